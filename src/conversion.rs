@@ -1,148 +1,1104 @@
 use crate::{
+    error::{HandleGraphError, HandleGraphResult},
     handle::{Edge, Handle, NodeId},
-    handlegraph::HandleGraphRef,
+    handlegraph::*,
     mutablehandlegraph::*,
     pathgraph::PathHandleGraph,
+    pathhandlegraph::PanSN,
 };
 
 use gfa::{
     gfa::{Line, Link, Orientation, Path, Segment, GFA},
-    optfields::OptFields,
-    parser::GFAResult,
+    optfields::{OptField, OptFieldVal, OptFields, OptionalFields},
+    parser::{GFAResult, ParseError},
 };
 
 use bstr::BString;
+use fnv::FnvHashSet;
 
+use std::hash::Hash;
+
+mod metadata;
+mod walk;
+mod writer;
+
+pub use self::metadata::*;
+pub use self::walk::*;
+pub use self::writer::*;
+
+/// The optional field tag used to mark circular paths in GFA P-lines,
+/// written as `TP:Z:circular`.
+pub const PATH_TOPOLOGY_TAG: &[u8; 2] = b"TP";
+
+const CIRCULAR_TOPOLOGY: &[u8] = b"circular";
+
+fn has_circular_tag<T: OptFields>(optional: &T) -> bool {
+    match optional.get_field(PATH_TOPOLOGY_TAG) {
+        Some(OptField {
+            value: OptFieldVal::Z(topology),
+            ..
+        }) => topology == CIRCULAR_TOPOLOGY,
+        _ => false,
+    }
+}
+
+/// Returns `true` if the GFA path is tagged as circular.
+pub fn is_circular_path<N, T: OptFields>(path: &Path<N, T>) -> bool {
+    has_circular_tag(&path.optional)
+}
+
+/// An error from building a graph from GFA lines, either when
+/// parsing a line, or when adding it to the graph.
+#[derive(Debug)]
+pub enum GFAConversionError {
+    Parse(ParseError),
+    Graph(HandleGraphError),
+    /// A W-line would be loaded as a path with the same name as an
+    /// existing path, e.g. another walk with the same PanSN name.
+    DuplicateWalk(BString),
+}
+
+pub type GFAConversionResult<T> = Result<T, GFAConversionError>;
+
+impl From<ParseError> for GFAConversionError {
+    fn from(err: ParseError) -> Self {
+        GFAConversionError::Parse(err)
+    }
+}
+
+impl From<HandleGraphError> for GFAConversionError {
+    fn from(err: HandleGraphError) -> Self {
+        GFAConversionError::Graph(err)
+    }
+}
+
+impl From<std::io::Error> for GFAConversionError {
+    fn from(err: std::io::Error) -> Self {
+        GFAConversionError::Parse(ParseError::IOError(err))
+    }
+}
+
+impl std::fmt::Display for GFAConversionError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            GFAConversionError::Parse(err) => write!(f, "{}", err),
+            GFAConversionError::Graph(err) => write!(f, "{}", err),
+            GFAConversionError::DuplicateWalk(name) => {
+                write!(f, "Duplicate walk path name {}", name)
+            }
+        }
+    }
+}
+
+impl std::error::Error for GFAConversionError {}
+
+fn add_gfa_segment<G, T>(
+    graph: &mut G,
+    segment: &Segment<usize, T>,
+) -> HandleGraphResult<()>
+where
+    G: AdditiveHandleGraph,
+    T: OptFields,
+{
+    graph.try_create_handle(&segment.sequence, NodeId::from(segment.name))?;
+    Ok(())
+}
+
+fn add_gfa_link<G, T>(
+    graph: &mut G,
+    link: &Link<usize, T>,
+) -> HandleGraphResult<()>
+where
+    G: AdditiveHandleGraph,
+    T: OptFields,
+{
+    let left = Handle::new(link.from_segment, link.from_orient);
+    let right = Handle::new(link.to_segment, link.to_orient);
+    graph.try_create_edge(Edge(left, right))
+}
+
+/// Create a path with the given steps, after making sure that all
+/// the nodes they visit exist.
+fn add_path_steps<G>(
+    graph: &mut G,
+    name: &[u8],
+    circular: bool,
+    steps: &[Handle],
+) -> HandleGraphResult<G::PathHandle>
+where
+    G: PathHandleGraph,
+    for<'a> &'a G: AllHandles,
+{
+    if let Some(missing) = steps.iter().find(|h| !graph.has_node(h.id())) {
+        return Err(HandleGraphError::MissingNode(missing.id()));
+    }
+
+    let path_id = graph.create_path_handle(name, circular);
+    for &handle in steps.iter() {
+        graph.append_step(&path_id, handle);
+    }
+    Ok(path_id)
+}
+
+fn add_gfa_path<G, T>(
+    graph: &mut G,
+    path: &Path<usize, T>,
+) -> HandleGraphResult<G::PathHandle>
+where
+    G: PathHandleGraph,
+    for<'a> &'a G: AllHandles,
+    T: OptFields,
+{
+    let steps = path
+        .iter()
+        .map(|(seg, orient)| Handle::new(seg, orient))
+        .collect::<Vec<_>>();
+    add_path_steps(graph, &path.path_name, is_circular_path(path), &steps)
+}
+
+fn add_gfa_walk<G, T>(
+    graph: &mut G,
+    walk: &Walk<T>,
+) -> GFAConversionResult<G::PathHandle>
+where
+    G: PathHandleGraph,
+    for<'a> &'a G: AllHandles,
+    T: OptFields,
+{
+    let name = walk.pansn().to_name();
+    if graph.name_to_path_handle(&name).is_some() {
+        return Err(GFAConversionError::DuplicateWalk(name.into()));
+    }
+    let path_id = add_path_steps(graph, &name, false, &walk.steps)?;
+    Ok(path_id)
+}
+
+/// Build a graph from the segments, links, and paths of a GFA.
+/// Everything else in the GFA is discarded, use
+/// `from_gfa_with_metadata` to keep it.
+///
+/// The graph is created using `Default`; use `fill_gfa` to load the
+/// GFA into a graph that's set up differently, e.g. a `PackedGraph`
+/// with a `SequenceEncoding` other than the default.
+///
+/// Panics if the GFA can't be represented as a graph, use
+/// `try_from_gfa` to get an error instead.
 pub fn from_gfa<G, T>(gfa: &GFA<usize, T>) -> G
 where
     G: Default + AdditiveHandleGraph + PathHandleGraph,
+    for<'a> &'a G: AllHandles,
+    T: OptFields,
+{
+    match try_from_gfa(gfa) {
+        Ok(graph) => graph,
+        Err(err) => panic!("{}", err),
+    }
+}
+
+/// Build a graph from the segments, links, and paths of a GFA, like
+/// `from_gfa`. Fails if a segment has an invalid name or sequence,
+/// or if a link or path refers to a segment that doesn't exist.
+pub fn try_from_gfa<G, T>(gfa: &GFA<usize, T>) -> HandleGraphResult<G>
+where
+    G: Default + AdditiveHandleGraph + PathHandleGraph,
+    for<'a> &'a G: AllHandles,
+    T: OptFields,
+{
+    let mut graph: G = Default::default();
+    fill_gfa(&mut graph, gfa)?;
+    Ok(graph)
+}
+
+/// Add the segments, links, and paths of a GFA to the graph, failing
+/// in the same cases as `try_from_gfa`. The parts of the GFA before
+/// the failure are kept in the graph.
+pub fn fill_gfa<G, T>(
+    graph: &mut G,
+    gfa: &GFA<usize, T>,
+) -> HandleGraphResult<()>
+where
+    G: AdditiveHandleGraph + PathHandleGraph,
+    for<'a> &'a G: AllHandles,
+    T: OptFields,
+{
+    for segment in gfa.segments.iter() {
+        add_gfa_segment(graph, segment)?;
+    }
+
+    for link in gfa.links.iter() {
+        add_gfa_link(graph, link)?;
+    }
+
+    for path in gfa.paths.iter() {
+        add_gfa_path(graph, path)?;
+    }
+
+    Ok(())
+}
+
+/// Build a graph from a GFA, storing the header, containments,
+/// overlaps, and optional fields in a `GFAMetadata`, which can be
+/// used with `to_gfa_with_metadata` to reproduce the GFA.
+///
+/// Panics if the GFA can't be represented as a graph, use
+/// `try_from_gfa_with_metadata` to get an error instead.
+pub fn from_gfa_with_metadata<G, T>(
+    gfa: &GFA<usize, T>,
+) -> (G, GFAMetadata<G::PathHandle>)
+where
+    G: Default + AdditiveHandleGraph + PathHandleGraph,
+    G::PathHandle: Copy + Eq + Hash,
+    for<'a> &'a G: AllHandles,
+    T: OptFields,
+{
+    match try_from_gfa_with_metadata(gfa) {
+        Ok(result) => result,
+        Err(err) => panic!("{}", err),
+    }
+}
+
+/// Build a graph and its `GFAMetadata` from a GFA, like
+/// `from_gfa_with_metadata`, failing in the same cases as
+/// `try_from_gfa`.
+pub fn try_from_gfa_with_metadata<G, T>(
+    gfa: &GFA<usize, T>,
+) -> HandleGraphResult<(G, GFAMetadata<G::PathHandle>)>
+where
+    G: Default + AdditiveHandleGraph + PathHandleGraph,
+    G::PathHandle: Copy + Eq + Hash,
+    for<'a> &'a G: AllHandles,
     T: OptFields,
 {
     let mut graph: G = Default::default();
+    let mut metadata = GFAMetadata::new();
+    fill_gfa_with_metadata(&mut graph, &mut metadata, gfa)?;
+    Ok((graph, metadata))
+}
+
+/// Add a GFA to the graph, like `fill_gfa`, storing the parts of the
+/// GFA that the graph can't represent in the metadata.
+pub fn fill_gfa_with_metadata<G, T>(
+    graph: &mut G,
+    metadata: &mut GFAMetadata<G::PathHandle>,
+    gfa: &GFA<usize, T>,
+) -> HandleGraphResult<()>
+where
+    G: AdditiveHandleGraph + PathHandleGraph,
+    G::PathHandle: Copy + Eq + Hash,
+    for<'a> &'a G: AllHandles,
+    T: OptFields,
+{
+    metadata.set_header(&gfa.header);
 
     for segment in gfa.segments.iter() {
-        assert!(segment.name > 0);
-        let seq = &segment.sequence;
-        graph.create_handle(seq, segment.name);
+        add_gfa_segment(graph, segment)?;
+        metadata.add_segment(segment);
     }
 
     for link in gfa.links.iter() {
-        let left = Handle::new(link.from_segment, link.from_orient);
-        let right = Handle::new(link.from_segment, link.from_orient);
-        graph.create_edge(Edge(left, right));
+        add_gfa_link(graph, link)?;
+        metadata.add_link(link);
+    }
+
+    for containment in gfa.containments.iter() {
+        metadata.add_containment(containment);
     }
 
     for path in gfa.paths.iter() {
-        let name = &path.path_name;
-        let path_id = graph.create_path_handle(name, false);
-        for (seg, orient) in path.iter() {
-            let handle = Handle::new(seg, orient);
-            graph.append_step(&path_id, handle);
+        let path_id = add_gfa_path(graph, path)?;
+        metadata.add_path(path_id, path);
+    }
+
+    Ok(())
+}
+
+/// Build a graph from a GFA 1.1, like `from_gfa_with_metadata`, with
+/// the walks loaded as paths named using PanSN, i.e.
+/// `sample#haplotype#contig[start-end]`. The structured metadata of
+/// the walks is available from the graph through `PathMetadata`.
+///
+/// Panics if the GFA can't be represented as a graph, use
+/// `try_from_gfa_with_walks` to get an error instead.
+pub fn from_gfa_with_walks<G, T>(
+    gfa: &GFAWithWalks<T>,
+) -> (G, GFAMetadata<G::PathHandle>)
+where
+    G: Default + AdditiveHandleGraph + PathHandleGraph,
+    G::PathHandle: Copy + Eq + Hash,
+    for<'a> &'a G: AllHandles,
+    T: OptFields,
+{
+    match try_from_gfa_with_walks(gfa) {
+        Ok(result) => result,
+        Err(err) => panic!("{}", err),
+    }
+}
+
+/// Build a graph and its `GFAMetadata` from a GFA 1.1, like
+/// `from_gfa_with_walks`, failing in the same cases as
+/// `try_from_gfa`, and if two walks, or a walk and a path, end up
+/// with the same path name.
+pub fn try_from_gfa_with_walks<G, T>(
+    gfa: &GFAWithWalks<T>,
+) -> GFAConversionResult<(G, GFAMetadata<G::PathHandle>)>
+where
+    G: Default + AdditiveHandleGraph + PathHandleGraph,
+    G::PathHandle: Copy + Eq + Hash,
+    for<'a> &'a G: AllHandles,
+    T: OptFields,
+{
+    let mut graph: G = Default::default();
+    let mut metadata = GFAMetadata::new();
+    fill_gfa_with_walks(&mut graph, &mut metadata, gfa)?;
+    Ok((graph, metadata))
+}
+
+/// Add a GFA 1.1 to the graph, like `fill_gfa_with_metadata`, with
+/// the walks loaded as paths as in `from_gfa_with_walks`.
+pub fn fill_gfa_with_walks<G, T>(
+    graph: &mut G,
+    metadata: &mut GFAMetadata<G::PathHandle>,
+    gfa: &GFAWithWalks<T>,
+) -> GFAConversionResult<()>
+where
+    G: AdditiveHandleGraph + PathHandleGraph,
+    G::PathHandle: Copy + Eq + Hash,
+    for<'a> &'a G: AllHandles,
+    T: OptFields,
+{
+    fill_gfa_with_metadata(graph, metadata, &gfa.gfa)?;
+
+    for walk in gfa.walks.iter() {
+        let path_id = add_gfa_walk(graph, walk)?;
+        metadata.add_walk(path_id, walk);
+    }
+
+    Ok(())
+}
+
+/// Add the segments, links, and paths in the GFA lines to the graph.
+/// Other lines are skipped, use `fill_gfa_lines_with_metadata` to
+/// keep them.
+///
+/// Stops at the first line that can't be parsed or added to the
+/// graph; the lines before it are kept in the graph.
+pub fn fill_gfa_lines<G, I, T>(
+    graph: &mut G,
+    gfa_lines: I,
+) -> GFAConversionResult<()>
+where
+    G: AdditiveHandleGraph + PathHandleGraph,
+    for<'a> &'a G: AllHandles,
+    I: Iterator<Item = GFAResult<Line<usize, T>>>,
+    T: OptFields,
+{
+    for line in gfa_lines {
+        match line? {
+            Line::Segment(v) => add_gfa_segment(graph, &v)?,
+            Line::Link(v) => add_gfa_link(graph, &v)?,
+            Line::Path(v) => {
+                add_gfa_path(graph, &v)?;
+            }
+            _ => (),
         }
     }
 
-    graph
+    Ok(())
 }
 
-pub fn fill_gfa_lines<G, I, T>(graph: &mut G, gfa_lines: I) -> GFAResult<()>
+/// Add the GFA lines to the graph, storing the parts of the lines
+/// that the graph can't represent in the metadata.
+///
+/// Stops at the first line that can't be parsed or added to the
+/// graph, like `fill_gfa_lines`.
+pub fn fill_gfa_lines_with_metadata<G, I, T>(
+    graph: &mut G,
+    metadata: &mut GFAMetadata<G::PathHandle>,
+    gfa_lines: I,
+) -> GFAConversionResult<()>
 where
     G: AdditiveHandleGraph + PathHandleGraph,
+    G::PathHandle: Copy + Eq + Hash,
+    for<'a> &'a G: AllHandles,
     I: Iterator<Item = GFAResult<Line<usize, T>>>,
     T: OptFields,
 {
     for line in gfa_lines {
-        let line = line?;
-        match line {
+        match line? {
+            Line::Header(v) => metadata.set_header(&v),
             Line::Segment(v) => {
-                let id = NodeId::from(v.name);
-                graph.create_handle(&v.sequence, id);
+                add_gfa_segment(graph, &v)?;
+                metadata.add_segment(&v);
             }
             Line::Link(v) => {
-                let left = Handle::new(v.from_segment, v.from_orient);
-                let right = Handle::new(v.to_segment, v.to_orient);
-                graph.create_edge(Edge(left, right));
+                add_gfa_link(graph, &v)?;
+                metadata.add_link(&v);
             }
+            Line::Containment(v) => metadata.add_containment(&v),
             Line::Path(v) => {
-                let name = &v.path_name;
-                let path_id = graph.create_path_handle(name, false);
-                for (seg, orient) in v.iter() {
-                    let handle = Handle::new(seg, orient);
-                    graph.append_step(&path_id, handle);
-                }
+                let path_id = add_gfa_path(graph, &v)?;
+                metadata.add_path(path_id, &v);
             }
-            _ => (),
         }
     }
 
     Ok(())
 }
 
-pub fn to_gfa<G>(graph: &G) -> GFA<usize, ()>
+fn orient(rev: bool) -> Orientation {
+    if rev {
+        Orientation::Backward
+    } else {
+        Orientation::Forward
+    }
+}
+
+/// Write the graph as a GFA, with `0M` link overlaps and no optional
+/// fields other than the tag marking circular paths.
+pub fn to_gfa<G>(graph: &G) -> GFA<usize, OptionalFields>
+where
+    G: PathHandleGraph,
+    G::PathHandle: Copy + Eq + Hash,
+    for<'a> &'a G: HandleGraphRef,
+{
+    to_gfa_with_metadata(graph, &GFAMetadata::new())
+}
+
+/// Write the graph as a GFA, using the header, containments,
+/// overlaps, and optional fields in the metadata.
+///
+/// Segments, links, and paths that have metadata are written first,
+/// in the order they were added to the metadata, followed by those
+/// that were added to the graph later. Metadata for nodes, edges, and
+/// paths that are no longer in the graph is ignored.
+///
+/// All paths are written as P-lines, including those that were
+/// loaded from W-lines; use `to_gfa_with_walks` to write GFA 1.1.
+pub fn to_gfa_with_metadata<G>(
+    graph: &G,
+    metadata: &GFAMetadata<G::PathHandle>,
+) -> GFA<usize, OptionalFields>
+where
+    G: PathHandleGraph,
+    G::PathHandle: Copy + Eq + Hash,
+    for<'a> &'a G: HandleGraphRef,
+{
+    let mut gfa = to_gfa_without_paths(graph, metadata);
+
+    let paths = ordered_paths(graph, metadata.path_order(), |path_id| {
+        metadata.path(path_id).is_none()
+    });
+
+    gfa.paths = paths
+        .into_iter()
+        .map(|path_id| gfa_path_line(graph, metadata, path_id))
+        .collect();
+
+    gfa
+}
+
+/// Write the graph as a GFA 1.1, like `to_gfa_with_metadata`, but
+/// with the paths that were loaded from W-lines written as walks.
+///
+/// Paths that were added to the graph after the metadata was
+/// created are written as walks if they have PanSN names, and as
+/// P-lines otherwise.
+pub fn to_gfa_with_walks<G>(
+    graph: &G,
+    metadata: &GFAMetadata<G::PathHandle>,
+) -> GFAWithWalks<OptionalFields>
+where
+    G: PathHandleGraph,
+    G::PathHandle: Copy + Eq + Hash,
+    for<'a> &'a G: HandleGraphRef,
+{
+    let mut gfa = to_gfa_without_paths(graph, metadata);
+
+    // Paths that have no metadata are written as walks if they have
+    // PanSN names
+    let pansn = |path_id: &G::PathHandle| {
+        if metadata.path(path_id).is_some() || metadata.walk(path_id).is_some()
+        {
+            None
+        } else {
+            PanSN::parse(graph.path_handle_to_name(path_id))
+        }
+    };
+
+    let paths = ordered_paths(graph, metadata.path_order(), |path_id| {
+        metadata.path(path_id).is_none()
+            && metadata.walk(path_id).is_none()
+            && pansn(path_id).is_none()
+    });
+
+    gfa.paths = paths
+        .into_iter()
+        .map(|path_id| gfa_path_line(graph, metadata, path_id))
+        .collect();
+
+    let walk_ids = ordered_paths(graph, metadata.walk_order(), |path_id| {
+        pansn(path_id).is_some()
+    });
+
+    let walks = walk_ids
+        .into_iter()
+        .filter_map(|path_id| {
+            let steps = graph
+                .steps_iter(&path_id)
+                .filter_map(|step| graph.handle_of_step(&step))
+                .collect();
+            match metadata.walk(&path_id) {
+                Some(walk) => Some(Walk::from_metadata(walk, steps)),
+                None => {
+                    let pansn = pansn(&path_id)?;
+                    Some(Walk::from_pansn(&pansn, steps, Default::default()))
+                }
+            }
+        })
+        .collect::<Vec<_>>();
+
+    let is_gfa_1_0 = match &gfa.header.version {
+        Some(version) => version == "1.0",
+        None => true,
+    };
+
+    if !walks.is_empty() && is_gfa_1_0 {
+        gfa.header.version = Some("1.1".into());
+    }
+
+    GFAWithWalks { gfa, walks }
+}
+
+/// Write the header, segments, links, and containments of the GFA.
+fn to_gfa_without_paths<G>(
+    graph: &G,
+    metadata: &GFAMetadata<G::PathHandle>,
+) -> GFA<usize, OptionalFields>
 where
-    G: HandleGraphRef + PathHandleGraph,
+    G: PathHandleGraph,
+    G::PathHandle: Copy + Eq + Hash,
+    for<'a> &'a G: HandleGraphRef,
 {
     let mut gfa = GFA::new();
 
-    for handle in graph.all_handles() {
-        let name = usize::from(handle.id());
-        let sequence: BString = graph.sequence_iter(handle.forward()).collect();
+    gfa.header = metadata.header.clone();
+
+    let new_nodes = graph
+        .all_handles()
+        .map(|h| h.id())
+        .filter(|&id| metadata.segment(id).is_none());
+
+    let node_ids = metadata
+        .segment_order()
+        .iter()
+        .copied()
+        .filter(|&id| graph.has_node(id))
+        .chain(new_nodes)
+        .collect::<Vec<_>>();
+
+    for id in node_ids {
+        let handle = Handle::pack(id, false);
+        let sequence: BString = graph.sequence_iter(handle).collect();
+        let optional = metadata.segment(id).cloned().unwrap_or_default();
 
         let segment = Segment {
-            name,
+            name: usize::from(id),
             sequence,
-            optional: (),
+            optional,
         };
         gfa.segments.push(segment);
     }
 
-    let orient = |rev: bool| {
-        if rev {
-            Orientation::Backward
-        } else {
-            Orientation::Forward
-        }
-    };
+    let edges = graph
+        .all_edges()
+        .map(|Edge(l, r)| Edge::edge_handle(l, r))
+        .collect::<FnvHashSet<_>>();
+
+    let new_edges = graph
+        .all_edges()
+        .filter(|&edge| metadata.link(edge).is_none())
+        .map(|edge| (edge, BString::from("0M"), Vec::new()));
+
+    let links = metadata
+        .link_order()
+        .iter()
+        .filter(|edge| edges.contains(edge))
+        .filter_map(|&edge| metadata.link(edge))
+        .map(|link| (link.edge, link.overlap.clone(), link.optional.clone()))
+        .chain(new_edges)
+        .collect::<Vec<_>>();
 
-    for edge in graph.all_edges() {
+    for (edge, overlap, optional) in links {
         let Edge(left, right) = edge;
-        let from_segment: usize = usize::from(left.id());
-        let from_orient = orient(left.is_reverse());
-        let to_segment: usize = usize::from(right.id());
-        let to_orient = orient(right.is_reverse());
-        let overlap = BString::from("0M");
 
         let link = Link {
-            from_segment,
-            from_orient,
-            to_segment,
-            to_orient,
+            from_segment: usize::from(left.id()),
+            from_orient: orient(left.is_reverse()),
+            to_segment: usize::from(right.id()),
+            to_orient: orient(right.is_reverse()),
             overlap,
-            optional: (),
+            optional,
         };
 
         gfa.links.push(link);
     }
 
-    for path_id in graph.paths_iter() {
-        let path_name: BString = graph.path_handle_to_name(path_id).into();
-        let overlaps = Vec::new();
-        let mut segment_names: Vec<Vec<u8>> = Vec::new();
-        for step in graph.steps_iter(path_id) {
-            let handle = graph.handle_of_step(&step).unwrap();
-            let segment: usize = handle.id().into();
-            let orientation = orient(handle.is_reverse());
-            segment_names.push(segment.to_string().into());
-            segment_names.push(orientation.to_string().into());
+    gfa.containments = metadata.containments().to_vec();
+
+    gfa
+}
+
+/// The paths in `order` that are still in the graph, followed by the
+/// paths in the graph for which `is_new` returns true.
+fn ordered_paths<G, F>(
+    graph: &G,
+    order: &[G::PathHandle],
+    is_new: F,
+) -> Vec<G::PathHandle>
+where
+    G: PathHandleGraph,
+    G::PathHandle: Copy + Eq + Hash,
+    F: Fn(&G::PathHandle) -> bool,
+{
+    let path_ids = graph.paths_iter().copied().collect::<FnvHashSet<_>>();
+
+    let new_paths = graph.paths_iter().copied().filter(|p| is_new(p));
+
+    order
+        .iter()
+        .copied()
+        .filter(|path_id| path_ids.contains(path_id))
+        .chain(new_paths)
+        .collect()
+}
+
+fn gfa_path_line<G>(
+    graph: &G,
+    metadata: &GFAMetadata<G::PathHandle>,
+    path_id: G::PathHandle,
+) -> Path<usize, OptionalFields>
+where
+    G: PathHandleGraph,
+    G::PathHandle: Copy + Eq + Hash,
+{
+    let path_name: BString = graph.path_handle_to_name(&path_id).into();
+    let mut segment_names: Vec<Vec<u8>> = Vec::new();
+    let mut step_count = 0;
+    for step in graph.steps_iter(&path_id) {
+        let handle = graph.handle_of_step(&step).unwrap();
+        let segment: usize = handle.id().into();
+        let orientation = orient(handle.is_reverse());
+        if !segment_names.is_empty() {
             segment_names.push(",".into());
         }
-        let segment_names: BString =
-            segment_names.into_iter().flatten().collect();
+        segment_names.push(segment.to_string().into());
+        segment_names.push(orientation.to_string().into());
+        step_count += 1;
+    }
+    let segment_names: BString = segment_names.into_iter().flatten().collect();
+
+    let path_meta = metadata.path(&path_id);
+
+    // The overlaps are only kept if they still fit the path
+    let overlaps = path_meta
+        .map(|p| p.overlaps.clone())
+        .filter(|o| {
+            o == &[None] || o.len() == step_count || o.len() + 1 == step_count
+        })
+        .unwrap_or_default();
+
+    let mut optional =
+        path_meta.map(|p| p.optional.clone()).unwrap_or_default();
+
+    let circular = graph.is_circular(&path_id);
+    if circular != has_circular_tag(&optional) {
+        optional.retain(|field| &field.tag != PATH_TOPOLOGY_TAG);
+        if circular {
+            optional.push(OptField::new(
+                PATH_TOPOLOGY_TAG,
+                OptFieldVal::Z(CIRCULAR_TOPOLOGY.into()),
+            ));
+        }
+    }
+
+    Path::new(path_name, segment_names, overlaps, optional)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
 
-        let path: Path<usize, ()> =
-            Path::new(path_name, segment_names, overlaps, ());
+    use crate::{hashgraph::HashGraph, packedgraph::PackedGraph};
 
-        gfa.paths.push(path);
+    use gfa::parser::GFAParser;
+
+    const GFA_LINES: &[&str] = &[
+        "H\tVN:Z:1.0\tXX:i:3",
+        "S\t1\tACGT\tLN:i:4\tSN:Z:chr1\tSO:i:0\tSR:i:0",
+        "S\t3\tTTAC",
+        "S\t2\tGG\tRC:i:12",
+        "S\t4\tCA",
+        "L\t1\t+\t2\t+\t2M\tRC:i:5",
+        "L\t2\t+\t4\t+\t*",
+        "L\t1\t+\t3\t+\t1M",
+        "L\t3\t+\t4\t+\t0M",
+        "C\t1\t+\t4\t+\t2\t2M\tID:Z:c1",
+        "P\tp2\t1+,3+,4+\t1M,0M\tXY:Z:abc",
+        "P\tp1\t1+,2+,4+\t2M,*\tTP:Z:circular",
+    ];
+
+    fn parse_gfa() -> GFA<usize, OptionalFields> {
+        let parser = GFAParser::new();
+        parser.parse_lines(GFA_LINES.iter()).unwrap()
     }
 
-    gfa
+    #[test]
+    fn gfa_metadata_roundtrip() {
+        let gfa = parse_gfa();
+        assert_eq!(gfa.containments.len(), 1);
+
+        let (graph, metadata) = from_gfa_with_metadata::<HashGraph, _>(&gfa);
+        assert_eq!(to_gfa_with_metadata(&graph, &metadata), gfa);
+
+        let (graph, metadata) = from_gfa_with_metadata::<PackedGraph, _>(&gfa);
+        assert_eq!(to_gfa_with_metadata(&graph, &metadata), gfa);
+
+        let p1 = graph.name_to_path_handle(b"p1").unwrap();
+        assert!(graph.is_circular(&p1));
+
+        let link =
+            metadata.link(Edge(Handle::pack(2, true), Handle::pack(1, true)));
+        assert_eq!(link.map(|l| l.overlap.as_slice()), Some(&b"2M"[..]));
+    }
+
+    #[test]
+    fn gfa_path_order_without_metadata() {
+        let mut lines = vec!["S\t1\tACGT".to_string()];
+        lines.extend((0..20).rev().map(|i| format!("P\tpath{}\t1+\t*", i)));
+
+        let parser = GFAParser::new();
+        let gfa: GFA<usize, OptionalFields> =
+            parser.parse_lines(lines.iter()).unwrap();
+
+        let mut graph: PackedGraph = from_gfa(&gfa);
+        let names = |gfa: &GFA<usize, OptionalFields>| {
+            gfa.paths
+                .iter()
+                .map(|p| p.path_name.to_vec())
+                .collect::<Vec<_>>()
+        };
+
+        // Paths are written in the order they were loaded, also after
+        // some have been removed
+        assert_eq!(names(&to_gfa(&graph)), names(&gfa));
+
+        let path = graph.name_to_path_handle(b"path7").unwrap();
+        crate::pathhandlegraph::MutEmbeddedPaths::remove_path(&mut graph, path);
+        let mut expected = names(&gfa);
+        expected.retain(|name| name != b"path7");
+        assert_eq!(names(&to_gfa(&graph)), expected);
+    }
+
+    #[test]
+    fn gfa_lines_metadata_roundtrip() {
+        let gfa = parse_gfa();
+
+        let parser: GFAParser<usize, OptionalFields> = GFAParser::new();
+        let lines = GFA_LINES
+            .iter()
+            .map(|l| parser.parse_gfa_line(l.as_bytes()));
+
+        let mut graph = PackedGraph::default();
+        let mut metadata = GFAMetadata::new();
+        fill_gfa_lines_with_metadata(&mut graph, &mut metadata, lines).unwrap();
+
+        assert_eq!(to_gfa_with_metadata(&graph, &metadata), gfa);
+    }
+
+    #[test]
+    fn gfa_metadata_modified_graph() {
+        let gfa = parse_gfa();
+        let (mut graph, metadata) =
+            from_gfa_with_metadata::<PackedGraph, _>(&gfa);
+
+        let h5 = graph.create_handle(b"AAA", 5u64);
+        graph.create_edge(Edge(Handle::pack(4, false), h5));
+        let p2 = graph.name_to_path_handle(b"p2").unwrap();
+        graph.append_step(&p2, h5);
+
+        let out = to_gfa_with_metadata(&graph, &metadata);
+
+        assert_eq!(out.header, gfa.header);
+        assert_eq!(out.segments[..4], gfa.segments[..]);
+        assert_eq!(out.segments[4].name, 5);
+        assert!(out.segments[4].optional.is_empty());
+
+        assert_eq!(out.links[..4], gfa.links[..]);
+        assert_eq!(out.links[4].overlap, "0M");
+
+        // p2 has a new step, so its overlaps no longer apply
+        assert_eq!(out.paths[0].path_name, "p2");
+        assert!(out.paths[0].overlaps.is_empty());
+        assert_eq!(out.paths[0].optional, gfa.paths[0].optional);
+        assert_eq!(out.paths[1], gfa.paths[1]);
+
+        // Without metadata nothing but the graph is written
+        let out = to_gfa(&graph);
+        assert!(out.containments.is_empty());
+        assert!(out.segments.iter().all(|s| s.optional.is_empty()));
+        assert!(out.links.iter().all(|l| l.overlap == "0M"));
+    }
+
+    const WALK_LINES: &[&str] = &[
+        "H\tVN:Z:1.1",
+        "S\t1\tACGT",
+        "S\t2\tGG",
+        "S\t3\tTTAC",
+        "L\t1\t+\t2\t+\t0M",
+        "L\t2\t+\t3\t+\t0M",
+        "L\t1\t+\t3\t-\t0M",
+        "P\tref#0#chr1\t1+,2+,3+\t*",
+        "W\tHG002\t1\tchr1\t0\t10\t>1>2>3\tXY:Z:abc",
+        "W\tHG002\t2\tchr1\t*\t*\t>1<3",
+        "W\tHG003\t1\tchr1\t100\t104\t<3",
+    ];
+
+    #[test]
+    fn gfa_walk_lines() {
+        let gfa: GFAWithWalks<OptionalFields> =
+            parse_gfa_with_walks(WALK_LINES.iter()).unwrap();
+
+        assert_eq!(gfa.gfa.paths.len(), 1);
+        assert_eq!(gfa.walks.len(), 3);
+
+        let walk = &gfa.walks[1];
+        assert_eq!(walk.sample_id, "HG002");
+        assert_eq!(walk.hap_index, 2);
+        assert_eq!(walk.seq_start, None);
+        assert_eq!(
+            walk.steps,
+            vec![Handle::pack(1, false), Handle::pack(3, true)]
+        );
+
+        for (walk, line) in gfa.walks.iter().zip(WALK_LINES[8..].iter()) {
+            assert_eq!(&walk.to_string(), line);
+        }
+
+        let invalid = |line: &str| {
+            Walk::<OptionalFields>::parse_line(line.as_bytes()).is_err()
+        };
+        assert!(invalid("W\tHG002\tx\tchr1\t0\t10\t>1"));
+        assert!(invalid("W\tHG002\t1\tchr1\t0\t10\t1+"));
+        assert!(invalid("W\tHG002\t1\tchr1\t0\t10"));
+    }
+
+    #[test]
+    fn gfa_walks_roundtrip() {
+        use crate::pathhandlegraph::{MutEmbeddedPaths, PathMetadata};
+
+        let gfa: GFAWithWalks<OptionalFields> =
+            parse_gfa_with_walks(WALK_LINES.iter()).unwrap();
+
+        let (graph, metadata) = from_gfa_with_walks::<PackedGraph, _>(&gfa);
+        assert_eq!(graph.path_count(), 4);
+        assert_eq!(to_gfa_with_walks(&graph, &metadata), gfa);
+
+        let (hash_graph, hash_metadata) =
+            from_gfa_with_walks::<HashGraph, _>(&gfa);
+        assert_eq!(to_gfa_with_walks(&hash_graph, &hash_metadata), gfa);
+
+        let path = graph.name_to_path_handle(b"HG002#1#chr1[0-10]").unwrap();
+        let pansn = graph.get_path_metadata(path).unwrap();
+        assert_eq!(pansn.sample, b"HG002");
+        assert_eq!(pansn.haplotype, 1);
+        assert_eq!(pansn.contig, b"chr1");
+        assert_eq!(pansn.subrange, Some((0, 10)));
+        assert_eq!(hash_graph.get_path_metadata(path), Some(pansn));
+
+        // The P-line has a PanSN name, but it's not a walk
+        let ref_path = graph.name_to_path_handle(b"ref#0#chr1").unwrap();
+        assert!(graph.get_path_metadata(ref_path).is_some());
+        assert!(metadata.walk(&ref_path).is_none());
+
+        let samples = graph.paths_by_sample();
+        assert_eq!(samples.len(), 3);
+        assert_eq!(samples[&b"HG002".to_vec()].len(), 2);
+        assert_eq!(samples[&b"HG003".to_vec()].len(), 1);
+        assert_eq!(samples, hash_graph.paths_by_sample());
+
+        // Without metadata, all PanSN paths are written as walks
+        let out = to_gfa_with_walks(&graph, &GFAMetadata::new());
+        assert!(out.gfa.paths.is_empty());
+        assert_eq!(out.walks.len(), 4);
+        assert_eq!(out.gfa.header.version, Some("1.1".into()));
+
+        // Removed paths are no longer reported, and the remaining
+        // paths keep their metadata when the IDs are compacted
+        let mut graph = graph;
+        graph.remove_path(path);
+        assert_eq!(graph.get_path_metadata(path), None);
+        assert_eq!(graph.paths_with_metadata().count(), 3);
+        let hg003 = samples[&b"HG003".to_vec()][0];
+        let id_map = graph.compact_paths().unwrap();
+        let pansn = graph.get_path_metadata(id_map[&hg003]).unwrap();
+        assert_eq!(pansn.sample, b"HG003");
+        assert!(graph.paths_with_metadata().all(|id| {
+            let name = graph.path_handle_to_name(&id).to_vec();
+            graph.get_path_metadata(id).map(|pansn| pansn.to_name())
+                == Some(name)
+        }));
+    }
+
+    #[test]
+    fn gfa_walks_metadata() {
+        let lines = [
+            "S\t1\tACGT",
+            "S\t2\tGG",
+            "W\tHG002\t1\tchr1\t5\t*\t>1>2",
+            "W\tHG002\t2\tchr1\t*\t9\t>2",
+        ];
+        let gfa: GFAWithWalks<OptionalFields> =
+            parse_gfa_with_walks(lines.iter()).unwrap();
+
+        // Walks with only one coordinate keep it when written back
+        let (graph, metadata) = from_gfa_with_walks::<HashGraph, _>(&gfa);
+        assert_eq!(to_gfa_with_walks(&graph, &metadata).walks, gfa.walks);
+
+        let path = graph.name_to_path_handle(b"HG002#1#chr1").unwrap();
+        let walk = metadata.walk(&path).unwrap();
+        assert_eq!((walk.seq_start, walk.seq_end), (Some(5), None));
+
+        // Walks that map to the same path name are rejected
+        let duplicate = |lines: &[&str]| {
+            let gfa: GFAWithWalks<OptionalFields> =
+                parse_gfa_with_walks(lines.iter()).unwrap();
+            match try_from_gfa_with_walks::<PackedGraph, _>(&gfa) {
+                Err(GFAConversionError::DuplicateWalk(name)) => Some(name),
+                _ => None,
+            }
+        };
+
+        let mut dup_walks = lines.to_vec();
+        dup_walks.push("W\tHG002\t1\tchr1\t5\t7\t>1");
+        dup_walks.push("W\tHG002\t1\tchr1\t5\t7\t>2");
+        assert_eq!(duplicate(&dup_walks), Some("HG002#1#chr1[5-7]".into()));
+
+        let mut dup_path = lines.to_vec();
+        dup_path.push("P\tHG002#2#chr1\t1+\t*");
+        assert_eq!(duplicate(&dup_path), Some("HG002#2#chr1".into()));
+
+        assert_eq!(duplicate(&lines), None);
+    }
+
+    #[test]
+    fn streaming_gfa_writer() {
+        use bstr::ByteSlice;
+
+        let mut graph: PackedGraph = from_gfa(&parse_gfa());
+        let circular = graph.create_path_handle(b"circ", true);
+        graph.append_step(&circular, Handle::pack(2, false));
+        graph.append_step(&circular, Handle::pack(4, false));
+
+        let expected = to_gfa(&graph);
+
+        let options = [
+            GFAWriterOptions::default(),
+            GFAWriterOptions {
+                segment_order: SegmentOrder::HandleOrder,
+                path_chunk_size: Some(1),
+            },
+        ];
+
+        for &opts in options.iter() {
+            let mut out: Vec<u8> = Vec::new();
+            write_gfa_with_options(&graph, opts, &mut out).unwrap();
+
+            let written: GFA<usize, OptionalFields> =
+                GFAParser::new().parse_lines(out.lines()).unwrap();
+
+            let mut segments = written.segments.clone();
+            segments.sort_by_key(|s| s.name);
+            assert_eq!(segments, expected.segments);
+            if opts.segment_order == SegmentOrder::ById {
+                assert_eq!(written.segments, expected.segments);
+            }
+
+            assert_eq!(written.links, expected.links);
+
+            assert_eq!(written.paths.len(), expected.paths.len());
+            for (w, e) in written.paths.iter().zip(expected.paths.iter()) {
+                assert_eq!(w.path_name, e.path_name);
+                assert_eq!(w.segment_names, e.segment_names);
+                assert_eq!(w.optional, e.optional);
+                assert_eq!(is_circular_path(w), is_circular_path(e));
+            }
+        }
+
+        let hash_graph: HashGraph = from_gfa(&parse_gfa());
+        let mut out: Vec<u8> = Vec::new();
+        write_gfa(&hash_graph, &mut out).unwrap();
+        let written: GFA<usize, OptionalFields> =
+            GFAParser::new().parse_lines(out.lines()).unwrap();
+        let mut expected = to_gfa(&hash_graph);
+        expected.segments.sort_by_key(|s| s.name);
+        assert_eq!(written.segments, expected.segments);
+        assert_eq!(written.links.len(), expected.links.len());
+        assert_eq!(written.paths.len(), 2);
+    }
+
+    #[test]
+    fn gfa_conversion_errors() {
+        let gfa_error = |lines: &[&str]| -> Option<HandleGraphError> {
+            let gfa: GFA<usize, OptionalFields> =
+                GFAParser::new().parse_lines(lines.iter()).unwrap();
+            let packed = try_from_gfa::<PackedGraph, _>(&gfa).err();
+            let hash = try_from_gfa::<HashGraph, _>(&gfa).err();
+            assert_eq!(packed, hash);
+            packed
+        };
+
+        let id = |x: u64| NodeId::from(x);
+
+        assert_eq!(gfa_error(GFA_LINES), None);
+        assert_eq!(
+            gfa_error(&["S\t1\tACGT", "S\t1\tGG"]),
+            Some(HandleGraphError::DuplicateNode(id(1)))
+        );
+        assert_eq!(
+            gfa_error(&["S\t0\tACGT"]),
+            Some(HandleGraphError::NullNodeId)
+        );
+        assert_eq!(
+            gfa_error(&["S\t1\tACGT", "L\t1\t+\t2\t+\t0M"]),
+            Some(HandleGraphError::MissingNode(id(2)))
+        );
+        assert_eq!(
+            gfa_error(&["S\t1\tACGT", "P\tp1\t1+,2+\t*"]),
+            Some(HandleGraphError::MissingNode(id(2)))
+        );
+
+        // Errors from fill_gfa_lines keep the lines that were added
+        // before the error
+        let parser: GFAParser<usize, ()> = GFAParser::new();
+        let lines = ["S\t1\tACGT", "L\t1\t+\t3\t+\t0M", "S\t2\tGG"];
+        let mut graph = PackedGraph::new();
+        let result = fill_gfa_lines(
+            &mut graph,
+            lines.iter().map(|l| parser.parse_gfa_line(l.as_bytes())),
+        );
+        match result {
+            Err(GFAConversionError::Graph(err)) => {
+                assert_eq!(err, HandleGraphError::MissingNode(id(3)))
+            }
+            _ => panic!("expected a graph error"),
+        }
+        assert_eq!(graph.node_count(), 1);
+
+        let lines = ["S\t1\tACGT", "S\tx\tGG"];
+        let result = fill_gfa_lines(
+            &mut PackedGraph::new(),
+            lines.iter().map(|l| parser.parse_gfa_line(l.as_bytes())),
+        );
+        assert!(matches!(result, Err(GFAConversionError::Parse(_))));
+    }
 }
@@ -0,0 +1,248 @@
+use std::hash::Hash;
+
+use bstr::BString;
+use fnv::FnvHashMap;
+
+use gfa::{
+    cigar::CIGAR,
+    gfa::{Containment, Header, Link, Path, Segment},
+    optfields::{OptFields, OptionalFields},
+};
+
+use crate::{
+    handle::{Edge, Handle, NodeId},
+    pathhandlegraph::PanSN,
+};
+
+use super::Walk;
+
+/// The parts of a GFA link that aren't represented by the graph
+/// edge, along with the edge as it was written in the link.
+#[derive(Debug, Clone, PartialEq)]
+pub struct LinkMetadata {
+    pub edge: Edge,
+    pub overlap: BString,
+    pub optional: OptionalFields,
+}
+
+/// The parts of a GFA path that aren't represented by the steps of
+/// the embedded path.
+#[derive(Debug, Clone, PartialEq)]
+pub struct PathLineMetadata {
+    pub overlaps: Vec<Option<CIGAR>>,
+    pub optional: OptionalFields,
+}
+
+/// The fields of a GFA W-line other than its steps. These are kept
+/// as they were written, rather than parsed back out of the name of
+/// the embedded path, so that e.g. a walk with only one of its
+/// sequence coordinates is written back unchanged.
+#[derive(Debug, Clone, PartialEq)]
+pub struct WalkMetadata {
+    pub sample_id: BString,
+    pub hap_index: usize,
+    pub seq_id: BString,
+    pub seq_start: Option<usize>,
+    pub seq_end: Option<usize>,
+    pub optional: OptionalFields,
+}
+
+impl WalkMetadata {
+    pub fn new<T: OptFields>(walk: &Walk<T>) -> Self {
+        WalkMetadata {
+            sample_id: walk.sample_id.clone(),
+            hap_index: walk.hap_index,
+            seq_id: walk.seq_id.clone(),
+            seq_start: walk.seq_start,
+            seq_end: walk.seq_end,
+            optional: walk.optional.fields().to_vec(),
+        }
+    }
+
+    /// The PanSN metadata of the walk. As with `Walk::pansn`, the
+    /// sequence coordinates are only used as the subrange if both
+    /// are present.
+    pub fn pansn(&self) -> PanSN {
+        let subrange = match (self.seq_start, self.seq_end) {
+            (Some(start), Some(end)) => Some((start, end)),
+            _ => None,
+        };
+
+        PanSN {
+            sample: self.sample_id.to_vec(),
+            haplotype: self.hap_index,
+            contig: self.seq_id.to_vec(),
+            subrange,
+        }
+    }
+}
+
+/// Side tables for everything in a GFA file that a handlegraph can't
+/// store, i.e. the header, optional fields, overlaps, and
+/// containments, keyed by the node, edge, or path they belong to.
+/// Paths that were loaded from W-lines are tracked separately from
+/// those loaded from P-lines.
+///
+/// The order in which the lines were added is also kept, so that
+/// writing the graph together with its metadata reproduces the
+/// original GFA.
+#[derive(Debug, Clone)]
+pub struct GFAMetadata<P> {
+    pub header: Header<OptionalFields>,
+    segments: FnvHashMap<NodeId, OptionalFields>,
+    links: FnvHashMap<Edge, LinkMetadata>,
+    paths: FnvHashMap<P, PathLineMetadata>,
+    walks: FnvHashMap<P, WalkMetadata>,
+    containments: Vec<Containment<usize, OptionalFields>>,
+    segment_order: Vec<NodeId>,
+    link_order: Vec<Edge>,
+    path_order: Vec<P>,
+    walk_order: Vec<P>,
+}
+
+impl<P> Default for GFAMetadata<P> {
+    fn default() -> Self {
+        Self {
+            header: Default::default(),
+            segments: Default::default(),
+            links: Default::default(),
+            paths: Default::default(),
+            walks: Default::default(),
+            containments: Vec::new(),
+            segment_order: Vec::new(),
+            link_order: Vec::new(),
+            path_order: Vec::new(),
+            walk_order: Vec::new(),
+        }
+    }
+}
+
+/// Links are stored using the canonical orientation of their edge,
+/// so that either orientation can be used to look them up.
+#[inline]
+fn link_key(edge: Edge) -> Edge {
+    let Edge(left, right) = edge;
+    Edge::edge_handle(left, right)
+}
+
+impl<P: Copy + Eq + Hash> GFAMetadata<P> {
+    pub fn new() -> Self {
+        Default::default()
+    }
+
+    pub fn set_header<T: OptFields>(&mut self, header: &Header<T>) {
+        self.header = Header {
+            version: header.version.clone(),
+            optional: header.optional.fields().to_vec(),
+        };
+    }
+
+    pub fn add_segment<T: OptFields>(&mut self, segment: &Segment<usize, T>) {
+        let id = NodeId::from(segment.name);
+        let optional = segment.optional.fields().to_vec();
+        if self.segments.insert(id, optional).is_none() {
+            self.segment_order.push(id);
+        }
+    }
+
+    pub fn add_link<T: OptFields>(&mut self, link: &Link<usize, T>) {
+        let left = Handle::new(link.from_segment, link.from_orient);
+        let right = Handle::new(link.to_segment, link.to_orient);
+        let edge = Edge(left, right);
+        let key = link_key(edge);
+
+        let link = LinkMetadata {
+            edge,
+            overlap: link.overlap.clone(),
+            optional: link.optional.fields().to_vec(),
+        };
+
+        if self.links.insert(key, link).is_none() {
+            self.link_order.push(key);
+        }
+    }
+
+    pub fn add_containment<T: OptFields>(
+        &mut self,
+        containment: &Containment<usize, T>,
+    ) {
+        self.containments.push(Containment {
+            container_name: containment.container_name,
+            container_orient: containment.container_orient,
+            contained_name: containment.contained_name,
+            contained_orient: containment.contained_orient,
+            pos: containment.pos,
+            overlap: containment.overlap.clone(),
+            optional: containment.optional.fields().to_vec(),
+        });
+    }
+
+    pub fn add_path<T: OptFields>(
+        &mut self,
+        path_id: P,
+        path: &Path<usize, T>,
+    ) {
+        let path = PathLineMetadata {
+            overlaps: path.overlaps.clone(),
+            optional: path.optional.fields().to_vec(),
+        };
+
+        if self.paths.insert(path_id, path).is_none() {
+            self.path_order.push(path_id);
+        }
+    }
+
+    pub fn add_walk<T: OptFields>(&mut self, path_id: P, walk: &Walk<T>) {
+        let walk = WalkMetadata::new(walk);
+        if self.walks.insert(path_id, walk).is_none() {
+            self.walk_order.push(path_id);
+        }
+    }
+
+    pub fn segment(&self, id: NodeId) -> Option<&OptionalFields> {
+        self.segments.get(&id)
+    }
+
+    /// Get the metadata of the link for the given edge, in either
+    /// orientation.
+    pub fn link(&self, edge: Edge) -> Option<&LinkMetadata> {
+        self.links.get(&link_key(edge))
+    }
+
+    pub fn path(&self, path_id: &P) -> Option<&PathLineMetadata> {
+        self.paths.get(path_id)
+    }
+
+    /// Get the metadata of the W-line the path was loaded from.
+    pub fn walk(&self, path_id: &P) -> Option<&WalkMetadata> {
+        self.walks.get(path_id)
+    }
+
+    pub fn containments(&self) -> &[Containment<usize, OptionalFields>] {
+        &self.containments
+    }
+
+    /// The IDs of the segments that have metadata, in the order they
+    /// were added.
+    pub fn segment_order(&self) -> &[NodeId] {
+        &self.segment_order
+    }
+
+    /// The canonical edges of the links that have metadata, in the
+    /// order they were added.
+    pub fn link_order(&self) -> &[Edge] {
+        &self.link_order
+    }
+
+    /// The paths that have P-line metadata, in the order they were
+    /// added.
+    pub fn path_order(&self) -> &[P] {
+        &self.path_order
+    }
+
+    /// The paths that have W-line metadata, in the order they were
+    /// added.
+    pub fn walk_order(&self) -> &[P] {
+        &self.walk_order
+    }
+}
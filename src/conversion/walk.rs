@@ -0,0 +1,253 @@
+use std::fmt;
+use std::fs::File;
+use std::io::{BufRead, BufReader};
+
+use bstr::{BString, ByteSlice};
+
+use gfa::{
+    gfa::GFA,
+    optfields::{OptFields, OptionalFields},
+    parser::{GFAParser, GFAResult, ParseError, ParseFieldError},
+};
+
+use crate::{handle::Handle, pathhandlegraph::PanSN};
+
+use super::WalkMetadata;
+
+/// A GFA 1.1 walk line, describing the path taken by a haplotype
+/// through the graph.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Walk<T: OptFields> {
+    pub sample_id: BString,
+    pub hap_index: usize,
+    pub seq_id: BString,
+    pub seq_start: Option<usize>,
+    pub seq_end: Option<usize>,
+    pub steps: Vec<Handle>,
+    pub optional: T,
+}
+
+fn parse_usize(bytes: &[u8]) -> Option<usize> {
+    std::str::from_utf8(bytes).ok()?.parse().ok()
+}
+
+/// Parse a walk field, e.g. `>1<2>3`, into the handles it consists of.
+fn parse_walk_steps(bytes: &[u8]) -> Option<Vec<Handle>> {
+    if bytes.is_empty() {
+        return None;
+    }
+
+    let mut steps = Vec::new();
+    let mut rest = bytes;
+
+    while let Some((&orient, tail)) = rest.split_first() {
+        let is_reverse = match orient {
+            b'>' => false,
+            b'<' => true,
+            _ => return None,
+        };
+        let end = tail
+            .iter()
+            .position(|&b| b == b'>' || b == b'<')
+            .unwrap_or(tail.len());
+        let id = parse_usize(&tail[..end]).filter(|&id| id > 0)?;
+        steps.push(Handle::pack(id as u64, is_reverse));
+        rest = &tail[end..];
+    }
+
+    Some(steps)
+}
+
+impl<T: OptFields> Walk<T> {
+    /// Parse a W-line, including the leading `W` field.
+    pub fn parse_line(line: &[u8]) -> GFAResult<Self> {
+        let line_error = |err: ParseFieldError| {
+            ParseError::InvalidLine(err, line.to_str_lossy().into_owned())
+        };
+        let invalid = |name| line_error(ParseFieldError::InvalidField(name));
+
+        let mut fields = line.trim().split_str(b"\t");
+
+        if fields.next() != Some(b"W") {
+            return Err(ParseError::UnknownLineType);
+        }
+
+        let mut next_field = || {
+            fields
+                .next()
+                .filter(|f| !f.is_empty())
+                .ok_or_else(|| line_error(ParseFieldError::MissingFields))
+        };
+
+        let coordinate = |bytes: &[u8], name| match bytes {
+            b"*" => Ok(None),
+            _ => parse_usize(bytes).map(Some).ok_or_else(|| invalid(name)),
+        };
+
+        let sample_id = BString::from(next_field()?);
+        let hap_index =
+            parse_usize(next_field()?).ok_or_else(|| invalid("HapIndex"))?;
+        let seq_id = BString::from(next_field()?);
+        let seq_start = coordinate(next_field()?, "SeqStart")?;
+        let seq_end = coordinate(next_field()?, "SeqEnd")?;
+        let steps =
+            parse_walk_steps(next_field()?).ok_or_else(|| invalid("Walk"))?;
+
+        let optional = T::parse(fields);
+
+        Ok(Walk {
+            sample_id,
+            hap_index,
+            seq_id,
+            seq_start,
+            seq_end,
+            steps,
+            optional,
+        })
+    }
+
+    /// Build a walk from the PanSN metadata of a path. The subrange,
+    /// if any, is used as the sequence coordinates.
+    pub fn from_pansn(pansn: &PanSN, steps: Vec<Handle>, optional: T) -> Self {
+        let (seq_start, seq_end) = match pansn.subrange {
+            Some((start, end)) => (Some(start), Some(end)),
+            None => (None, None),
+        };
+
+        Walk {
+            sample_id: pansn.sample.clone().into(),
+            hap_index: pansn.haplotype,
+            seq_id: pansn.contig.clone().into(),
+            seq_start,
+            seq_end,
+            steps,
+            optional,
+        }
+    }
+
+    /// The PanSN metadata of the walk, which is also used to name the
+    /// embedded path the walk is loaded as. The sequence coordinates
+    /// are only used as the subrange if both are present.
+    pub fn pansn(&self) -> PanSN {
+        let subrange = match (self.seq_start, self.seq_end) {
+            (Some(start), Some(end)) => Some((start, end)),
+            _ => None,
+        };
+
+        PanSN {
+            sample: self.sample_id.to_vec(),
+            haplotype: self.hap_index,
+            contig: self.seq_id.to_vec(),
+            subrange,
+        }
+    }
+}
+
+impl Walk<OptionalFields> {
+    /// Build a walk from the W-line metadata of a path.
+    pub fn from_metadata(walk: &WalkMetadata, steps: Vec<Handle>) -> Self {
+        Walk {
+            sample_id: walk.sample_id.clone(),
+            hap_index: walk.hap_index,
+            seq_id: walk.seq_id.clone(),
+            seq_start: walk.seq_start,
+            seq_end: walk.seq_end,
+            steps,
+            optional: walk.optional.clone(),
+        }
+    }
+}
+
+/// Writes the walk as a W-line, without a trailing newline.
+impl<T: OptFields> fmt::Display for Walk<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "W\t{}\t{}\t{}\t",
+            self.sample_id, self.hap_index, self.seq_id
+        )?;
+
+        for coord in [self.seq_start, self.seq_end].iter() {
+            match coord {
+                Some(c) => write!(f, "{}\t", c)?,
+                None => write!(f, "*\t")?,
+            }
+        }
+
+        for step in self.steps.iter() {
+            let orient = if step.is_reverse() { '<' } else { '>' };
+            write!(f, "{}{}", orient, u64::from(step.id()))?;
+        }
+
+        for field in self.optional.fields() {
+            write!(f, "\t{}", field)?;
+        }
+
+        Ok(())
+    }
+}
+
+/// A GFA along with its GFA 1.1 walks, which are stored separately
+/// as the `gfa` crate only supports GFA 1.0.
+#[derive(Default, Debug, Clone, PartialEq)]
+pub struct GFAWithWalks<T: OptFields> {
+    pub gfa: GFA<usize, T>,
+    pub walks: Vec<Walk<T>>,
+}
+
+impl<T: OptFields> GFAWithWalks<T> {
+    /// Parse a single GFA line and add it to the GFA. Empty lines and
+    /// unknown line types are skipped.
+    fn insert_line(
+        &mut self,
+        parser: &GFAParser<usize, T>,
+        line: &[u8],
+    ) -> GFAResult<()> {
+        if line.starts_with(b"W\t") {
+            self.walks.push(Walk::parse_line(line)?);
+            return Ok(());
+        }
+
+        match parser.parse_gfa_line(line) {
+            Ok(parsed) => self.gfa.insert_line(parsed),
+            Err(ParseError::UnknownLineType) | Err(ParseError::EmptyLine) => (),
+            Err(err) => return Err(err),
+        }
+
+        Ok(())
+    }
+}
+
+/// Parse GFA 1.1 lines, including W-lines.
+pub fn parse_gfa_with_walks<T, I>(lines: I) -> GFAResult<GFAWithWalks<T>>
+where
+    T: OptFields,
+    I: IntoIterator,
+    I::Item: AsRef<[u8]>,
+{
+    let parser = GFAParser::new();
+    let mut gfa = GFAWithWalks::default();
+
+    for line in lines {
+        gfa.insert_line(&parser, line.as_ref())?;
+    }
+
+    Ok(gfa)
+}
+
+/// Parse a GFA 1.1 file, including W-lines.
+pub fn parse_gfa_file_with_walks<T, P>(path: P) -> GFAResult<GFAWithWalks<T>>
+where
+    T: OptFields,
+    P: AsRef<std::path::Path>,
+{
+    let parser = GFAParser::new();
+    let mut gfa = GFAWithWalks::default();
+
+    let reader = BufReader::new(File::open(path)?);
+    for line in reader.split(b'\n') {
+        gfa.insert_line(&parser, &line?)?;
+    }
+
+    Ok(gfa)
+}
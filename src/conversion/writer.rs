@@ -0,0 +1,184 @@
+use std::io::{BufWriter, Write};
+
+use rayon::prelude::*;
+
+use crate::{
+    handle::{Edge, Handle, NodeId},
+    handlegraph::*,
+    pathgraph::PathHandleGraph,
+};
+
+use super::{CIRCULAR_TOPOLOGY, PATH_TOPOLOGY_TAG};
+
+/// The order in which `write_gfa_with_options` writes segments.
+#[derive(Default, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SegmentOrder {
+    /// Sort the segments by node ID. This requires collecting the
+    /// node IDs before writing.
+    #[default]
+    ById,
+    /// Write the segments in the order `all_handles` returns them.
+    HandleOrder,
+}
+
+/// Options for the streaming GFA writer.
+#[derive(Default, Debug, Clone, Copy, PartialEq, Eq)]
+pub struct GFAWriterOptions {
+    pub segment_order: SegmentOrder,
+    /// If set, the P-lines are built in parallel, this many paths at
+    /// a time, and written in order once the whole chunk is done.
+    /// Each path in the chunk is kept in memory as a line, so this
+    /// trades memory for speed.
+    pub path_chunk_size: Option<usize>,
+}
+
+/// Write the graph as GFA directly to `writer`, without building a
+/// `GFA` in memory first. Segments are written in node ID order, and
+/// paths one at a time.
+///
+/// Like `to_gfa`, links have `0M` overlaps, and the only optional
+/// field written is the tag marking circular paths.
+pub fn write_gfa<G, W>(graph: &G, writer: W) -> std::io::Result<()>
+where
+    G: PathHandleGraph + Sync,
+    G::PathHandle: Copy + Send + Sync,
+    for<'a> &'a G: HandleGraphRef,
+    W: Write,
+{
+    write_gfa_with_options(graph, GFAWriterOptions::default(), writer)
+}
+
+/// Write the graph as GFA directly to `writer`, like `write_gfa`, but
+/// with the segment order and path parallelism set by `options`.
+///
+/// The writer is buffered internally.
+pub fn write_gfa_with_options<G, W>(
+    graph: &G,
+    options: GFAWriterOptions,
+    writer: W,
+) -> std::io::Result<()>
+where
+    G: PathHandleGraph + Sync,
+    G::PathHandle: Copy + Send + Sync,
+    for<'a> &'a G: HandleGraphRef,
+    W: Write,
+{
+    let mut writer = BufWriter::new(writer);
+
+    writeln!(writer, "H\tVN:Z:1.0")?;
+
+    match options.segment_order {
+        SegmentOrder::ById => {
+            let mut node_ids =
+                graph.all_handles().map(|h| h.id()).collect::<Vec<_>>();
+            node_ids.sort_unstable();
+            for id in node_ids {
+                write_segment(graph, id, &mut writer)?;
+            }
+        }
+        SegmentOrder::HandleOrder => {
+            for handle in graph.all_handles() {
+                write_segment(graph, handle.id(), &mut writer)?;
+            }
+        }
+    }
+
+    for Edge(left, right) in graph.all_edges() {
+        writeln!(
+            writer,
+            "L\t{}\t{}\t{}\t{}\t0M",
+            u64::from(left.id()),
+            orient_char(left),
+            u64::from(right.id()),
+            orient_char(right),
+        )?;
+    }
+
+    match options.path_chunk_size {
+        None => {
+            for path_id in graph.paths_iter() {
+                write_path_line(graph, *path_id, &mut writer)?;
+            }
+        }
+        Some(chunk_size) => {
+            let path_ids = graph.paths_iter().copied().collect::<Vec<_>>();
+
+            for chunk in path_ids.chunks(chunk_size.max(1)) {
+                let lines = chunk
+                    .par_iter()
+                    .map(|&path_id| {
+                        let mut line = Vec::new();
+                        write_path_line(graph, path_id, &mut line)?;
+                        Ok(line)
+                    })
+                    .collect::<std::io::Result<Vec<_>>>()?;
+
+                for line in lines {
+                    writer.write_all(&line)?;
+                }
+            }
+        }
+    }
+
+    writer.flush()
+}
+
+#[inline]
+fn orient_char(handle: Handle) -> char {
+    if handle.is_reverse() {
+        '-'
+    } else {
+        '+'
+    }
+}
+
+fn write_segment<G, W>(
+    graph: &G,
+    id: NodeId,
+    writer: &mut W,
+) -> std::io::Result<()>
+where
+    for<'a> &'a G: HandleGraphRef,
+    W: Write,
+{
+    let sequence = graph
+        .sequence_iter(Handle::pack(id, false))
+        .collect::<Vec<_>>();
+
+    write!(writer, "S\t{}\t", u64::from(id))?;
+    writer.write_all(&sequence)?;
+    writeln!(writer)
+}
+
+fn write_path_line<G, W>(
+    graph: &G,
+    path_id: G::PathHandle,
+    writer: &mut W,
+) -> std::io::Result<()>
+where
+    G: PathHandleGraph,
+    W: Write,
+{
+    writer.write_all(b"P\t")?;
+    writer.write_all(graph.path_handle_to_name(&path_id))?;
+    writer.write_all(b"\t")?;
+
+    for (ix, step) in graph.steps_iter(&path_id).enumerate() {
+        let handle = graph.handle_of_step(&step).unwrap();
+        if ix > 0 {
+            writer.write_all(b",")?;
+        }
+        write!(writer, "{}{}", u64::from(handle.id()), orient_char(handle))?;
+    }
+
+    writer.write_all(b"\t*")?;
+
+    if graph.is_circular(&path_id) {
+        writer.write_all(b"\t")?;
+        writer.write_all(PATH_TOPOLOGY_TAG)?;
+        writer.write_all(b":Z:")?;
+        writer.write_all(CIRCULAR_TOPOLOGY)?;
+    }
+
+    writeln!(writer)
+}
@@ -0,0 +1,57 @@
+use std::fmt;
+
+use crate::{
+    handle::{Handle, NodeId},
+    pathhandlegraph::{PathId, StepHandle},
+};
+
+/// Errors that can occur when building or modifying a graph, as
+/// returned by the fallible `try_` methods on the graph traits.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum HandleGraphError {
+    /// A node with the given ID already exists in the graph.
+    DuplicateNode(NodeId),
+    /// No node with the given ID exists in the graph.
+    MissingNode(NodeId),
+    /// Node IDs start from 1, so 0 can't be used.
+    NullNodeId,
+    /// An offset used to divide a handle was zero, past the end of
+    /// the node, or not larger than the previous offset.
+    InvalidOffset { handle: Handle, offset: usize },
+    /// No path with the given ID exists in the graph.
+    UnknownPath(PathId),
+    /// The steps used to delimit a path segment are on different
+    /// paths, or the end can't be reached from the start.
+    InvalidSegment { begin: StepHandle, end: StepHandle },
+    /// Tried to create a node with an empty sequence.
+    EmptySequence(NodeId),
+}
+
+pub type HandleGraphResult<T> = Result<T, HandleGraphError>;
+
+impl fmt::Display for HandleGraphError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        use HandleGraphError as HGE;
+        match self {
+            HGE::DuplicateNode(id) => write!(f, "Node {} already exists", id),
+            HGE::MissingNode(id) => write!(f, "Node {} doesn't exist", id),
+            HGE::NullNodeId => write!(f, "Node ID 0 is not allowed"),
+            HGE::InvalidOffset { handle, offset } => write!(
+                f,
+                "Invalid offset {} when dividing node {}{}",
+                offset,
+                handle.id(),
+                if handle.is_reverse() { '-' } else { '+' }
+            ),
+            HGE::UnknownPath(id) => write!(f, "Path {} doesn't exist", id.0),
+            HGE::InvalidSegment { begin, end } => {
+                write!(f, "Invalid path segment from {:?} to {:?}", begin, end)
+            }
+            HGE::EmptySequence(id) => {
+                write!(f, "Node {} has an empty sequence", id)
+            }
+        }
+    }
+}
+
+impl std::error::Error for HandleGraphError {}
@@ -95,6 +95,19 @@ impl crate::packed::PackedElement for Handle {
     }
 }
 
+/// A `NodeId` can be packed for use in a packed collection.
+impl crate::packed::PackedElement for NodeId {
+    #[inline]
+    fn unpack(v: u64) -> Self {
+        NodeId(v)
+    }
+
+    #[inline]
+    fn pack(self) -> u64 {
+        self.0
+    }
+}
+
 impl Handle {
     #[inline]
     pub fn as_integer(self) -> u64 {
@@ -43,6 +43,14 @@ pub trait AllEdges: Sized {
     }
 }
 
+/// Access all the edges in the graph as a parallel iterator. Each
+/// edge is produced once, in the same orientation as by `AllEdges`.
+pub trait AllEdgesPar {
+    type EdgesPar: ParallelIterator<Item = Edge>;
+
+    fn all_edges_par(self) -> Self::EdgesPar;
+}
+
 /// Access to the neighbors of any handle in the given direction, and related methods.
 ///
 /// Implementors should make sure that handles are flipped correctly depending on direction, e.g. using NeighborIter
@@ -76,9 +84,25 @@ pub trait HandleSequences: Sized {
         self.sequence_iter(handle.forward()).collect()
     }
 
+    /// Returns the (up to) `len` bases of the handle's sequence,
+    /// starting from `start`, in the handle's orientation.
     #[inline]
     fn subsequence(self, handle: Handle, start: usize, len: usize) -> Vec<u8> {
-        self.sequence_iter(handle).skip(start).take(len).collect()
+        self.subsequence_iter(handle, start, len).collect()
+    }
+
+    /// Iterate over the (up to) `len` bases of the handle's sequence,
+    /// starting from `start`, in the handle's orientation, without
+    /// allocating. Skipping to `start` is constant-time if the
+    /// `Sequence` iterator implements `nth` in constant time.
+    #[inline]
+    fn subsequence_iter(
+        self,
+        handle: Handle,
+        start: usize,
+        len: usize,
+    ) -> std::iter::Take<std::iter::Skip<Self::Sequence>> {
+        self.sequence_iter(handle).skip(start).take(len)
     }
 
     #[inline]
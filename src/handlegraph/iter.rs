@@ -121,12 +121,13 @@ impl<I> std::iter::FusedIterator for HandleEdgesIter<I> where
 
 /// Utility struct for iterating over all edges of a HandleGraph for
 /// which we can already iterate over all handles and their neighbors.
-pub struct EdgesIter<G>
+pub struct EdgesIter<G, I = <G as AllHandles>::Handles>
 where
     G: HandleNeighbors + AllHandles + Copy,
+    I: Iterator<Item = Handle>,
 {
     neighbors: Option<HandleEdgesIter<G::Neighbors>>,
-    handles: G::Handles,
+    handles: I,
     graph: G,
 }
 
@@ -136,6 +137,21 @@ where
 {
     pub fn new(graph: G) -> Self {
         let handles = graph.all_handles();
+        Self::with_handles(graph, handles)
+    }
+}
+
+impl<G, I> EdgesIter<G, I>
+where
+    G: HandleNeighbors + AllHandles + Copy,
+    I: Iterator<Item = Handle>,
+{
+    /// Iterate through the edges of only the given handles, e.g. to
+    /// split the edges of a graph into several iterators. Each edge
+    /// is only produced by one of its handles, so splitting the
+    /// handles of a graph into disjoint sets will also split its
+    /// edges into disjoint sets.
+    pub fn with_handles(graph: G, handles: I) -> Self {
         let mut edges_iter = Self {
             graph,
             handles,
@@ -161,9 +177,10 @@ where
     }
 }
 
-impl<G> Iterator for EdgesIter<G>
+impl<G, I> Iterator for EdgesIter<G, I>
 where
     G: HandleNeighbors + AllHandles + Copy,
+    I: Iterator<Item = Handle>,
 {
     type Item = Edge;
 
@@ -183,8 +200,10 @@ where
     }
 }
 
-impl<G> std::iter::FusedIterator for EdgesIter<G> where
-    G: HandleNeighbors + AllHandles + Copy
+impl<G, I> std::iter::FusedIterator for EdgesIter<G, I>
+where
+    G: HandleNeighbors + AllHandles + Copy,
+    I: Iterator<Item = Handle>,
 {
 }
 
@@ -260,6 +279,25 @@ where
             self.iter.next()
         }
     }
+
+    #[inline]
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.iter.size_hint()
+    }
+
+    #[inline]
+    fn nth(&mut self, n: usize) -> Option<u8> {
+        if self.reversing {
+            self.iter.nth_back(n).map(bio::alphabets::dna::complement)
+        } else {
+            self.iter.nth(n)
+        }
+    }
+}
+
+impl<I> ExactSizeIterator for SequenceIter<I> where
+    I: ExactSizeIterator<Item = u8> + DoubleEndedIterator
+{
 }
 
 // This one might be more efficient? Probably not, but it'd be
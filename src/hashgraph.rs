@@ -4,10 +4,15 @@ use bstr::BString;
 use rayon::prelude::*;
 
 use crate::{
+    error::{HandleGraphError, HandleGraphResult},
     handle::{Direction, Edge, Handle, NodeId},
     handlegraph::*,
     mutablehandlegraph::*,
     pathgraph::PathHandleGraph,
+    pathhandlegraph::{
+        step::PathStep, AllPathIdsPar, EmbeddedMutablePath, EmbeddedPaths,
+        MutEmbeddedPaths, PathOccurrences,
+    },
 };
 
 pub mod graph;
@@ -16,7 +21,7 @@ pub mod path;
 
 pub use self::graph::HashGraph;
 pub use self::node::Node;
-pub use self::path::{Path, PathId, PathStep};
+pub use self::path::{HashGraphPathMut, Path, PathId, PathRemoval, StepHandle};
 
 impl<'a> AllHandles for &'a HashGraph {
     type Handles = NodeIdRefHandles<
@@ -54,6 +59,31 @@ impl<'a> AllHandlesPar for &'a HashGraph {
     }
 }
 
+impl<'a> AllEdgesPar for &'a HashGraph {
+    type EdgesPar = rayon::iter::FlattenIter<
+        rayon::iter::MapWith<
+            rayon::collections::hash_map::Iter<'a, NodeId, Node>,
+            &'a HashGraph,
+            fn(
+                &mut &'a HashGraph,
+                (&'a NodeId, &'a Node),
+            )
+                -> EdgesIter<&'a HashGraph, std::iter::Once<Handle>>,
+        >,
+    >;
+
+    fn all_edges_par(self) -> Self::EdgesPar {
+        let node_edges: fn(&mut &'a HashGraph, _) -> _ = |graph, (&id, _)| {
+            let handle = std::iter::once(Handle::pack(id, false));
+            EdgesIter::with_handles(*graph, handle)
+        };
+        self.graph
+            .par_iter()
+            .map_with(self, node_edges)
+            .flatten_iter()
+    }
+}
+
 impl<'a> AllEdges for &'a HashGraph {
     type Edges = EdgesIter<&'a HashGraph>;
 
@@ -110,6 +140,29 @@ impl<'a> HandleSequences for &'a HashGraph {
         }
     }
 
+    fn subsequence(self, handle: Handle, start: usize, len: usize) -> Vec<u8> {
+        let seq: &[u8] =
+            self.get_node_unchecked(&handle.id()).sequence.as_ref();
+        let start = start.min(seq.len());
+        let end = start.saturating_add(len).min(seq.len());
+        if handle.is_reverse() {
+            dna::revcomp(&seq[seq.len() - end..seq.len() - start])
+        } else {
+            seq[start..end].into()
+        }
+    }
+
+    #[inline]
+    fn base(self, handle: Handle, index: usize) -> u8 {
+        let seq: &[u8] =
+            self.get_node_unchecked(&handle.id()).sequence.as_ref();
+        if handle.is_reverse() {
+            dna::complement(seq[seq.len() - 1 - index])
+        } else {
+            seq[index]
+        }
+    }
+
     #[inline]
     fn node_len(self, handle: Handle) -> usize {
         self.get_node_unchecked(&handle.id()).sequence.len()
@@ -139,23 +192,39 @@ impl AdditiveHandleGraph for HashGraph {
         self.create_handle(sequence, self.max_id + 1)
     }
 
-    fn create_handle<T: Into<NodeId>>(
+    fn try_create_handle<T: Into<NodeId>>(
         &mut self,
         seq: &[u8],
         node_id: T,
-    ) -> Handle {
+    ) -> HandleGraphResult<Handle> {
         let id: NodeId = node_id.into();
 
+        if id == NodeId::from(0) {
+            return Err(HandleGraphError::NullNodeId);
+        }
         if seq.is_empty() {
-            panic!("Tried to add empty handle");
+            return Err(HandleGraphError::EmptySequence(id));
+        }
+        if self.graph.contains_key(&id) {
+            return Err(HandleGraphError::DuplicateNode(id));
         }
+
         self.graph.insert(id, Node::new(seq));
         self.max_id = std::cmp::max(self.max_id, id);
         self.min_id = std::cmp::min(self.min_id, id);
-        Handle::pack(id, false)
+        Ok(Handle::pack(id, false))
     }
 
-    fn create_edge(&mut self, Edge(left, right): Edge) {
+    fn try_create_edge(
+        &mut self,
+        Edge(left, right): Edge,
+    ) -> HandleGraphResult<()> {
+        for handle in [left, right].iter() {
+            if !self.graph.contains_key(&handle.id()) {
+                return Err(HandleGraphError::MissingNode(handle.id()));
+            }
+        }
+
         let add_edge = {
             let left_node = self
                 .graph
@@ -187,17 +256,41 @@ impl AdditiveHandleGraph for HashGraph {
                 }
             }
         }
+
+        Ok(())
+    }
+}
+
+impl SubtractiveHandleGraph for HashGraph {
+    fn remove_handle(&mut self, handle: Handle) -> bool {
+        self.remove_handle_impl(handle).is_some()
+    }
+
+    fn remove_edge(&mut self, edge: Edge) -> bool {
+        self.remove_edge_impl(edge).is_some()
+    }
+
+    fn clear_graph(&mut self) {
+        *self = HashGraph {
+            path_removal: self.path_removal,
+            ..Default::default()
+        };
     }
 }
 
 impl MutableHandleGraph for HashGraph {
-    fn divide_handle(
+    fn try_divide_handle(
         &mut self,
         handle: Handle,
         mut offsets: Vec<usize>,
-    ) -> Vec<Handle> {
+    ) -> HandleGraphResult<Vec<Handle>> {
+        if !self.graph.contains_key(&handle.id()) {
+            return Err(HandleGraphError::MissingNode(handle.id()));
+        }
+
         let mut result = vec![handle];
         let node_len = self.node_len(handle);
+        validate_offsets(handle, node_len, &offsets)?;
         let sequence = self.sequence(handle);
 
         let fwd_handle = handle.forward();
@@ -278,7 +371,7 @@ impl MutableHandleGraph for HashGraph {
 
         // TODO this is probably not correct, and it's silly to clone
         // the results all the time
-        let affected_paths: Vec<(i64, usize)> = self
+        let affected_paths: Vec<(PathId, usize)> = self
             .get_node_unchecked(&handle.id())
             .occurrences
             .iter()
@@ -286,11 +379,11 @@ impl MutableHandleGraph for HashGraph {
             .collect();
 
         for (path_id, ix) in affected_paths.into_iter() {
-            let step = PathStep::Step(path_id, ix);
+            let step = StepHandle::new(path_id, PathStep::Step(ix));
             self.rewrite_segment(&step, &step, result.clone());
         }
 
-        result
+        Ok(result)
     }
 
     fn apply_orientation(&mut self, handle: Handle) -> Handle {
@@ -344,7 +437,7 @@ impl MutableHandleGraph for HashGraph {
 
 impl PathHandleGraph for HashGraph {
     type PathHandle = PathId;
-    type StepHandle = PathStep;
+    type StepHandle = StepHandle;
 
     fn path_count(&self) -> usize {
         self.path_id.len()
@@ -358,8 +451,15 @@ impl PathHandleGraph for HashGraph {
         self.path_id.get(name).copied()
     }
 
-    fn path_handle_to_name(&self, path_id: &Self::PathHandle) -> &[u8] {
-        self.get_path_unchecked(path_id).name.as_slice()
+    fn try_path_handle_to_name(
+        &self,
+        path_id: &Self::PathHandle,
+    ) -> HandleGraphResult<&[u8]> {
+        let path = self
+            .paths
+            .get(path_id)
+            .ok_or(HandleGraphError::UnknownPath(*path_id))?;
+        Ok(path.name.as_slice())
     }
 
     fn is_circular(&self, path_id: &Self::PathHandle) -> bool {
@@ -380,27 +480,34 @@ impl PathHandleGraph for HashGraph {
     }
 
     fn path_begin(&self, path: &Self::PathHandle) -> Self::StepHandle {
-        PathStep::Step(*path, 0)
+        if self.step_count(path) == 0 {
+            self.path_end(path)
+        } else {
+            StepHandle::new(*path, PathStep::Step(0))
+        }
     }
 
     fn path_end(&self, path: &Self::PathHandle) -> Self::StepHandle {
-        PathStep::End(*path)
+        StepHandle::new(*path, PathStep::After)
     }
 
     fn path_back(&self, path: &Self::PathHandle) -> Self::StepHandle {
-        PathStep::Step(*path, self.step_count(path) - 1)
+        match self.step_count(path) {
+            0 => self.path_front_end(path),
+            len => StepHandle::new(*path, PathStep::Step(len - 1)),
+        }
     }
 
     fn path_front_end(&self, path: &Self::PathHandle) -> Self::StepHandle {
-        PathStep::Front(*path)
+        StepHandle::new(*path, PathStep::Before)
     }
 
     fn has_next_step(&self, step: &Self::StepHandle) -> bool {
-        matches!(step, PathStep::End(_))
+        !self.next_step(step).step().is_after()
     }
 
     fn has_previous_step(&self, step: &Self::StepHandle) -> bool {
-        matches!(step, PathStep::Front(_))
+        !self.previous_step(step).step().is_before()
     }
 
     fn path_bases_len(&self, path_handle: &Self::PathHandle) -> Option<usize> {
@@ -423,41 +530,50 @@ impl PathHandleGraph for HashGraph {
     }
 
     fn next_step(&self, step: &Self::StepHandle) -> Self::StepHandle {
-        match step {
-            PathStep::Front(pid) => self.path_begin(pid),
-            PathStep::End(pid) => self.path_end(pid),
-            PathStep::Step(pid, ix) => {
-                if *ix < self.step_count(pid) - 1 {
-                    PathStep::Step(*pid, ix + 1)
+        let pid = step.path_id();
+        match step.step() {
+            PathStep::Before => self.path_begin(&pid),
+            PathStep::After => *step,
+            PathStep::Step(ix) => {
+                if ix + 1 < self.step_count(&pid) {
+                    StepHandle::new(pid, PathStep::Step(ix + 1))
                 } else {
-                    self.path_end(pid)
+                    self.path_end(&pid)
                 }
             }
         }
     }
 
     fn previous_step(&self, step: &Self::StepHandle) -> Self::StepHandle {
-        match step {
-            PathStep::Front(pid) => self.path_front_end(pid),
-            PathStep::End(pid) => self.path_back(pid),
-            PathStep::Step(pid, ix) => {
-                if *ix > 0 {
-                    PathStep::Step(*pid, ix - 1)
+        let pid = step.path_id();
+        match step.step() {
+            PathStep::Before => *step,
+            PathStep::After => self.path_back(&pid),
+            PathStep::Step(ix) => {
+                if ix > 0 {
+                    StepHandle::new(pid, PathStep::Step(ix - 1))
                 } else {
-                    self.path_end(pid)
+                    self.path_front_end(&pid)
                 }
             }
         }
     }
 
-    fn destroy_path(&mut self, path: &Self::PathHandle) {
-        let p: &Path = self.paths.get(&path).unwrap();
+    fn try_destroy_path(
+        &mut self,
+        path: &Self::PathHandle,
+    ) -> HandleGraphResult<()> {
+        let p: Path = self
+            .paths
+            .remove(path)
+            .ok_or(HandleGraphError::UnknownPath(*path))?;
 
         for handle in p.nodes.iter() {
             let node: &mut Node = self.graph.get_mut(&handle.id()).unwrap();
             node.occurrences.remove(path);
         }
-        self.paths.remove(&path);
+        self.path_id.remove(p.name.as_slice());
+        Ok(())
     }
 
     fn create_path_handle(
@@ -465,32 +581,52 @@ impl PathHandleGraph for HashGraph {
         name: &[u8],
         is_circular: bool,
     ) -> Self::PathHandle {
-        let path_id = self.paths.len() as i64;
+        // Paths may have been destroyed, so the path count can
+        // already be in use as an ID
+        let mut path_id = PathId(self.paths.len() as u64);
+        while self.paths.contains_key(&path_id) {
+            path_id.0 += 1;
+        }
         let path = Path::new(name, path_id, is_circular);
         self.path_id.insert(name.into(), path_id);
         self.paths.insert(path_id, path);
         path_id
     }
 
-    fn append_step(
+    fn try_append_step(
         &mut self,
         path_id: &Self::PathHandle,
         to_append: Handle,
-    ) -> Self::StepHandle {
-        let path: &mut Path = self.paths.get_mut(path_id).unwrap();
+    ) -> HandleGraphResult<Self::StepHandle> {
+        let path: &mut Path = self
+            .paths
+            .get_mut(path_id)
+            .ok_or(HandleGraphError::UnknownPath(*path_id))?;
+        let node: &mut Node = self
+            .graph
+            .get_mut(&to_append.id())
+            .ok_or(HandleGraphError::MissingNode(to_append.id()))?;
         path.nodes.push(to_append);
         let step = (*path_id, path.nodes.len() - 1);
-        let node: &mut Node = self.graph.get_mut(&to_append.id()).unwrap();
         node.occurrences.insert(step.0, step.1);
-        PathStep::Step(*path_id, path.nodes.len() - 1)
+        Ok(StepHandle::new(
+            *path_id,
+            PathStep::Step(path.nodes.len() - 1),
+        ))
     }
 
-    fn prepend_step(
+    fn try_prepend_step(
         &mut self,
         path_id: &Self::PathHandle,
         to_prepend: Handle,
-    ) -> Self::StepHandle {
-        let path: &mut Path = self.paths.get_mut(path_id).unwrap();
+    ) -> HandleGraphResult<Self::StepHandle> {
+        if !self.graph.contains_key(&to_prepend.id()) {
+            return Err(HandleGraphError::MissingNode(to_prepend.id()));
+        }
+        let path: &mut Path = self
+            .paths
+            .get_mut(path_id)
+            .ok_or(HandleGraphError::UnknownPath(*path_id))?;
         // update occurrences in nodes already in the graph
         for h in path.nodes.iter() {
             let node: &mut Node = self.graph.get_mut(&h.id()).unwrap();
@@ -499,33 +635,58 @@ impl PathHandleGraph for HashGraph {
         path.nodes.insert(0, to_prepend);
         let node: &mut Node = self.graph.get_mut(&to_prepend.id()).unwrap();
         node.occurrences.insert(*path_id, 0);
-        PathStep::Step(*path_id, 0)
+        Ok(StepHandle::new(*path_id, PathStep::Step(0)))
     }
 
-    fn rewrite_segment(
+    fn try_rewrite_segment(
         &mut self,
         begin: &Self::StepHandle,
         end: &Self::StepHandle,
         new_segment: Vec<Handle>,
-    ) -> (Self::StepHandle, Self::StepHandle) {
+    ) -> HandleGraphResult<(Self::StepHandle, Self::StepHandle)> {
         // extract the index range from the begin and end handles
 
+        let invalid_segment = HandleGraphError::InvalidSegment {
+            begin: *begin,
+            end: *end,
+        };
+
         if begin.path_id() != end.path_id() {
-            panic!("Tried to rewrite path segment between two different paths");
+            return Err(invalid_segment);
         }
 
         let path_id = begin.path_id();
-        let path_len = self.paths.get(&path_id).unwrap().nodes.len();
+        let path_len = self
+            .paths
+            .get(&path_id)
+            .ok_or(HandleGraphError::UnknownPath(path_id))?
+            .nodes
+            .len();
+
+        if let Some(h) = new_segment
+            .iter()
+            .find(|h| !self.graph.contains_key(&h.id()))
+        {
+            return Err(HandleGraphError::MissingNode(h.id()));
+        }
+
+        if path_len == 0 {
+            return Err(invalid_segment);
+        }
 
-        let step_index = |s: &Self::StepHandle| match s {
-            PathStep::Front(_) => 0,
-            PathStep::End(_) => path_len - 1,
-            PathStep::Step(_, i) => *i,
+        let step_index = |s: &Self::StepHandle| match s.step() {
+            PathStep::Before => 0,
+            PathStep::After => path_len - 1,
+            PathStep::Step(i) => i,
         };
 
         let l = step_index(begin);
         let r = step_index(end);
 
+        if l > r || r >= path_len {
+            return Err(invalid_segment);
+        }
+
         let range = l..=r;
 
         // first delete the occurrences of the nodes in the range
@@ -559,9 +720,12 @@ impl PathHandleGraph for HashGraph {
         }
 
         // return the new beginning and end step handles: even if the
-        // input steps were Front and/or End, the output steps exist
+        // input steps were Before and/or After, the output steps exist
         // on the path
-        (PathStep::Step(path_id, l), PathStep::Step(path_id, r))
+        Ok((
+            StepHandle::new(path_id, PathStep::Step(l)),
+            StepHandle::new(path_id, PathStep::Step(r)),
+        ))
     }
 
     fn paths_iter<'a>(
@@ -575,7 +739,11 @@ impl PathHandleGraph for HashGraph {
         handle: Handle,
     ) -> Box<dyn Iterator<Item = Self::StepHandle> + 'a> {
         let node: &Node = self.get_node_unchecked(&handle.id());
-        Box::new(node.occurrences.iter().map(|(k, v)| PathStep::Step(*k, *v)))
+        Box::new(
+            node.occurrences
+                .iter()
+                .map(|(k, v)| StepHandle::new(*k, PathStep::Step(*v))),
+        )
     }
 
     fn steps_iter<'a>(
@@ -583,11 +751,119 @@ impl PathHandleGraph for HashGraph {
         path_handle: &'a Self::PathHandle,
     ) -> Box<dyn Iterator<Item = Self::StepHandle> + 'a> {
         let path = self.get_path_unchecked(path_handle);
-        Box::new(
-            path.nodes
+        Box::new(path.nodes.iter().enumerate().map(move |(i, _)| {
+            StepHandle::new(*path_handle, PathStep::Step(i))
+        }))
+    }
+}
+
+impl<'a> EmbeddedPaths for &'a HashGraph {
+    type AllPaths =
+        std::iter::Copied<std::collections::hash_map::Keys<'a, PathId, Path>>;
+    type PathName = std::iter::Copied<std::slice::Iter<'a, u8>>;
+    type Path = &'a Path;
+
+    fn all_path_ids(self) -> Self::AllPaths {
+        self.paths.keys().copied()
+    }
+
+    fn get_path(self, path_id: PathId) -> Option<Self::Path> {
+        self.paths.get(&path_id)
+    }
+
+    fn lookup_path_id(self, name: &[u8]) -> Option<PathId> {
+        self.path_id.get(name).copied()
+    }
+
+    fn get_path_name(self, path_id: PathId) -> Option<Self::PathName> {
+        let path = self.paths.get(&path_id)?;
+        Some(path.name.iter().copied())
+    }
+
+    fn path_count(self) -> usize {
+        self.paths.len()
+    }
+}
+
+impl AllPathIdsPar for &HashGraph {
+    type PathIdsPar = rayon::vec::IntoIter<PathId>;
+
+    fn all_path_ids_par(self) -> Self::PathIdsPar {
+        let path_ids = self.paths.keys().copied();
+        path_ids.collect::<Vec<_>>().into_par_iter()
+    }
+}
+
+impl<'a> EmbeddedPaths for &'a mut HashGraph {
+    type AllPaths = <&'a HashGraph as EmbeddedPaths>::AllPaths;
+    type PathName = <&'a HashGraph as EmbeddedPaths>::PathName;
+    type Path = <&'a HashGraph as EmbeddedPaths>::Path;
+
+    fn all_path_ids(self) -> Self::AllPaths {
+        EmbeddedPaths::all_path_ids(&*self)
+    }
+
+    fn get_path(self, path_id: PathId) -> Option<Self::Path> {
+        EmbeddedPaths::get_path(&*self, path_id)
+    }
+
+    fn lookup_path_id(self, name: &[u8]) -> Option<PathId> {
+        EmbeddedPaths::lookup_path_id(&*self, name)
+    }
+
+    fn get_path_name(self, path_id: PathId) -> Option<Self::PathName> {
+        EmbeddedPaths::get_path_name(&*self, path_id)
+    }
+
+    fn path_count(self) -> usize {
+        EmbeddedPaths::path_count(&*self)
+    }
+}
+
+impl PathOccurrences for &HashGraph {
+    type Occurrences = std::vec::IntoIter<StepHandle>;
+
+    /// Nodes only store one occurrence per path, so the paths that
+    /// visit the node are searched for the rest of its steps.
+    fn steps_on_handle(self, handle: Handle) -> Self::Occurrences {
+        let node = self.get_node_unchecked(&handle.id());
+
+        let mut path_ids = node.occurrences.keys().copied().collect::<Vec<_>>();
+        path_ids.sort_unstable();
+
+        let mut steps = Vec::new();
+        for path_id in path_ids {
+            let path = self.get_path_unchecked(&path_id);
+            let step_ixs = path
+                .nodes
                 .iter()
                 .enumerate()
-                .map(move |(i, _)| PathStep::Step(*path_handle, i)),
-        )
+                .filter(|(_, h)| h.id() == handle.id())
+                .map(|(ix, _)| StepHandle::new(path_id, PathStep::Step(ix)));
+            steps.extend(step_ixs);
+        }
+
+        steps.into_iter()
+    }
+}
+
+impl<'a> EmbeddedMutablePath for &'a mut HashGraph {
+    type PathMut = HashGraphPathMut<'a>;
+
+    fn get_path_mut(self, path_id: PathId) -> Option<Self::PathMut> {
+        let path = self.paths.get_mut(&path_id)?;
+        Some(HashGraphPathMut::new(path, &mut self.graph))
+    }
+}
+
+impl MutEmbeddedPaths for HashGraph {
+    fn create_path(&mut self, name: &[u8], circular: bool) -> PathId {
+        self.create_path_handle(name, circular)
+    }
+
+    fn remove_path(&mut self, id: PathId) {
+        if self.paths.contains_key(&id) {
+            self.destroy_path(&id);
+        }
     }
 }
@@ -1,26 +1,46 @@
-use fnv::FnvHashMap;
+use std::ops::Range;
 
-use gfa::{
-    gfa::{Link, Segment, GFA},
-    optfields::OptFields,
-};
+use fnv::{FnvHashMap, FnvHashSet};
+
+use gfa::{gfa::GFA, optfields::OptFields};
 
 use crate::{
+    conversion,
+    error::{HandleGraphError, HandleGraphResult},
     handle::{Edge, Handle, NodeId},
     handlegraph::*,
-    mutablehandlegraph::*,
     pathgraph::PathHandleGraph,
+    subgraph::subpath_name,
+    validation::{ValidationIssue, ValidationReport},
 };
 
-use super::{Node, Path, PathId};
+use super::{Node, Path, PathId, PathRemoval};
 
 #[derive(Debug)]
 pub struct HashGraph {
     pub max_id: NodeId,
     pub min_id: NodeId,
     pub graph: FnvHashMap<NodeId, Node>,
-    pub path_id: FnvHashMap<Vec<u8>, i64>,
-    pub paths: FnvHashMap<i64, Path>,
+    pub path_id: FnvHashMap<Vec<u8>, PathId>,
+    pub paths: FnvHashMap<PathId, Path>,
+    /// What to do with the paths that use a node or edge that's
+    /// being removed.
+    pub path_removal: PathRemoval,
+}
+
+// Join the run at the end of a circular path with the run at its
+// start, as the steps on either side of the origin are still
+// connected, so that the joined run ends past the end of the path
+fn join_circular_runs(runs: &mut Vec<Range<usize>>, len: usize) {
+    if runs.len() < 2 {
+        return;
+    }
+    let first = runs.remove(0);
+    let last = runs.last_mut().unwrap();
+    last.end = len + first.end;
+    if last.start == len {
+        *last = 0..first.end;
+    }
 }
 
 impl Default for HashGraph {
@@ -31,6 +51,7 @@ impl Default for HashGraph {
             graph: Default::default(),
             path_id: Default::default(),
             paths: Default::default(),
+            path_removal: Default::default(),
         }
     }
 }
@@ -40,38 +61,31 @@ impl HashGraph {
         Default::default()
     }
 
-    fn add_gfa_segment<'a, 'b, T: OptFields>(
-        &'a mut self,
-        seg: &'b Segment<usize, T>,
-    ) {
-        self.create_handle(&seg.sequence, seg.name as u64);
-    }
-
-    fn add_gfa_link<T: OptFields>(&mut self, link: &Link<usize, T>) {
-        let left = Handle::new(link.from_segment as u64, link.from_orient);
-        let right = Handle::new(link.to_segment as u64, link.to_orient);
-
-        self.create_edge(Edge(left, right));
-    }
-
-    fn add_gfa_path<T: OptFields>(&mut self, path: &gfa::gfa::Path<usize, T>) {
-        let path_id = self.create_path_handle(&path.path_name, false);
-        for (name, orient) in path.iter() {
-            self.append_step(&path_id, Handle::new(name as u64, orient));
+    /// Create an empty graph that uses `path_removal` when removing
+    /// nodes and edges that are on paths.
+    pub fn with_path_removal(path_removal: PathRemoval) -> HashGraph {
+        HashGraph {
+            path_removal,
+            ..Default::default()
         }
     }
 
+    /// Build a graph from a GFA, see `conversion::from_gfa`.
     pub fn from_gfa<T: OptFields>(gfa: &GFA<usize, T>) -> HashGraph {
-        let mut graph = Self::new();
-        gfa.segments.iter().for_each(|s| graph.add_gfa_segment(s));
-        gfa.links.iter().for_each(|l| graph.add_gfa_link(l));
-        gfa.paths.iter().for_each(|p| graph.add_gfa_path(p));
-        graph
+        conversion::from_gfa(gfa)
+    }
+
+    /// Build a graph from a GFA, returning an error if the GFA can't
+    /// be represented as a graph, see `conversion::try_from_gfa`.
+    pub fn try_from_gfa<T: OptFields>(
+        gfa: &GFA<usize, T>,
+    ) -> HandleGraphResult<HashGraph> {
+        conversion::try_from_gfa(gfa)
     }
 
     pub fn print_path(&self, path_id: &PathId) {
         let path = self.paths.get(&path_id).unwrap();
-        println!("Path\t{}", path_id);
+        println!("Path\t{}", path_id.0);
         for (ix, handle) in path.nodes.iter().enumerate() {
             let node = self.get_node(&handle.id()).unwrap();
             if ix != 0 {
@@ -109,8 +123,355 @@ impl HashGraph {
     }
 
     pub fn get_path_unchecked(&self, path_id: &PathId) -> &Path {
-        self.paths
-            .get(path_id)
-            .unwrap_or_else(|| panic!("Tried to look up nonexistent path:"))
+        self.paths.get(path_id).unwrap_or_else(|| {
+            panic!("{}", HandleGraphError::UnknownPath(*path_id))
+        })
+    }
+
+    pub(super) fn remove_handle_impl(&mut self, handle: Handle) -> Option<()> {
+        let id = handle.id();
+        let node = self.graph.get(&id)?;
+
+        let mut path_ids = node.occurrences.keys().copied().collect::<Vec<_>>();
+        path_ids.sort_unstable();
+
+        if !path_ids.is_empty() {
+            match self.path_removal {
+                PathRemoval::Refuse => return None,
+                PathRemoval::DestroyPaths => {
+                    for path_id in path_ids {
+                        self.destroy_path(&path_id);
+                    }
+                }
+                PathRemoval::SplitPaths => {
+                    let splits = path_ids
+                        .into_iter()
+                        .map(|path_id| {
+                            let path = &self.paths[&path_id];
+                            let len = path.nodes.len();
+                            let mut runs = Vec::new();
+                            let mut start = 0;
+                            for (ix, step) in path.nodes.iter().enumerate() {
+                                if step.id() == id {
+                                    runs.push(start..ix);
+                                    start = ix + 1;
+                                }
+                            }
+                            runs.push(start..len);
+                            // If the node is at either end of the
+                            // path, one of the joined runs is empty
+                            if path.is_circular {
+                                join_circular_runs(&mut runs, len);
+                            }
+                            (path_id, runs)
+                        })
+                        .collect();
+                    self.split_paths(splits)?;
+                }
+            }
+        }
+
+        let node = self.graph.get(&id)?;
+        let fwd = Handle::pack(id, false);
+        let edges = node
+            .right_edges
+            .iter()
+            .map(|&other| Edge(fwd, other))
+            .chain(node.left_edges.iter().map(|&other| Edge(fwd.flip(), other)))
+            .collect::<Vec<_>>();
+
+        for edge in edges {
+            self.remove_edge_entries(edge);
+        }
+
+        self.graph.remove(&id);
+
+        if self.graph.is_empty() {
+            self.min_id = NodeId::from(u64::MAX);
+            self.max_id = NodeId::from(0);
+        } else if id == self.min_id || id == self.max_id {
+            self.min_id = self.graph.keys().copied().min().unwrap();
+            self.max_id = self.graph.keys().copied().max().unwrap();
+        }
+
+        Some(())
+    }
+
+    pub(super) fn remove_edge_impl(&mut self, edge: Edge) -> Option<()> {
+        let Edge(left, right) = edge;
+        let left_node = self.graph.get(&left.id())?;
+
+        let left_edges = if left.is_reverse() {
+            &left_node.left_edges
+        } else {
+            &left_node.right_edges
+        };
+
+        if !left_edges.contains(&right) {
+            return None;
+        }
+
+        // Find the steps that are followed by the edge, in either
+        // orientation, on each path that visits its left node
+        let mut path_ids =
+            left_node.occurrences.keys().copied().collect::<Vec<_>>();
+        path_ids.sort_unstable();
+
+        let crossings = path_ids
+            .into_iter()
+            .filter_map(|path_id| {
+                let path = &self.paths[&path_id];
+                let steps = &path.nodes;
+                // Circular paths also step from the last to the first
+                // handle
+                let pairs = if path.is_circular {
+                    steps.len()
+                } else {
+                    steps.len().saturating_sub(1)
+                };
+                let cuts = (0..pairs)
+                    .filter(|&ix| {
+                        let (a, b) = (steps[ix], steps[(ix + 1) % steps.len()]);
+                        Edge(a, b) == edge || Edge(b.flip(), a.flip()) == edge
+                    })
+                    .collect::<Vec<_>>();
+                if cuts.is_empty() {
+                    None
+                } else {
+                    Some((path_id, cuts))
+                }
+            })
+            .collect::<Vec<_>>();
+
+        if !crossings.is_empty() {
+            match self.path_removal {
+                PathRemoval::Refuse => return None,
+                PathRemoval::DestroyPaths => {
+                    for (path_id, _) in crossings {
+                        self.destroy_path(&path_id);
+                    }
+                }
+                PathRemoval::SplitPaths => {
+                    let splits = crossings
+                        .into_iter()
+                        .map(|(path_id, cuts)| {
+                            let path = &self.paths[&path_id];
+                            let len = path.nodes.len();
+                            let wraps = cuts.last() != Some(&(len - 1));
+                            let mut runs = Vec::new();
+                            let mut start = 0;
+                            for ix in cuts {
+                                runs.push(start..ix + 1);
+                                start = ix + 1;
+                            }
+                            if start < len {
+                                runs.push(start..len);
+                            }
+                            if path.is_circular && wraps {
+                                join_circular_runs(&mut runs, len);
+                            }
+                            (path_id, runs)
+                        })
+                        .collect();
+                    self.split_paths(splits)?;
+                }
+            }
+        }
+
+        self.remove_edge_entries(edge);
+
+        Some(())
+    }
+
+    // Remove an edge from the edge lists of both its nodes, without
+    // touching the paths
+    fn remove_edge_entries(&mut self, Edge(left, right): Edge) {
+        if let Some(node) = self.graph.get_mut(&left.id()) {
+            let edges = if left.is_reverse() {
+                &mut node.left_edges
+            } else {
+                &mut node.right_edges
+            };
+            if let Some(ix) = edges.iter().position(|&h| h == right) {
+                edges.remove(ix);
+            }
+        }
+
+        if left != right.flip() {
+            if let Some(node) = self.graph.get_mut(&right.id()) {
+                let edges = if right.is_reverse() {
+                    &mut node.right_edges
+                } else {
+                    &mut node.left_edges
+                };
+                if let Some(ix) = edges.iter().position(|&h| h == left.flip()) {
+                    edges.remove(ix);
+                }
+            }
+        }
+    }
+
+    // Replace each path with the non-empty runs of its steps, where
+    // runs on circular paths may wrap past the end of the path. A
+    // circular path that's kept whole only loses its circularity.
+    //
+    // Fails without modifying any path if the name of a subpath is
+    // already used by a path that's not being split, or by another
+    // subpath.
+    fn split_paths(
+        &mut self,
+        splits: Vec<(PathId, Vec<Range<usize>>)>,
+    ) -> Option<()> {
+        let mut kept = Vec::new();
+        let mut subpaths = Vec::new();
+
+        for (path_id, runs) in splits {
+            let len = self.paths[&path_id].nodes.len();
+            match runs.as_slice() {
+                [run] if run.start == 0 && run.end == len => kept.push(path_id),
+                _ => subpaths.push((path_id, self.subpaths(path_id, &runs))),
+            }
+        }
+
+        let split_ids = subpaths
+            .iter()
+            .map(|(path_id, _)| *path_id)
+            .collect::<FnvHashSet<_>>();
+        let mut names = FnvHashSet::default();
+        for (name, _) in subpaths.iter().flat_map(|(_, paths)| paths) {
+            let taken = self
+                .path_id
+                .get(name)
+                .is_some_and(|other| !split_ids.contains(other));
+            if taken || !names.insert(name) {
+                return None;
+            }
+        }
+
+        for path_id in kept {
+            self.paths.get_mut(&path_id).unwrap().is_circular = false;
+        }
+
+        for (path_id, paths) in subpaths {
+            self.destroy_path(&path_id);
+            for (name, steps) in paths {
+                let new_path = self.create_path_handle(&name, false);
+                for step in steps {
+                    self.append_step(&new_path, step);
+                }
+            }
+        }
+
+        Some(())
+    }
+
+    // The names and steps of the subpaths covering the non-empty runs
+    // of the steps of a path
+    fn subpaths(
+        &self,
+        path_id: PathId,
+        runs: &[Range<usize>],
+    ) -> Vec<(Vec<u8>, Vec<Handle>)> {
+        let path = &self.paths[&path_id];
+        let len = path.nodes.len();
+
+        let mut offsets = Vec::with_capacity(len + 1);
+        let mut offset = 0;
+        offsets.push(offset);
+        for step in path.nodes.iter() {
+            offset += self.graph[&step.id()].sequence.len();
+            offsets.push(offset);
+        }
+
+        // Runs that wrap around the origin of a circular path end past
+        // the length of the path
+        let offset_at = |ix: usize| {
+            if ix > len {
+                offsets[len] + offsets[ix - len]
+            } else {
+                offsets[ix]
+            }
+        };
+
+        runs.iter()
+            .filter(|run| !run.is_empty())
+            .map(|run| {
+                let name = subpath_name(
+                    &path.name,
+                    offset_at(run.start),
+                    offset_at(run.end),
+                );
+                let steps = run
+                    .clone()
+                    .map(|ix| path.nodes[ix % len])
+                    .collect::<Vec<_>>();
+                (name, steps)
+            })
+            .collect()
+    }
+
+    /// Check that the edges, paths, and node occurrences of the graph
+    /// are consistent with each other, and return a report of every
+    /// inconsistency that was found.
+    ///
+    /// Nodes only store one occurrence per path, so a step is only
+    /// missing its occurrence if its node has no occurrence at all on
+    /// the step's path.
+    pub fn validate(&self) -> ValidationReport<PathId> {
+        let mut report = ValidationReport::new();
+
+        report.check_edges(self);
+
+        let mut path_ids = self.paths.keys().copied().collect::<Vec<_>>();
+        path_ids.sort_unstable();
+
+        for path_id in path_ids {
+            let path = &self.paths[&path_id];
+
+            report.check_path_steps(
+                self,
+                path_id,
+                path.is_circular,
+                path.nodes.iter().copied(),
+            );
+
+            for &handle in path.nodes.iter() {
+                if let Some(node) = self.graph.get(&handle.id()) {
+                    if !node.occurrences.contains_key(&path_id) {
+                        report.push(ValidationIssue::MissingOccurrence {
+                            path: path_id,
+                            handle,
+                        });
+                    }
+                }
+            }
+        }
+
+        let mut node_ids = self.graph.keys().copied().collect::<Vec<_>>();
+        node_ids.sort_unstable();
+
+        for node_id in node_ids {
+            let node = &self.graph[&node_id];
+
+            let mut occurrences = node.occurrences.iter().collect::<Vec<_>>();
+            occurrences.sort_unstable();
+
+            for (&path_id, &ix) in occurrences {
+                let on_node = self
+                    .paths
+                    .get(&path_id)
+                    .and_then(|path| path.nodes.get(ix))
+                    .is_some_and(|handle| handle.id() == node_id);
+
+                if !on_node {
+                    report.push(ValidationIssue::InvalidOccurrence {
+                        path: path_id,
+                        node: node_id,
+                    });
+                }
+            }
+        }
+
+        report
     }
 }
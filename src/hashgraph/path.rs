@@ -5,35 +5,34 @@ use fnv::FnvHashMap;
 
 use crate::handle::{Handle, NodeId};
 
-// use crate::pathhandlegraph::{PathBase, PathRef, PathRefMut};
+use crate::packed::OneBasedIndex;
+use crate::pathhandlegraph::{
+    step::{PathStep, PathStepIx},
+    PathBase, PathRef, PathRefMut, StepUpdate,
+};
 
-use super::Node;
-
-pub type PathId = i64;
-
-#[derive(Debug, Clone, PartialEq, Eq)]
-pub enum PathStep {
-    Front(i64),
-    End(i64),
-    Step(i64, usize),
-}
+pub use crate::pathhandlegraph::{PathId, StepHandle};
 
-impl PathStep {
-    pub fn index(&self) -> Option<usize> {
-        if let Self::Step(_, ix) = self {
-            Some(*ix)
-        } else {
-            None
-        }
-    }
+use super::Node;
 
-    pub fn path_id(&self) -> PathId {
-        match self {
-            Self::Front(i) => *i,
-            Self::End(i) => *i,
-            Self::Step(i, _) => *i,
-        }
-    }
+/// How `HashGraph` treats the paths that visit a node, or traverse
+/// an edge, when the node or edge is removed.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum PathRemoval {
+    /// Destroy every path that uses the node or edge, like
+    /// `PackedGraph` does.
+    #[default]
+    DestroyPaths,
+    /// Refuse to remove nodes and edges that are used by a path.
+    Refuse,
+    /// Remove the affected steps, and split each path into subpaths
+    /// around them, named `name[start-end]` after the bases of the
+    /// original path they cover. Circular paths become linear, and
+    /// the steps on either side of their origin stay in the same
+    /// subpath, whose end is then past the length of the path. If a
+    /// subpath name is already used by another path, the node or
+    /// edge isn't removed.
+    SplitPaths,
 }
 
 #[derive(Debug)]
@@ -44,44 +43,32 @@ pub struct Path {
     pub nodes: Vec<Handle>,
 }
 
-/*
-pub struct PathStepIter<'a> {
-    handles: std::slice::Iter<'a, Handle>,
-    index: usize,
-}
-
-impl<'a> PathStepIter<'a> {
-    fn new(nodes: &'a [Handle]) -> PathStepIter<'a> {
-        let handles = nodes.iter();
-        Self { handles, index: 0 }
+impl crate::pathhandlegraph::PathStep for (PathStepIx, Handle) {
+    fn handle(&self) -> Handle {
+        self.1
     }
 }
 
-impl<'a> Iterator for PathStepIter<'a> {
-    type Item = crate::pathhandlegraph::PathStep;
-
-    fn next(&mut self) -> Option<Self::Item> {
-        let _handle = self.handles.next()?;
-        let ix = self.index;
-        let item = crate::pathhandlegraph::PathStep::Step(ix);
-        self.index += 1;
-        Some(item)
-    }
+// The steps of a `Path` are indexed by their position in the path,
+// using the same step index type as `PackedGraph`
+fn path_step((ix, handle): (usize, Handle)) -> (PathStepIx, Handle) {
+    (PathStepIx::from_zero_based(ix), handle)
 }
-*/
-
-/*
-use crate::pathhandlegraph::PathStep as PStep;
 
 impl PathBase for Path {
-    type Step = PStep;
+    type Step = (PathStepIx, Handle);
+
+    type StepIx = PathStepIx;
 }
 
 impl<'a> PathRef for &'a Path {
-    type Steps = PathStepIter<'a>;
+    type Steps = std::iter::Map<
+        std::iter::Enumerate<std::iter::Copied<std::slice::Iter<'a, Handle>>>,
+        fn((usize, Handle)) -> (PathStepIx, Handle),
+    >;
 
     fn steps(self) -> Self::Steps {
-        PathStepIter::new(&self.nodes)
+        self.nodes.iter().copied().enumerate().map(path_step)
     }
 
     fn len(self) -> usize {
@@ -92,9 +79,34 @@ impl<'a> PathRef for &'a Path {
         self.is_circular
     }
 
-    fn handle_at(self, step: PStep) -> Option<Handle> {
-        if let PStep::Step(ix) = step {
-            self.nodes.get(ix).copied()
+    fn first_step(self) -> Self::Step {
+        assert!(!self.nodes.is_empty(), "first_step called on an empty path");
+        path_step((0, self.nodes[0]))
+    }
+
+    fn last_step(self) -> Self::Step {
+        assert!(!self.nodes.is_empty(), "last_step called on an empty path");
+        let ix = self.nodes.len() - 1;
+        path_step((ix, self.nodes[ix]))
+    }
+
+    fn next_step(self, (ix, _): Self::Step) -> Option<Self::Step> {
+        let ix = ix.to_zero_based()?;
+        if ix + 1 < self.nodes.len() {
+            Some(path_step((ix + 1, self.nodes[ix + 1])))
+        } else if self.is_circular && !self.nodes.is_empty() {
+            Some(self.first_step())
+        } else {
+            None
+        }
+    }
+
+    fn prev_step(self, (ix, _): Self::Step) -> Option<Self::Step> {
+        let ix = ix.to_zero_based()?;
+        if ix > 0 {
+            Some(path_step((ix - 1, self.nodes[ix - 1])))
+        } else if self.is_circular && !self.nodes.is_empty() {
+            Some(self.last_step())
         } else {
             None
         }
@@ -103,28 +115,79 @@ impl<'a> PathRef for &'a Path {
     fn contains(self, handle: Handle) -> bool {
         self.nodes.contains(&handle)
     }
+}
 
-    // fn next_step(self, step: PStep) -> Option<PStep> {
+/// A mutable reference to a path in a `HashGraph`, that also updates
+/// the occurrences of the nodes on the path as it's modified.
+pub struct HashGraphPathMut<'a> {
+    path: &'a mut Path,
+    graph: &'a mut FnvHashMap<NodeId, Node>,
 }
 
-impl<'a> PathRefMut for &'a mut Path {
-    fn append(self, handle: Handle) -> PStep {
-        let new_step = PStep::Step(self.nodes.len());
-        self.nodes.push(handle);
-        new_step
+impl<'a> HashGraphPathMut<'a> {
+    pub(super) fn new(
+        path: &'a mut Path,
+        graph: &'a mut FnvHashMap<NodeId, Node>,
+    ) -> Self {
+        Self { path, graph }
     }
 
-    fn prepend(self, handle: Handle) -> PStep {
-        let new_step = PStep::Step(0);
-        self.nodes.insert(0, handle);
-        new_step
+    // Point the occurrences of the nodes on the path to their steps,
+    // after steps have been inserted or removed
+    fn update_occurrences(&mut self) {
+        let path_id = self.path.path_id;
+        for (ix, handle) in self.path.nodes.iter().enumerate() {
+            if let Some(node) = self.graph.get_mut(&handle.id()) {
+                node.occurrences.insert(path_id, ix);
+            }
+        }
     }
+}
+
+impl<'a> PathBase for HashGraphPathMut<'a> {
+    type Step = (PathStepIx, Handle);
+
+    type StepIx = PathStepIx;
+}
 
-    fn set_circularity(self, circular: bool) {
-        self.is_circular = circular;
+impl<'a> PathRefMut for HashGraphPathMut<'a> {
+    fn append_step(&mut self, handle: Handle) -> StepUpdate<PathStepIx> {
+        let ix = self.path.nodes.len();
+        self.path.nodes.push(handle);
+        if let Some(node) = self.graph.get_mut(&handle.id()) {
+            node.occurrences.insert(self.path.path_id, ix);
+        }
+        let step = PathStepIx::from_zero_based(ix);
+        StepUpdate::Insert { handle, step }
+    }
+
+    fn prepend_step(&mut self, handle: Handle) -> StepUpdate<PathStepIx> {
+        self.path.nodes.insert(0, handle);
+        self.update_occurrences();
+        let step = PathStepIx::from_zero_based(0usize);
+        StepUpdate::Insert { handle, step }
+    }
+
+    fn remove_step(
+        &mut self,
+        step: PathStepIx,
+    ) -> Option<StepUpdate<PathStepIx>> {
+        let ix = step.to_zero_based()?;
+        if ix >= self.path.nodes.len() {
+            return None;
+        }
+        let handle = self.path.nodes.remove(ix);
+        if let Some(node) = self.graph.get_mut(&handle.id()) {
+            node.occurrences.remove(&self.path.path_id);
+        }
+        self.update_occurrences();
+        Some(StepUpdate::Remove { handle, step })
+    }
+
+    fn set_circularity(&mut self, circular: bool) {
+        self.path.is_circular = circular;
     }
 }
-*/
 
 impl Path {
     pub fn new<T: Into<BString>>(
@@ -157,27 +220,24 @@ impl Path {
             .sum()
     }
 
-    pub fn lookup_step_handle(&self, step: &PathStep) -> Option<Handle> {
-        match step {
-            PathStep::Front(_) => None,
-            PathStep::End(_) => None,
-            PathStep::Step(_, ix) => Some(self.nodes[*ix]),
-        }
+    pub fn lookup_step_handle(&self, step: &StepHandle) -> Option<Handle> {
+        let ix = step.step().index()?;
+        self.nodes.get(ix).copied()
     }
 
     pub fn position_of_step(
         &self,
         graph: &FnvHashMap<NodeId, Node>,
-        step: &PathStep,
+        step: &StepHandle,
     ) -> Option<usize> {
         if step.path_id() != self.path_id {
             return None;
         }
 
-        match step {
-            PathStep::Front(_) => Some(0),
-            PathStep::End(_) => Some(self.bases_len(graph)),
-            &PathStep::Step(_, step_ix) => {
+        match step.step() {
+            PathStep::Before => Some(0),
+            PathStep::After => Some(self.bases_len(graph)),
+            PathStep::Step(step_ix) => {
                 let mut bases = 0;
                 for handle in self.nodes[0..step_ix].iter() {
                     let node = graph.get(&handle.id())?;
@@ -188,24 +248,23 @@ impl Path {
         }
     }
 
+    /// Get the step that covers the base at `pos`, or the end of the
+    /// path if it's shorter than that. Position 0 is the first base
+    /// of the first step, matching `position_of_step` and PackedGraph.
     pub fn step_at_position(
         &self,
         graph: &FnvHashMap<NodeId, Node>,
         pos: usize,
-    ) -> PathStep {
-        if pos == 0 {
-            return PathStep::Front(self.path_id);
-        }
-
+    ) -> StepHandle {
         let mut bases = 0;
         for (ix, handle) in self.nodes.iter().enumerate() {
             let node = graph.get(&handle.id()).unwrap();
             bases += node.sequence.len();
             if pos < bases {
-                return PathStep::Step(self.path_id, ix);
+                return StepHandle::new(self.path_id, PathStep::Step(ix));
             }
         }
 
-        PathStep::End(self.path_id)
+        StepHandle::new(self.path_id, PathStep::After)
     }
 }
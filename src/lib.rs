@@ -1,4 +1,5 @@
 pub mod conversion;
+pub mod error;
 pub mod handle;
 pub mod handlegraph;
 pub mod hashgraph;
@@ -7,3 +8,5 @@ pub mod packed;
 pub mod packedgraph;
 pub mod pathgraph;
 pub mod pathhandlegraph;
+pub mod subgraph;
+pub mod validation;
@@ -1,3 +1,4 @@
+use crate::error::{HandleGraphError, HandleGraphResult};
 use crate::handle::{Edge, Handle, NodeId};
 use crate::handlegraph::{HandleGraph, HandleGraphRef};
 
@@ -12,13 +13,36 @@ pub trait SubtractiveHandleGraph {
 pub trait AdditiveHandleGraph {
     fn append_handle(&mut self, seq: &[u8]) -> Handle;
 
+    /// Create a node with the given sequence and ID. Fails if the ID
+    /// is 0 or already in the graph, or if the sequence is empty.
+    fn try_create_handle<T: Into<NodeId>>(
+        &mut self,
+        seq: &[u8],
+        node_id: T,
+    ) -> HandleGraphResult<Handle>;
+
+    /// Create an edge between two handles. Fails if either of the
+    /// nodes doesn't exist.
+    fn try_create_edge(&mut self, edge: Edge) -> HandleGraphResult<()>;
+
+    /// Like `try_create_handle`, but panics on failure.
     fn create_handle<T: Into<NodeId>>(
         &mut self,
         seq: &[u8],
         node_id: T,
-    ) -> Handle;
+    ) -> Handle {
+        match self.try_create_handle(seq, node_id) {
+            Ok(handle) => handle,
+            Err(err) => panic!("{}", err),
+        }
+    }
 
-    fn create_edge(&mut self, edge: Edge);
+    /// Like `try_create_edge`, but panics on failure.
+    fn create_edge(&mut self, edge: Edge) {
+        if let Err(err) = self.try_create_edge(edge) {
+            panic!("{}", err);
+        }
+    }
 }
 
 pub trait ModdableHandleGraph {
@@ -55,11 +79,28 @@ pub trait MutableHandleGraph: HandleGraph {
     fn create_edge(&mut self, edge: Edge);
     */
 
+    /// Divide the node of the handle at the given offsets, which are
+    /// relative to the orientation of the handle, returning the
+    /// handles of the new nodes in order. Fails if the node doesn't
+    /// exist, or if the offsets aren't strictly increasing and within
+    /// the node.
+    fn try_divide_handle(
+        &mut self,
+        handle: Handle,
+        offsets: Vec<usize>,
+    ) -> HandleGraphResult<Vec<Handle>>;
+
+    /// Like `try_divide_handle`, but panics on failure.
     fn divide_handle(
         &mut self,
         handle: Handle,
         offsets: Vec<usize>,
-    ) -> Vec<Handle>;
+    ) -> Vec<Handle> {
+        match self.try_divide_handle(handle, offsets) {
+            Ok(handles) => handles,
+            Err(err) => panic!("{}", err),
+        }
+    }
 
     fn split_handle(
         &mut self,
@@ -81,3 +122,21 @@ pub trait MutableHandleGraph: HandleGraph {
 //     &'a T: HandleGraphRef,
 // {
 // }
+
+/// Check that the offsets can be used to divide a node of length
+/// `node_len`, i.e. that they are strictly increasing and within the
+/// node.
+pub(crate) fn validate_offsets(
+    handle: Handle,
+    node_len: usize,
+    offsets: &[usize],
+) -> HandleGraphResult<()> {
+    let mut prev = 0;
+    for &offset in offsets.iter() {
+        if offset <= prev || offset >= node_len {
+            return Err(HandleGraphError::InvalidOffset { handle, offset });
+        }
+        prev = offset;
+    }
+    Ok(())
+}
@@ -1,12 +1,16 @@
 pub mod deque;
+pub mod index;
 pub mod paged;
 pub mod robust;
+pub mod serialize;
 pub mod traits;
 pub mod vector;
+pub mod view;
 
 pub use self::{
-    deque::PackedDeque, paged::PagedIntVec, robust::RobustPagedIntVec,
-    traits::*, vector::PackedIntVec,
+    deque::PackedDeque, index::OneBasedIndex, paged::PagedIntVec,
+    robust::RobustPagedIntVec, serialize::Serializable, traits::*,
+    vector::PackedIntVec, view::PackedCollectionView,
 };
 
 #[macro_export]
@@ -28,3 +32,19 @@ macro_rules! impl_space_usage {
         }
     };
 }
+
+#[macro_export]
+macro_rules! impl_space_usage_stack_newtype {
+    ($type:ty) => {
+        impl succinct::SpaceUsage for $type {
+            #[inline]
+            fn is_stack_only() -> bool {
+                true
+            }
+            #[inline]
+            fn heap_bytes(&self) -> usize {
+                0
+            }
+        }
+    };
+}
@@ -1,7 +1,10 @@
+use std::io::{self, Read, Write};
+
 use succinct::SpaceUsage;
 
 use super::vector::PackedIntVec;
 
+use super::serialize::*;
 use super::traits::*;
 
 use quickcheck::{Arbitrary, Gen};
@@ -22,7 +25,7 @@ impl PackedDeque {
         Default::default()
     }
 
-    pub(super) fn reserve(&mut self, capacity: usize) {
+    pub(crate) fn reserve(&mut self, capacity: usize) {
         if capacity > self.vector.len() {
             let mut vector = PackedIntVec::new();
             vector.resize(capacity);
@@ -93,6 +96,17 @@ impl PackedDeque {
         Iter::new(self)
     }
 
+    /// Iterate through the values from index `start` up to, but not
+    /// including, `end`.
+    pub fn iter_range(&self, start: usize, end: usize) -> Iter<'_> {
+        let len = end.min(self.len());
+        Iter {
+            deque: self,
+            index: start.min(len),
+            len,
+        }
+    }
+
     #[inline]
     fn contract(&mut self) {
         let capacity = self.vector.len() as f64 / Self::FACTOR.powi(2);
@@ -204,6 +218,28 @@ impl std::iter::FromIterator<u64> for PackedDeque {
     }
 }
 
+impl Serializable for PackedDeque {
+    fn serialize<W: Write>(&self, writer: &mut W) -> io::Result<()> {
+        write_usize(writer, self.start_ix)?;
+        write_usize(writer, self.num_entries)?;
+        self.vector.serialize(writer)
+    }
+
+    fn deserialize<R: Read>(reader: &mut R) -> io::Result<Self> {
+        let start_ix = read_usize(reader)?;
+        let num_entries = read_usize(reader)?;
+        let vector = PackedIntVec::deserialize(reader)?;
+
+        check_deque_layout(start_ix, num_entries, vector.len())?;
+
+        Ok(PackedDeque {
+            vector,
+            start_ix,
+            num_entries,
+        })
+    }
+}
+
 impl Arbitrary for PackedDeque {
     fn arbitrary<G: Gen>(g: &mut G) -> PackedDeque {
         let front: Vec<u64> = Vec::arbitrary(g);
@@ -287,4 +323,17 @@ mod tests {
             }
         }
     }
+
+    quickcheck! {
+        fn prop_deque_serialize(deque: PackedDeque) -> bool {
+            let mut bytes: Vec<u8> = Vec::new();
+            deque.serialize(&mut bytes).unwrap();
+
+            let deserialized =
+                PackedDeque::deserialize(&mut bytes.as_slice()).unwrap();
+
+            deserialized.len() == deque.len()
+                && (0..deque.len()).all(|ix| deserialized.get(ix) == deque.get(ix))
+        }
+    }
 }
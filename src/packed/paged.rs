@@ -1,7 +1,10 @@
+use std::io::{self, Read, Write};
+
 use succinct::SpaceUsage;
 
 use super::vector::PackedIntVec;
 
+use super::serialize::*;
 use super::traits::*;
 
 use quickcheck::{Arbitrary, Gen};
@@ -89,7 +92,7 @@ impl PagedIntVec {
 
     #[allow(clippy::wrong_self_convention)]
     #[inline]
-    const fn from_diff(diff: u64, anchor: u64) -> u64 {
+    pub(super) const fn from_diff(diff: u64, anchor: u64) -> u64 {
         if diff == 0 {
             0
         } else if diff % 5 == 0 {
@@ -173,6 +176,43 @@ impl PackedCollection for PagedIntVec {
     }
 }
 
+impl Serializable for PagedIntVec {
+    fn serialize<W: Write>(&self, writer: &mut W) -> io::Result<()> {
+        write_usize(writer, self.page_size)?;
+        write_usize(writer, self.num_entries)?;
+        self.anchors.serialize(writer)?;
+        write_usize(writer, self.pages.len())?;
+        for page in self.pages.iter() {
+            page.serialize(writer)?;
+        }
+        Ok(())
+    }
+
+    fn deserialize<R: Read>(reader: &mut R) -> io::Result<Self> {
+        let page_size = read_usize(reader)?;
+        let num_entries = read_usize(reader)?;
+        let anchors = PackedIntVec::deserialize(reader)?;
+        let num_pages = read_usize(reader)?;
+
+        check_paged_layout(page_size, num_entries, num_pages, anchors.len())?;
+
+        let pages = (0..num_pages)
+            .map(|_| PackedIntVec::deserialize(reader))
+            .collect::<io::Result<Vec<_>>>()?;
+
+        if pages.iter().any(|page| page.len() != page_size) {
+            return Err(invalid_data("invalid PagedIntVec page length"));
+        }
+
+        Ok(PagedIntVec {
+            page_size,
+            num_entries,
+            anchors,
+            pages,
+        })
+    }
+}
+
 impl Arbitrary for PagedIntVec {
     fn arbitrary<G: Gen>(g: &mut G) -> PagedIntVec {
         let mut paged = PagedIntVec::new(64);
@@ -244,4 +284,18 @@ mod tests {
             len_correct
         }
     }
+
+    quickcheck! {
+        fn prop_paged_serialize(paged: PagedIntVec) -> bool {
+            let mut bytes: Vec<u8> = Vec::new();
+            paged.serialize(&mut bytes).unwrap();
+
+            let deserialized =
+                PagedIntVec::deserialize(&mut bytes.as_slice()).unwrap();
+
+            deserialized.len() == paged.len()
+                && deserialized.page_width() == paged.page_width()
+                && (0..paged.len()).all(|ix| deserialized.get(ix) == paged.get(ix))
+        }
+    }
 }
@@ -1,8 +1,11 @@
+use std::io::{self, Read, Write};
+
 use succinct::SpaceUsage;
 
 use super::paged::PagedIntVec;
 use super::vector::PackedIntVec;
 
+use super::serialize::*;
 use super::traits::*;
 
 use quickcheck::{Arbitrary, Gen};
@@ -36,8 +39,7 @@ impl RobustPagedIntVec {
         }
     }
 
-    #[allow(dead_code)]
-    pub(super) fn reserve(&mut self, capacity: usize) {
+    pub(crate) fn reserve(&mut self, capacity: usize) {
         if capacity > self.page_width() {
             self.first_page.reserve(self.page_width());
             self.other_pages.reserve(capacity - self.page_width());
@@ -106,6 +108,22 @@ impl PackedCollection for RobustPagedIntVec {
     }
 }
 
+impl Serializable for RobustPagedIntVec {
+    fn serialize<W: Write>(&self, writer: &mut W) -> io::Result<()> {
+        self.first_page.serialize(writer)?;
+        self.other_pages.serialize(writer)
+    }
+
+    fn deserialize<R: Read>(reader: &mut R) -> io::Result<Self> {
+        let first_page = PackedIntVec::deserialize(reader)?;
+        let other_pages = PagedIntVec::deserialize(reader)?;
+        Ok(RobustPagedIntVec {
+            first_page,
+            other_pages,
+        })
+    }
+}
+
 impl Arbitrary for RobustPagedIntVec {
     fn arbitrary<G: Gen>(g: &mut G) -> RobustPagedIntVec {
         let only_first = bool::arbitrary(g);
@@ -186,4 +204,17 @@ mod tests {
             }
         }
     }
+
+    quickcheck! {
+        fn prop_robust_serialize(robust: RobustPagedIntVec) -> bool {
+            let mut bytes: Vec<u8> = Vec::new();
+            robust.serialize(&mut bytes).unwrap();
+
+            let deserialized =
+                RobustPagedIntVec::deserialize(&mut bytes.as_slice()).unwrap();
+
+            deserialized.len() == robust.len()
+                && (0..robust.len()).all(|ix| deserialized.get(ix) == robust.get(ix))
+        }
+    }
 }
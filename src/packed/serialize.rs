@@ -0,0 +1,268 @@
+use std::hash::Hasher;
+use std::io::{self, Read, Write};
+
+use fnv::FnvHasher;
+
+use super::traits::PackedElement;
+
+/// A type that can be written to, and read back from, the binary
+/// format used to store packed collections and the `PackedGraph` on
+/// disk.
+///
+/// Every value is written as a sequence of little-endian `u64` words,
+/// so a serialized collection is always aligned to 8 bytes, and the
+/// raw blocks of the underlying packed vectors are written verbatim.
+pub trait Serializable: Sized {
+    fn serialize<W: Write>(&self, writer: &mut W) -> io::Result<()>;
+
+    fn deserialize<R: Read>(reader: &mut R) -> io::Result<Self>;
+}
+
+#[inline]
+pub(crate) fn invalid_data(msg: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, msg)
+}
+
+#[inline]
+pub(crate) fn write_u64<W: Write>(
+    writer: &mut W,
+    value: u64,
+) -> io::Result<()> {
+    writer.write_all(&value.to_le_bytes())
+}
+
+#[inline]
+pub(crate) fn read_u64<R: Read>(reader: &mut R) -> io::Result<u64> {
+    let mut buf = [0u8; 8];
+    reader.read_exact(&mut buf)?;
+    Ok(u64::from_le_bytes(buf))
+}
+
+#[inline]
+pub(crate) fn write_usize<W: Write>(
+    writer: &mut W,
+    value: usize,
+) -> io::Result<()> {
+    write_u64(writer, value as u64)
+}
+
+#[inline]
+pub(crate) fn read_usize<R: Read>(reader: &mut R) -> io::Result<usize> {
+    use std::convert::TryFrom;
+    let value = read_u64(reader)?;
+    usize::try_from(value).map_err(|_| invalid_data("value overflows usize"))
+}
+
+/// The largest number of elements that is preallocated when reading
+/// a collection, as the length is read from input that may be
+/// corrupt, and the checksum is only verified at the end.
+const MAX_PREALLOC: usize = 1 << 16;
+
+/// Read `len` little-endian `u64` words, in chunks, so that a corrupt
+/// length fails with an `UnexpectedEof` error once the input runs
+/// out, instead of being used for a single huge allocation.
+pub(crate) fn read_u64_words<R: Read>(
+    reader: &mut R,
+    len: usize,
+) -> io::Result<Vec<u64>> {
+    const CHUNK_WORDS: usize = 1024;
+
+    let mut words = Vec::with_capacity(len.min(MAX_PREALLOC));
+    let mut buf = [0u8; CHUNK_WORDS * 8];
+    let mut remaining = len;
+
+    while remaining > 0 {
+        let chunk_len = remaining.min(CHUNK_WORDS);
+        let bytes = &mut buf[..chunk_len * 8];
+        reader.read_exact(bytes)?;
+        words.extend(bytes.chunks_exact(8).map(|word| {
+            let mut word_buf = [0u8; 8];
+            word_buf.copy_from_slice(word);
+            u64::from_le_bytes(word_buf)
+        }));
+        remaining -= chunk_len;
+    }
+
+    Ok(words)
+}
+
+/// Check the header of a serialized `PackedIntVec`. The header is
+/// read from untrusted input, so this is shared by the deserializer
+/// and `PackedIntVecView`, rather than each checking it separately.
+pub(crate) fn check_intvec_layout(
+    width: usize,
+    num_entries: usize,
+    capacity: u64,
+    block_len: usize,
+) -> io::Result<()> {
+    if width == 0 || width > 64 {
+        return Err(invalid_data("invalid PackedIntVec width"));
+    }
+
+    let max_capacity = (block_len as u64)
+        .checked_mul(64)
+        .map(|bits| bits / width as u64);
+
+    if num_entries as u64 > capacity
+        || max_capacity.is_none_or(|max| capacity > max)
+    {
+        return Err(invalid_data("invalid PackedIntVec length"));
+    }
+
+    Ok(())
+}
+
+/// Check the header of a serialized `PagedIntVec`, given the number
+/// of page anchors that were read.
+pub(crate) fn check_paged_layout(
+    page_size: usize,
+    num_entries: usize,
+    num_pages: usize,
+    num_anchors: usize,
+) -> io::Result<()> {
+    let max_entries = num_pages.checked_mul(page_size);
+
+    if page_size == 0
+        || num_pages != num_anchors
+        || max_entries.is_none_or(|max| num_entries > max)
+    {
+        return Err(invalid_data("invalid PagedIntVec layout"));
+    }
+
+    Ok(())
+}
+
+/// Check the header of a serialized `PackedDeque`, given the length
+/// of the vector that was read.
+pub(crate) fn check_deque_layout(
+    start_ix: usize,
+    num_entries: usize,
+    vector_len: usize,
+) -> io::Result<()> {
+    if num_entries > vector_len || (vector_len > 0 && start_ix >= vector_len) {
+        return Err(invalid_data("invalid PackedDeque layout"));
+    }
+
+    Ok(())
+}
+
+/// Serializes a `Vec` of packable elements as its length followed by
+/// the packed representation of each element.
+impl<T: PackedElement> Serializable for Vec<T> {
+    fn serialize<W: Write>(&self, writer: &mut W) -> io::Result<()> {
+        write_usize(writer, self.len())?;
+        for &elem in self.iter() {
+            write_u64(writer, elem.pack())?;
+        }
+        Ok(())
+    }
+
+    fn deserialize<R: Read>(reader: &mut R) -> io::Result<Self> {
+        let len = read_usize(reader)?;
+        let words = read_u64_words(reader, len)?;
+        Ok(words.into_iter().map(T::unpack).collect())
+    }
+}
+
+/// Wraps a writer, computing an FNV-1a checksum over all the bytes
+/// that are written through it.
+pub(crate) struct ChecksumWriter<W: Write> {
+    inner: W,
+    hasher: FnvHasher,
+}
+
+impl<W: Write> ChecksumWriter<W> {
+    pub(crate) fn new(inner: W) -> Self {
+        Self {
+            inner,
+            hasher: Default::default(),
+        }
+    }
+
+    pub(crate) fn checksum(&self) -> u64 {
+        self.hasher.finish()
+    }
+
+    pub(crate) fn into_inner(self) -> W {
+        self.inner
+    }
+}
+
+impl<W: Write> Write for ChecksumWriter<W> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let written = self.inner.write(buf)?;
+        self.hasher.write(&buf[..written]);
+        Ok(written)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.inner.flush()
+    }
+}
+
+/// Wraps a reader, computing an FNV-1a checksum over all the bytes
+/// that are read through it.
+pub(crate) struct ChecksumReader<R: Read> {
+    inner: R,
+    hasher: FnvHasher,
+}
+
+impl<R: Read> ChecksumReader<R> {
+    pub(crate) fn new(inner: R) -> Self {
+        Self {
+            inner,
+            hasher: Default::default(),
+        }
+    }
+
+    pub(crate) fn checksum(&self) -> u64 {
+        self.hasher.finish()
+    }
+
+    pub(crate) fn into_inner(self) -> R {
+        self.inner
+    }
+}
+
+impl<R: Read> Read for ChecksumReader<R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let read = self.inner.read(buf)?;
+        self.hasher.write(&buf[..read]);
+        Ok(read)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn checksum_matches_on_read() {
+        let mut writer = ChecksumWriter::new(Vec::new());
+        let values: Vec<u64> = vec![0, 1, 2, 123, u64::MAX];
+        values.serialize(&mut writer).unwrap();
+        let write_sum = writer.checksum();
+        let bytes = writer.into_inner();
+
+        let mut reader = ChecksumReader::new(bytes.as_slice());
+        let read_values: Vec<u64> = Vec::deserialize(&mut reader).unwrap();
+        let read_sum = reader.checksum();
+
+        assert_eq!(values, read_values);
+        assert_eq!(write_sum, read_sum);
+    }
+
+    #[test]
+    fn corrupt_lengths_fail_to_read() {
+        let mut bytes = Vec::new();
+        write_u64(&mut bytes, u64::MAX).unwrap();
+        write_u64(&mut bytes, 1).unwrap();
+
+        let err = Vec::<u64>::deserialize(&mut bytes.as_slice()).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::UnexpectedEof);
+
+        let words = read_u64_words(&mut &bytes[..], 2).unwrap();
+        assert_eq!(words, vec![u64::MAX, 1]);
+        assert!(read_u64_words(&mut &bytes[..], 3).is_err());
+    }
+}
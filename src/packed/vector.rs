@@ -1,5 +1,8 @@
-use succinct::{IntVec, IntVecMut, IntVector, SpaceUsage};
+use std::io::{self, Read, Write};
 
+use succinct::{BitVec, BitVecMut, IntVec, IntVecMut, IntVector, SpaceUsage};
+
+use super::serialize::*;
 use super::traits::*;
 
 use quickcheck::{Arbitrary, Gen};
@@ -67,20 +70,19 @@ impl PackedIntVec {
         self.num_entries = size;
     }
 
-    pub(super) fn reserve(&mut self, size: usize) {
+    pub(crate) fn reserve(&mut self, size: usize) {
         if size > self.vector.len() as usize {
             self.vector.resize(size as u64, 0);
         }
     }
 
     pub fn iter(&self) -> Iter<'_> {
-        let iter = self.vector.iter();
-        Iter::new(iter, self.num_entries)
+        Iter::new(&self.vector, 0, self.num_entries)
     }
 
     pub fn iter_slice(&self, offset: usize, length: usize) -> Iter<'_> {
-        let iter = self.vector.iter();
-        Iter::offset_new(iter, offset, length)
+        assert!(offset + length <= self.num_entries);
+        Iter::new(&self.vector, offset, length)
     }
 }
 
@@ -137,41 +139,68 @@ impl PackedCollection for PackedIntVec {
     }
 }
 
-pub struct Iter<'a> {
-    iter: succinct::int_vec::Iter<'a, u64>,
-    left_ix: usize,
-    right_ix: usize,
-}
+impl Serializable for PackedIntVec {
+    fn serialize<W: Write>(&self, writer: &mut W) -> io::Result<()> {
+        write_usize(writer, self.width)?;
+        write_usize(writer, self.num_entries)?;
+        write_u64(writer, self.vector.len())?;
 
-impl<'a> Iter<'a> {
-    fn new(iter: succinct::int_vec::Iter<'a, u64>, num_entries: usize) -> Self {
-        let left_ix = 0;
-        let right_ix = num_entries;
-        Self {
-            iter,
-            left_ix,
-            right_ix,
+        let block_len = self.vector.block_len();
+        write_usize(writer, block_len)?;
+
+        let mut bytes = Vec::with_capacity(block_len * 8);
+        for ix in 0..block_len {
+            bytes.extend_from_slice(&self.vector.get_block(ix).to_le_bytes());
         }
+        writer.write_all(&bytes)
     }
 
-    fn offset_new(
-        mut iter: succinct::int_vec::Iter<'a, u64>,
-        offset: usize,
-        length: usize,
-    ) -> Self {
-        let drop_right = iter.len() - (offset + length);
-        for _ in 0..drop_right {
-            iter.next_back();
+    fn deserialize<R: Read>(reader: &mut R) -> io::Result<Self> {
+        let width = read_usize(reader)?;
+        let num_entries = read_usize(reader)?;
+        let capacity = read_u64(reader)?;
+        let block_len = read_usize(reader)?;
+
+        check_intvec_layout(width, num_entries, capacity, block_len)?;
+
+        // Only allocate the vector once all the blocks have been read,
+        // so a corrupt block length can't cause a huge allocation
+        let blocks = read_u64_words(reader, block_len)?;
+
+        let mut vector: IntVector<u64> =
+            IntVector::block_with_fill(width, block_len, 0);
+        for (ix, &block) in blocks.iter().enumerate() {
+            vector.set_block(ix, block);
         }
-        let left_ix = offset;
-        let right_ix = offset + length;
-        for _ in 0..offset {
-            iter.next();
+        vector.truncate(capacity);
+
+        if vector.block_len() != block_len {
+            return Err(invalid_data("invalid PackedIntVec length"));
         }
+
+        Ok(PackedIntVec {
+            vector,
+            num_entries,
+            width,
+        })
+    }
+}
+
+/// Iterator over a range of the elements of a `PackedIntVec`. Each
+/// element is looked up by index, so creating an iterator over a
+/// slice, `nth`, and iterating from the back are all constant-time.
+pub struct Iter<'a> {
+    vector: &'a IntVector<u64>,
+    left_ix: usize,
+    right_ix: usize,
+}
+
+impl<'a> Iter<'a> {
+    fn new(vector: &'a IntVector<u64>, offset: usize, length: usize) -> Self {
         Self {
-            iter,
-            left_ix,
-            right_ix,
+            vector,
+            left_ix: offset,
+            right_ix: offset + length,
         }
     }
 
@@ -183,63 +212,72 @@ impl<'a> Iter<'a> {
 impl<'a> Iterator for Iter<'a> {
     type Item = u64;
 
+    #[inline]
     fn next(&mut self) -> Option<u64> {
         if self.left_ix < self.right_ix {
-            let item = self.iter.next();
+            let item = self.vector.get(self.left_ix as u64);
             self.left_ix += 1;
-            item
+            Some(item)
         } else {
             None
         }
     }
 
+    #[inline]
     fn size_hint(&self) -> (usize, Option<usize>) {
-        let lower = if self.left_ix < self.right_ix {
-            self.right_ix - self.left_ix
-        } else {
-            0
-        };
-        let upper = Some(lower);
-        (lower, upper)
+        let len = self.right_ix.saturating_sub(self.left_ix);
+        (len, Some(len))
     }
 
+    #[inline]
     fn count(self) -> usize {
-        if self.left_ix < self.right_ix {
-            self.right_ix - self.left_ix
-        } else {
-            0
-        }
+        self.len()
     }
 
+    #[inline]
     fn last(mut self) -> Option<u64> {
-        if self.left_ix < self.right_ix {
-            self.iter.nth(self.right_ix - self.left_ix)
-        } else {
-            None
-        }
+        self.next_back()
     }
 
+    #[inline]
     fn nth(&mut self, n: usize) -> Option<u64> {
-        if self.left_ix + n < self.right_ix {
-            self.iter.nth(n)
+        if n < self.len() {
+            self.left_ix += n;
+            self.next()
         } else {
+            self.left_ix = self.right_ix;
             None
         }
     }
 }
 
 impl<'a> DoubleEndedIterator for Iter<'a> {
+    #[inline]
     fn next_back(&mut self) -> Option<u64> {
-        if self.right_ix > self.left_ix {
-            let item = self.iter.next_back();
-            self.right_ix += 1;
-            item
+        if self.left_ix < self.right_ix {
+            self.right_ix -= 1;
+            Some(self.vector.get(self.right_ix as u64))
+        } else {
+            None
+        }
+    }
+
+    #[inline]
+    fn nth_back(&mut self, n: usize) -> Option<u64> {
+        if n < self.len() {
+            self.right_ix -= n;
+            self.next_back()
         } else {
+            self.right_ix = self.left_ix;
             None
         }
     }
 }
 
+impl<'a> ExactSizeIterator for Iter<'a> {}
+
+impl<'a> std::iter::FusedIterator for Iter<'a> {}
+
 pub struct IterView<'a, T: PackedElement> {
     iter: Iter<'a>,
     _element: std::marker::PhantomData<T>,
@@ -408,4 +446,68 @@ mod tests {
             vector.into_iter().eq(intvec.iter())
         }
     }
+
+    quickcheck! {
+        fn prop_intvec_iter_slice(
+            vector: Vec<u64>,
+            offset: usize,
+            length: usize
+        ) -> bool {
+            let intvec = vector.iter().copied().collect::<PackedIntVec>();
+
+            let offset = offset % (vector.len() + 1);
+            let length = length % (vector.len() - offset + 1);
+            let slice = &vector[offset..offset + length];
+
+            let mut iter = intvec.iter_slice(offset, length);
+            let mut from_back = intvec.iter_slice(offset, length);
+
+            iter.len() == length
+                && intvec.iter_slice(offset, length).eq(slice.iter().copied())
+                && from_back.next_back() == slice.last().copied()
+                && from_back.rev().eq(slice.iter().rev().skip(1).copied())
+                && iter.nth(length / 2) == slice.get(length / 2).copied()
+                && iter.eq(slice.iter().skip(length / 2 + 1).copied())
+        }
+    }
+
+    quickcheck! {
+        fn prop_intvec_serialize(intvec: PackedIntVec) -> bool {
+            let mut bytes: Vec<u8> = Vec::new();
+            intvec.serialize(&mut bytes).unwrap();
+
+            let deserialized =
+                PackedIntVec::deserialize(&mut bytes.as_slice()).unwrap();
+
+            deserialized == intvec
+                && deserialized.len() == intvec.len()
+                && deserialized.width() == intvec.width()
+                && deserialized.iter().eq(intvec.iter())
+        }
+    }
+
+    #[test]
+    fn intvec_deserialize_corrupt_header() {
+        let header = |block_len: u64| {
+            let mut bytes = Vec::new();
+            for &value in [8, 1, 8, block_len, 7].iter() {
+                write_u64(&mut bytes, value).unwrap();
+            }
+            bytes
+        };
+
+        let read = |bytes: Vec<u8>| {
+            PackedIntVec::deserialize(&mut bytes.as_slice()).map(|v| v.len())
+        };
+
+        assert_eq!(read(header(1)).unwrap(), 1);
+
+        // The capacity check would overflow
+        let err = read(header(u64::MAX)).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+
+        // The block length is far larger than the input
+        let err = read(header(1 << 40)).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::UnexpectedEof);
+    }
 }
@@ -0,0 +1,406 @@
+use std::convert::TryInto;
+use std::io;
+
+use super::paged::PagedIntVec;
+use super::serialize::{
+    check_deque_layout, check_intvec_layout, check_paged_layout, invalid_data,
+};
+use super::traits::PackedElement;
+
+/// Read-only access to a packed collection that is backed by a byte
+/// slice holding its serialized representation, e.g. a memory-mapped
+/// file written by `Serializable::serialize`.
+pub trait PackedCollectionView {
+    fn len(&self) -> usize;
+
+    #[inline]
+    fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    fn get(&self, index: usize) -> u64;
+
+    /// Get the element at `index` and unpack it.
+    #[inline]
+    fn get_unpack<T: PackedElement>(&self, index: usize) -> T {
+        T::unpack(self.get(index))
+    }
+}
+
+/// Cursor over a serialized byte slice, used to construct views into
+/// the collections stored in the slice without copying any of the
+/// packed data.
+#[derive(Debug, Clone)]
+pub struct WordCursor<'a> {
+    bytes: &'a [u8],
+    pos: usize,
+}
+
+impl<'a> WordCursor<'a> {
+    pub fn new(bytes: &'a [u8]) -> Self {
+        Self { bytes, pos: 0 }
+    }
+
+    /// Take `count` bytes from the slice, advancing the cursor.
+    pub fn take_bytes(&mut self, count: usize) -> io::Result<&'a [u8]> {
+        let end = self
+            .pos
+            .checked_add(count)
+            .filter(|&end| end <= self.bytes.len())
+            .ok_or_else(|| {
+                io::Error::new(
+                    io::ErrorKind::UnexpectedEof,
+                    "unexpected end of serialized data",
+                )
+            })?;
+        let slice = &self.bytes[self.pos..end];
+        self.pos = end;
+        Ok(slice)
+    }
+
+    /// Take `count` little-endian `u64` words from the slice,
+    /// advancing the cursor.
+    #[inline]
+    pub fn take_words(&mut self, count: usize) -> io::Result<&'a [u8]> {
+        let len = count
+            .checked_mul(8)
+            .ok_or_else(|| invalid_data("word count overflows usize"))?;
+        self.take_bytes(len)
+    }
+
+    #[inline]
+    pub fn read_u64(&mut self) -> io::Result<u64> {
+        let bytes = self.take_bytes(8)?;
+        Ok(u64::from_le_bytes(bytes.try_into().unwrap()))
+    }
+
+    #[inline]
+    pub fn read_usize(&mut self) -> io::Result<usize> {
+        use std::convert::TryFrom;
+        let value = self.read_u64()?;
+        usize::try_from(value)
+            .map_err(|_| invalid_data("value overflows usize"))
+    }
+
+    /// Skip past a serialized `Vec` of packed elements.
+    pub fn skip_vec(&mut self) -> io::Result<()> {
+        let len = self.read_usize()?;
+        self.take_words(len)?;
+        Ok(())
+    }
+
+    /// The bytes that haven't been consumed yet.
+    #[inline]
+    pub fn remaining(&self) -> &'a [u8] {
+        &self.bytes[self.pos..]
+    }
+}
+
+#[inline]
+fn word_at(words: &[u8], index: usize) -> u64 {
+    let start = index * 8;
+    u64::from_le_bytes(words[start..start + 8].try_into().unwrap())
+}
+
+/// Read-only view of a serialized `PackedIntVec`.
+#[derive(Debug, Clone, Copy)]
+pub struct PackedIntVecView<'a> {
+    blocks: &'a [u8],
+    num_entries: usize,
+    width: usize,
+}
+
+impl<'a> PackedIntVecView<'a> {
+    pub fn from_cursor(cursor: &mut WordCursor<'a>) -> io::Result<Self> {
+        let width = cursor.read_usize()?;
+        let num_entries = cursor.read_usize()?;
+        let capacity = cursor.read_u64()?;
+        let block_len = cursor.read_usize()?;
+
+        check_intvec_layout(width, num_entries, capacity, block_len)?;
+
+        let blocks = cursor.take_words(block_len)?;
+
+        Ok(Self {
+            blocks,
+            num_entries,
+            width,
+        })
+    }
+
+    #[inline]
+    pub fn width(&self) -> usize {
+        self.width
+    }
+
+    pub fn iter(&self) -> ViewIter<'_, Self> {
+        ViewIter::new(self)
+    }
+}
+
+impl<'a> PackedCollectionView for PackedIntVecView<'a> {
+    #[inline]
+    fn len(&self) -> usize {
+        self.num_entries
+    }
+
+    #[inline]
+    fn get(&self, index: usize) -> u64 {
+        assert!(index < self.num_entries);
+
+        let bit_ix = index * self.width;
+        let block_ix = bit_ix / 64;
+        let offset = bit_ix % 64;
+
+        let mut value = word_at(self.blocks, block_ix) >> offset;
+        if offset + self.width > 64 {
+            value |= word_at(self.blocks, block_ix + 1) << (64 - offset);
+        }
+
+        if self.width == 64 {
+            value
+        } else {
+            value & ((1 << self.width) - 1)
+        }
+    }
+}
+
+/// Read-only view of a serialized `PagedIntVec`.
+#[derive(Debug, Clone)]
+pub struct PagedIntVecView<'a> {
+    page_size: usize,
+    num_entries: usize,
+    anchors: PackedIntVecView<'a>,
+    pages: Vec<PackedIntVecView<'a>>,
+}
+
+impl<'a> PagedIntVecView<'a> {
+    pub fn from_cursor(cursor: &mut WordCursor<'a>) -> io::Result<Self> {
+        let page_size = cursor.read_usize()?;
+        let num_entries = cursor.read_usize()?;
+        let anchors = PackedIntVecView::from_cursor(cursor)?;
+        let num_pages = cursor.read_usize()?;
+
+        check_paged_layout(page_size, num_entries, num_pages, anchors.len())?;
+
+        let pages = (0..num_pages)
+            .map(|_| PackedIntVecView::from_cursor(cursor))
+            .collect::<io::Result<Vec<_>>>()?;
+
+        if pages.iter().any(|page| page.len() != page_size) {
+            return Err(invalid_data("invalid PagedIntVec page length"));
+        }
+
+        Ok(Self {
+            page_size,
+            num_entries,
+            anchors,
+            pages,
+        })
+    }
+
+    pub fn iter(&self) -> ViewIter<'_, Self> {
+        ViewIter::new(self)
+    }
+}
+
+impl<'a> PackedCollectionView for PagedIntVecView<'a> {
+    #[inline]
+    fn len(&self) -> usize {
+        self.num_entries
+    }
+
+    #[inline]
+    fn get(&self, index: usize) -> u64 {
+        assert!(index < self.num_entries);
+        let page_ix = index / self.page_size;
+        PagedIntVec::from_diff(
+            self.pages[page_ix].get(index % self.page_size),
+            self.anchors.get(page_ix),
+        )
+    }
+}
+
+/// Read-only view of a serialized `PackedDeque`.
+#[derive(Debug, Clone, Copy)]
+pub struct PackedDequeView<'a> {
+    vector: PackedIntVecView<'a>,
+    start_ix: usize,
+    num_entries: usize,
+}
+
+impl<'a> PackedDequeView<'a> {
+    pub fn from_cursor(cursor: &mut WordCursor<'a>) -> io::Result<Self> {
+        let start_ix = cursor.read_usize()?;
+        let num_entries = cursor.read_usize()?;
+        let vector = PackedIntVecView::from_cursor(cursor)?;
+
+        check_deque_layout(start_ix, num_entries, vector.len())?;
+
+        Ok(Self {
+            vector,
+            start_ix,
+            num_entries,
+        })
+    }
+
+    pub fn iter(&self) -> ViewIter<'_, Self> {
+        ViewIter::new(self)
+    }
+}
+
+impl<'a> PackedCollectionView for PackedDequeView<'a> {
+    #[inline]
+    fn len(&self) -> usize {
+        self.num_entries
+    }
+
+    #[inline]
+    fn get(&self, index: usize) -> u64 {
+        assert!(index < self.num_entries);
+        let ix = index + self.start_ix;
+        let ix = ix.checked_sub(self.vector.len()).unwrap_or(ix);
+        self.vector.get(ix)
+    }
+}
+
+/// Iterator over the elements of any `PackedCollectionView`.
+pub struct ViewIter<'a, V: PackedCollectionView> {
+    view: &'a V,
+    left_ix: usize,
+    right_ix: usize,
+}
+
+impl<'a, V: PackedCollectionView> ViewIter<'a, V> {
+    fn new(view: &'a V) -> Self {
+        Self {
+            view,
+            left_ix: 0,
+            right_ix: view.len(),
+        }
+    }
+}
+
+impl<'a, V: PackedCollectionView> Iterator for ViewIter<'a, V> {
+    type Item = u64;
+
+    #[inline]
+    fn next(&mut self) -> Option<u64> {
+        if self.left_ix < self.right_ix {
+            let item = self.view.get(self.left_ix);
+            self.left_ix += 1;
+            Some(item)
+        } else {
+            None
+        }
+    }
+
+    #[inline]
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        let len = self.right_ix - self.left_ix;
+        (len, Some(len))
+    }
+
+    #[inline]
+    fn nth(&mut self, n: usize) -> Option<u64> {
+        self.left_ix = self.left_ix.saturating_add(n).min(self.right_ix);
+        self.next()
+    }
+}
+
+impl<'a, V: PackedCollectionView> DoubleEndedIterator for ViewIter<'a, V> {
+    #[inline]
+    fn next_back(&mut self) -> Option<u64> {
+        if self.left_ix < self.right_ix {
+            self.right_ix -= 1;
+            Some(self.view.get(self.right_ix))
+        } else {
+            None
+        }
+    }
+}
+
+impl<'a, V: PackedCollectionView> ExactSizeIterator for ViewIter<'a, V> {}
+
+#[cfg(test)]
+mod tests {
+    use quickcheck::quickcheck;
+
+    use super::*;
+    use crate::packed::serialize::write_u64;
+    use crate::packed::*;
+
+    quickcheck! {
+        fn prop_intvec_view(intvec: PackedIntVec) -> bool {
+            let mut bytes: Vec<u8> = Vec::new();
+            intvec.serialize(&mut bytes).unwrap();
+
+            let mut cursor = WordCursor::new(&bytes);
+            let view = PackedIntVecView::from_cursor(&mut cursor).unwrap();
+
+            cursor.remaining().is_empty()
+                && view.len() == intvec.len()
+                && view.iter().eq(intvec.iter())
+        }
+    }
+
+    quickcheck! {
+        fn prop_paged_view(paged: PagedIntVec) -> bool {
+            let mut bytes: Vec<u8> = Vec::new();
+            paged.serialize(&mut bytes).unwrap();
+
+            let mut cursor = WordCursor::new(&bytes);
+            let view = PagedIntVecView::from_cursor(&mut cursor).unwrap();
+
+            cursor.remaining().is_empty()
+                && view.len() == paged.len()
+                && (0..paged.len()).all(|ix| view.get(ix) == paged.get(ix))
+        }
+    }
+
+    quickcheck! {
+        fn prop_deque_view(deque: PackedDeque) -> bool {
+            let mut bytes: Vec<u8> = Vec::new();
+            deque.serialize(&mut bytes).unwrap();
+
+            let mut cursor = WordCursor::new(&bytes);
+            let view = PackedDequeView::from_cursor(&mut cursor).unwrap();
+
+            cursor.remaining().is_empty()
+                && view.len() == deque.len()
+                && view.iter().eq(deque.iter())
+        }
+    }
+
+    #[test]
+    fn views_reject_overflowing_headers() {
+        let words = |values: &[u64]| {
+            let mut bytes = Vec::new();
+            for &value in values.iter() {
+                write_u64(&mut bytes, value).unwrap();
+            }
+            bytes
+        };
+
+        let intvec = |bytes: &[u8]| {
+            let mut cursor = WordCursor::new(bytes);
+            PackedIntVecView::from_cursor(&mut cursor).map(|v| v.len())
+        };
+
+        assert_eq!(intvec(&words(&[8, 1, 8, 1, 7])).unwrap(), 1);
+
+        // width, num_entries, capacity, block_len
+        let err = intvec(&words(&[8, 1, 8, u64::MAX])).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+
+        let err = intvec(&words(&[8, 1, 8, 1 << 40])).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::UnexpectedEof);
+
+        // page_size, num_entries, four anchors, and num_pages, so that
+        // num_pages * page_size overflows
+        let paged = words(&[1 << 62, 1 << 63, 1, 4, 64, 1, 0, 4]);
+        let mut cursor = WordCursor::new(&paged);
+        let err = PagedIntVecView::from_cursor(&mut cursor).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+    }
+}
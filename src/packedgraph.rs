@@ -1,25 +1,35 @@
 use crate::{
+    error::{HandleGraphError, HandleGraphResult},
     handle::{Direction, Edge, Handle, NodeId},
     handlegraph::*,
     mutablehandlegraph::{
-        AdditiveHandleGraph, MutableHandleGraph, SubtractiveHandleGraph,
+        validate_offsets, AdditiveHandleGraph, MutableHandleGraph,
+        SubtractiveHandleGraph,
     },
 };
 
 use crate::pathhandlegraph::{
-    AllPathIds, AllPathRefs, AllPathRefsMut, EmbeddedPaths, HandleOccurrences,
-    MutEmbeddedPaths, MutHandleOccurrences, OccurBase, PathId, PathNames,
-    PathNamesMut, PathRef, PathRefMut, PathRefs, PathRefsMut,
+    AllPathIds, AllPathIdsPar, EmbeddedMutablePath, EmbeddedPaths,
+    HandleOccurrences, MutEmbeddedPaths, MutHandleOccurrences, OccurBase,
+    PathId, PathNames, PathOccurrences, PathRef,
 };
 
+use crate::pathgraph::PathHandleGraph;
+use crate::pathhandlegraph::step::{self, StepHandle};
+
+use rayon::prelude::*;
+
 pub mod edges;
 pub mod graph;
 pub mod index;
 pub mod iter;
+pub mod loader;
 pub mod nodes;
 pub mod occurrences;
 pub mod paths;
 pub mod sequence;
+pub mod validation;
+pub mod view;
 
 pub use self::{
     edges::{EdgeListIx, EdgeLists, EdgeRecord, EdgeVecIx},
@@ -28,17 +38,17 @@ pub use self::{
         list, NodeRecordId, OneBasedIndex, PackedDoubleList, PackedList,
         PackedListMut, RecordIndex,
     },
-    iter::{EdgeListHandleIter, PackedHandlesIter},
+    iter::{EdgeListHandleIter, PackedGraphPar, PackedHandlesIter},
     nodes::{GraphVecIx, NodeIdIndexMap, NodeRecords},
     occurrences::{NodeOccurrences, OccurListIx, OccurRecord, OccurrencesIter},
     paths::*,
-    sequence::{PackedSeqIter, Sequences},
+    sequence::{PackedSeqIter, SequenceEncoding, Sequences},
+    view::{MmapPackedGraph, PackedGraphView},
 };
 
 use self::graph::SeqRecordIx;
 
 use crate::packed;
-use crate::packed::PackedElement;
 
 impl<'a> AllHandles for &'a PackedGraph {
     type Handles = PackedHandlesIter<packed::deque::Iter<'a>>;
@@ -60,6 +70,62 @@ impl<'a> AllHandles for &'a PackedGraph {
     }
 }
 
+// The number of node IDs in each chunk of the parallel handle and
+// edge iterators
+const PAR_NODE_CHUNK_LEN: usize = 1 << 12;
+
+impl PackedGraph {
+    /// Split the node ID range into chunks, and iterate through the
+    /// chunks in parallel using `chunk_iter`, which is given the
+    /// index of the chunk.
+    fn node_chunks_par<'a, I>(
+        &'a self,
+        chunk_iter: fn(&mut &'a PackedGraph, usize) -> I,
+    ) -> PackedGraphPar<'a, I>
+    where
+        I: Iterator + Send,
+        I::Item: Send,
+    {
+        let chunks =
+            self.nodes.node_id_range_len().div_ceil(PAR_NODE_CHUNK_LEN);
+        (0..chunks)
+            .into_par_iter()
+            .map_with(self, chunk_iter)
+            .flatten_iter()
+    }
+
+    fn node_chunk_handles(
+        &self,
+        chunk: usize,
+    ) -> <&'_ PackedGraph as AllHandles>::Handles {
+        let start = chunk * PAR_NODE_CHUNK_LEN;
+        let end = start + PAR_NODE_CHUNK_LEN;
+        let iter = self.nodes.nodes_iter_range(start, end);
+        let min_id = usize::from(self.min_node_id()) + start;
+        PackedHandlesIter::new(iter, min_id)
+    }
+}
+
+impl<'a> AllHandlesPar for &'a PackedGraph {
+    type HandlesPar =
+        PackedGraphPar<'a, <&'a PackedGraph as AllHandles>::Handles>;
+
+    fn all_handles_par(self) -> Self::HandlesPar {
+        self.node_chunks_par(|graph, chunk| graph.node_chunk_handles(chunk))
+    }
+}
+
+impl<'a> AllEdgesPar for &'a PackedGraph {
+    type EdgesPar = PackedGraphPar<'a, EdgesIter<&'a PackedGraph>>;
+
+    fn all_edges_par(self) -> Self::EdgesPar {
+        self.node_chunks_par(|graph, chunk| {
+            let handles = graph.node_chunk_handles(chunk);
+            EdgesIter::with_handles(*graph, handles)
+        })
+    }
+}
+
 impl<'a> AllEdges for &'a PackedGraph {
     type Edges = EdgesIter<&'a PackedGraph>;
 
@@ -69,7 +135,7 @@ impl<'a> AllEdges for &'a PackedGraph {
 
     #[inline]
     fn edge_count(self) -> usize {
-        self.edges.len()
+        self.edge_count
     }
 }
 
@@ -114,6 +180,14 @@ impl<'a> HandleSequences for &'a PackedGraph {
             .iter(seq_ix.unwrap(), handle.is_reverse())
     }
 
+    #[inline]
+    fn base(self, handle: Handle, index: usize) -> u8 {
+        let g_ix = self.nodes.handle_record(handle).unwrap();
+        self.nodes
+            .sequences()
+            .base(g_ix, index, handle.is_reverse())
+    }
+
     #[inline]
     fn node_len(self, handle: Handle) -> usize {
         let g_ix = self.nodes.handle_record(handle).unwrap();
@@ -165,26 +239,39 @@ impl AdditiveHandleGraph for PackedGraph {
         self.create_handle(sequence, id)
     }
 
-    fn create_handle<T: Into<NodeId>>(
+    fn try_create_handle<T: Into<NodeId>>(
         &mut self,
         sequence: &[u8],
         node_id: T,
-    ) -> Handle {
+    ) -> HandleGraphResult<Handle> {
         let id = node_id.into();
-        assert!(
-            id != NodeId::from(0)
-                && !sequence.is_empty()
-                && !self.nodes.has_node(id)
-        );
+        if id == NodeId::from(0) {
+            return Err(HandleGraphError::NullNodeId);
+        }
+        if sequence.is_empty() {
+            return Err(HandleGraphError::EmptySequence(id));
+        }
+        if self.nodes.has_node(id) {
+            return Err(HandleGraphError::DuplicateNode(id));
+        }
 
         let _g_ix = self.nodes.create_node(id, sequence).unwrap();
 
-        Handle::pack(id, false)
+        Ok(Handle::pack(id, false))
     }
 
-    fn create_edge(&mut self, Edge(left, right): Edge) {
-        let left_gix = self.nodes.handle_record(left).unwrap();
-        let right_gix = self.nodes.handle_record(right).unwrap();
+    fn try_create_edge(
+        &mut self,
+        Edge(left, right): Edge,
+    ) -> HandleGraphResult<()> {
+        let left_gix = self
+            .nodes
+            .handle_record(left)
+            .ok_or_else(|| HandleGraphError::MissingNode(left.id()))?;
+        let right_gix = self
+            .nodes
+            .handle_record(right)
+            .ok_or_else(|| HandleGraphError::MissingNode(right.id()))?;
 
         let left_edge_dir = if left.is_reverse() {
             Direction::Left
@@ -221,6 +308,10 @@ impl AdditiveHandleGraph for PackedGraph {
 
         self.nodes
             .set_edge_list(right_gix, right_edge_dir, right_to_left);
+
+        self.edge_count += 1;
+
+        Ok(())
     }
 }
 
@@ -234,19 +325,24 @@ impl SubtractiveHandleGraph for PackedGraph {
     }
 
     fn clear_graph(&mut self) {
-        unimplemented!();
+        *self = PackedGraph::with_encoding(self.encoding());
     }
 }
 
 impl MutableHandleGraph for PackedGraph {
-    fn divide_handle(
+    fn try_divide_handle(
         &mut self,
         handle: Handle,
         offsets: Vec<usize>,
-    ) -> Vec<Handle> {
+    ) -> HandleGraphResult<Vec<Handle>> {
+        if !self.nodes.has_node(handle.id()) {
+            return Err(HandleGraphError::MissingNode(handle.id()));
+        }
+
         let mut result = vec![handle];
 
         let node_len = self.node_len(handle);
+        validate_offsets(handle, node_len, &offsets)?;
 
         let _fwd_handle = handle.forward();
 
@@ -281,15 +377,13 @@ impl MutableHandleGraph for PackedGraph {
         let new_seq_ixs =
             self.nodes.sequences_mut().split_sequence(seq_ix, &lengths);
 
-        if new_seq_ixs.is_none() {
-            panic!(
-                "Something went wrong when \
-                 dividing the handle {:?} with offsets {:#?}",
-                handle, offsets
-            );
-        }
-
-        let new_seq_ixs = new_seq_ixs.unwrap();
+        // The offsets have been validated, so this can only fail if
+        // the sequence record is inconsistent with the node length
+        let new_seq_ixs =
+            new_seq_ixs.ok_or(HandleGraphError::InvalidOffset {
+                handle,
+                offset: total_len,
+            })?;
 
         // Add new nodes and graph records for the new sequence records
 
@@ -351,9 +445,21 @@ impl MutableHandleGraph for PackedGraph {
             }
         }
 
-        // TODO update paths and occurrences once they're implmented
+        // Update the paths that cross the divided node, and the
+        // occurrences of the new nodes
+        let fragments = std::iter::once(handle.forward())
+            .chain(result.iter().skip(1).copied())
+            .collect::<Vec<_>>();
+
+        let occurrences = self.handle_occurrences(handle).collect::<Vec<_>>();
+
+        for (path_id, step_ix) in occurrences {
+            self.with_path_mut_ctx(path_id, |path| {
+                path.divide_step(step_ix, &fragments).unwrap()
+            });
+        }
 
-        result
+        Ok(result)
     }
 
     fn apply_orientation(&mut self, handle: Handle) -> Handle {
@@ -364,7 +470,7 @@ impl MutableHandleGraph for PackedGraph {
         let g_ix = self.nodes.handle_record(handle).unwrap();
 
         // Overwrite the sequence with its reverse complement
-        let rev_seq = self.sequence(handle);
+        let rev_seq = self.sequence_iter(handle).collect::<Vec<_>>();
         self.nodes
             .sequences_mut()
             .overwrite_sequence(g_ix, &rev_seq);
@@ -395,15 +501,139 @@ impl MutableHandleGraph for PackedGraph {
             .update_node_edge_lists(g_ix, |l, r| (r, l))
             .unwrap();
 
-        // TODO update paths and occurrences once they're implmented
+        // Flip the path steps on the node
+        let occurrences = self.handle_occurrences(handle).collect::<Vec<_>>();
+
+        for (path_id, step_ix) in occurrences {
+            self.with_path_mut_ctx(path_id, |path| {
+                path.flip_step(step_ix).unwrap();
+                Vec::new()
+            });
+        }
 
         handle.flip()
     }
 }
 
+impl<'a> AllPathIds for &'a PackedGraph {
+    type PathIds = <&'a PackedPathNames as AllPathIds>::PathIds;
+
+    fn all_path_ids(self) -> Self::PathIds {
+        self.paths.path_names.all_path_ids()
+    }
+}
+
+impl AllPathIdsPar for &PackedGraph {
+    type PathIdsPar = rayon::vec::IntoIter<PathId>;
+
+    fn all_path_ids_par(self) -> Self::PathIdsPar {
+        let path_ids = self.paths.path_names.path_ids().copied();
+        path_ids.collect::<Vec<_>>().into_par_iter()
+    }
+}
+
+impl<'a> PathNames for &'a PackedGraph {
+    type PathName = <&'a PackedPathNames as PathNames>::PathName;
+
+    fn get_path_name(self, id: PathId) -> Option<Self::PathName> {
+        self.paths.path_names.get_path_name(id)
+    }
+
+    fn get_path_id(self, name: &[u8]) -> Option<PathId> {
+        self.paths.path_names.get_path_id(name)
+    }
+}
+
+impl<'a> EmbeddedPaths for &'a PackedGraph {
+    type AllPaths = <&'a PackedPathNames as AllPathIds>::PathIds;
+    type PathName = <&'a PackedPathNames as PathNames>::PathName;
+    type Path = PackedPathRef<'a>;
+
+    fn all_path_ids(self) -> Self::AllPaths {
+        self.paths.path_names.all_path_ids()
+    }
+
+    fn get_path(self, path_id: PathId) -> Option<Self::Path> {
+        if !self.paths.path_names.contains_id(path_id) {
+            return None;
+        }
+        self.paths.path_ref(path_id)
+    }
+
+    fn lookup_path_id(self, name: &[u8]) -> Option<PathId> {
+        self.paths.path_names.get_path_id(name)
+    }
+
+    fn get_path_name(self, path_id: PathId) -> Option<Self::PathName> {
+        if !self.paths.path_names.contains_id(path_id) {
+            return None;
+        }
+        self.paths.path_names.get_path_name(path_id)
+    }
+
+    fn path_count(self) -> usize {
+        self.paths.path_names.path_ids().len()
+    }
+}
+
+impl<'a> EmbeddedPaths for &'a mut PackedGraph {
+    type AllPaths = <&'a PackedGraph as EmbeddedPaths>::AllPaths;
+    type PathName = <&'a PackedGraph as EmbeddedPaths>::PathName;
+    type Path = <&'a PackedGraph as EmbeddedPaths>::Path;
+
+    fn all_path_ids(self) -> Self::AllPaths {
+        EmbeddedPaths::all_path_ids(&*self)
+    }
+
+    fn get_path(self, path_id: PathId) -> Option<Self::Path> {
+        EmbeddedPaths::get_path(&*self, path_id)
+    }
+
+    fn lookup_path_id(self, name: &[u8]) -> Option<PathId> {
+        EmbeddedPaths::lookup_path_id(&*self, name)
+    }
+
+    fn get_path_name(self, path_id: PathId) -> Option<Self::PathName> {
+        EmbeddedPaths::get_path_name(&*self, path_id)
+    }
+
+    fn path_count(self) -> usize {
+        EmbeddedPaths::path_count(&*self)
+    }
+}
+
+impl<'a> PathOccurrences for &'a PackedGraph {
+    type Occurrences = std::iter::Map<
+        OccurrencesIter<'a>,
+        fn((PathId, PathStepIx)) -> StepHandle,
+    >;
+
+    fn steps_on_handle(self, handle: Handle) -> Self::Occurrences {
+        self.handle_occurrences(handle).map(|(path_id, ix)| {
+            PackedGraph::step_handle_or(path_id, ix, step::PathStep::Before)
+        })
+    }
+}
+
+impl<'a> EmbeddedMutablePath for &'a mut PackedGraph {
+    type PathMut = PackedGraphPathMut<'a>;
+
+    fn get_path_mut(self, path_id: PathId) -> Option<Self::PathMut> {
+        if !self.paths.path_names.contains_id(path_id) {
+            return None;
+        }
+        let path = self.paths.get_path_mut_ctx(path_id)?;
+        Some(PackedGraphPathMut::new(
+            path,
+            &mut self.nodes,
+            &mut self.occurrences,
+        ))
+    }
+}
+
 impl MutEmbeddedPaths for PackedGraph {
     fn create_path(&mut self, name: &[u8], circular: bool) -> PathId {
-        self.paths.create_path(name)
+        self.paths.create_path(name, circular)
     }
 
     fn remove_path(&mut self, id: PathId) {
@@ -411,9 +641,342 @@ impl MutEmbeddedPaths for PackedGraph {
     }
 }
 
+impl PackedGraph {
+    /// Resolve the `Before` and `After` steps of the legacy path API
+    /// to the head and tail of the path.
+    fn step_handle_ix(&self, step: &StepHandle) -> Option<PathStepIx> {
+        let path = self.paths.path_ref(step.path_id())?;
+        let ix = match step.step() {
+            step::PathStep::Before => path.head(),
+            step::PathStep::After => path.tail(),
+            step::PathStep::Step(ix) => PathStepIx::from_zero_based(ix),
+        };
+        Some(ix)
+    }
+
+    /// Check that a step on `handle` can be added to the path.
+    fn check_path_step(
+        &self,
+        path_id: PathId,
+        handle: Handle,
+    ) -> HandleGraphResult<()> {
+        if !self.paths.path_names.contains_id(path_id) {
+            return Err(HandleGraphError::UnknownPath(path_id));
+        }
+        if !self.nodes.has_node(handle.id()) {
+            return Err(HandleGraphError::MissingNode(handle.id()));
+        }
+        Ok(())
+    }
+
+    /// Build a step handle from a step index, where a null index
+    /// denotes the given boundary step.
+    fn step_handle_or(
+        path_id: PathId,
+        ix: PathStepIx,
+        boundary: step::PathStep,
+    ) -> StepHandle {
+        match ix.to_zero_based() {
+            Some(ix) => StepHandle::new(path_id, step::PathStep::Step(ix)),
+            None => StepHandle::new(path_id, boundary),
+        }
+    }
+}
+
+impl PathHandleGraph for PackedGraph {
+    type PathHandle = PathId;
+    type StepHandle = StepHandle;
+
+    fn path_count(&self) -> usize {
+        self.paths.path_names.path_ids().len()
+    }
+
+    fn has_path(&self, name: &[u8]) -> bool {
+        self.paths.path_names.get_path_id(name).is_some()
+    }
+
+    fn name_to_path_handle(&self, name: &[u8]) -> Option<Self::PathHandle> {
+        self.paths.path_names.get_path_id(name)
+    }
+
+    fn try_path_handle_to_name(
+        &self,
+        path_id: &Self::PathHandle,
+    ) -> HandleGraphResult<&[u8]> {
+        self.paths
+            .path_names
+            .name_slice(*path_id)
+            .ok_or(HandleGraphError::UnknownPath(*path_id))
+    }
+
+    fn is_circular(&self, path_id: &Self::PathHandle) -> bool {
+        self.paths.path_ref(*path_id).unwrap().circular()
+    }
+
+    fn step_count(&self, path_id: &Self::PathHandle) -> usize {
+        self.paths.path_ref(*path_id).unwrap().len()
+    }
+
+    fn handle_of_step(&self, step: &Self::StepHandle) -> Option<Handle> {
+        let ix = PathStepIx::from_zero_based(step.step().index()?);
+        let path = self.paths.path_ref(step.path_id())?;
+        path.handle_at(ix)
+    }
+
+    fn path_handle_of_step(&self, step: &Self::StepHandle) -> Self::PathHandle {
+        step.path_id()
+    }
+
+    fn path_begin(&self, path: &Self::PathHandle) -> Self::StepHandle {
+        let head = self.paths.path_ref(*path).unwrap().head();
+        Self::step_handle_or(*path, head, step::PathStep::After)
+    }
+
+    fn path_end(&self, path: &Self::PathHandle) -> Self::StepHandle {
+        StepHandle::new(*path, step::PathStep::After)
+    }
+
+    fn path_back(&self, path: &Self::PathHandle) -> Self::StepHandle {
+        let tail = self.paths.path_ref(*path).unwrap().tail();
+        Self::step_handle_or(*path, tail, step::PathStep::Before)
+    }
+
+    fn path_front_end(&self, path: &Self::PathHandle) -> Self::StepHandle {
+        StepHandle::new(*path, step::PathStep::Before)
+    }
+
+    fn has_next_step(&self, step: &Self::StepHandle) -> bool {
+        !self.next_step(step).step().is_after()
+    }
+
+    fn has_previous_step(&self, step: &Self::StepHandle) -> bool {
+        !self.previous_step(step).step().is_before()
+    }
+
+    fn path_bases_len(&self, path_handle: &Self::PathHandle) -> Option<usize> {
+        let index = self.path_position_index(*path_handle)?;
+        Some(index.len())
+    }
+
+    fn position_of_step(&self, step: &Self::StepHandle) -> Option<usize> {
+        let index = self.path_position_index(step.path_id())?;
+
+        match step.step() {
+            step::PathStep::Before => Some(0),
+            step::PathStep::After => Some(index.len()),
+            step::PathStep::Step(ix) => {
+                index.position_of_step(PathStepIx::from_zero_based(ix))
+            }
+        }
+    }
+
+    fn step_at_position(
+        &self,
+        path_handle: &Self::PathHandle,
+        pos: usize,
+    ) -> Option<Self::StepHandle> {
+        let circular = self.paths.path_ref(*path_handle)?.circular();
+        let index = self.path_position_index(*path_handle)?;
+
+        // Positions on circular paths wrap around the origin
+        let pos = if circular && !index.is_empty() {
+            pos % index.len()
+        } else {
+            pos
+        };
+
+        let step = match index.step_at_position(pos) {
+            Some(ix) => {
+                Self::step_handle_or(*path_handle, ix, step::PathStep::After)
+            }
+            None => self.path_end(path_handle),
+        };
+
+        Some(step)
+    }
+
+    fn try_destroy_path(
+        &mut self,
+        path: &Self::PathHandle,
+    ) -> HandleGraphResult<()> {
+        self.remove_path_impl(*path)
+            .ok_or(HandleGraphError::UnknownPath(*path))
+    }
+
+    fn next_step(&self, step: &Self::StepHandle) -> Self::StepHandle {
+        let path_id = step.path_id();
+        match step.step() {
+            step::PathStep::Before => self.path_begin(&path_id),
+            step::PathStep::After => *step,
+            step::PathStep::Step(ix) => {
+                let path = self.paths.path_ref(path_id).unwrap();
+                let ix = PathStepIx::from_zero_based(ix);
+                let next = path.next_step_ix(ix).unwrap();
+                Self::step_handle_or(path_id, next, step::PathStep::After)
+            }
+        }
+    }
+
+    fn previous_step(&self, step: &Self::StepHandle) -> Self::StepHandle {
+        let path_id = step.path_id();
+        match step.step() {
+            step::PathStep::Before => *step,
+            step::PathStep::After => self.path_back(&path_id),
+            step::PathStep::Step(ix) => {
+                let path = self.paths.path_ref(path_id).unwrap();
+                let ix = PathStepIx::from_zero_based(ix);
+                let prev = path.prev_step_ix(ix).unwrap();
+                Self::step_handle_or(path_id, prev, step::PathStep::Before)
+            }
+        }
+    }
+
+    fn create_path_handle(
+        &mut self,
+        name: &[u8],
+        is_circular: bool,
+    ) -> Self::PathHandle {
+        self.create_path(name, is_circular)
+    }
+
+    fn try_append_step(
+        &mut self,
+        path_id: &Self::PathHandle,
+        to_append: Handle,
+    ) -> HandleGraphResult<Self::StepHandle> {
+        self.check_path_step(*path_id, to_append)?;
+
+        let update = self
+            .paths
+            .with_path_mut_ctx(*path_id, |path| {
+                vec![path.append_handle(to_append)]
+            })
+            .unwrap();
+
+        let step_ix = match update[0] {
+            paths::StepUpdate::Insert { step, .. } => step,
+            paths::StepUpdate::Remove { step, .. } => step,
+        };
+
+        self.apply_node_occurrences_iter(*path_id, update);
+        Ok(Self::step_handle_or(
+            *path_id,
+            step_ix,
+            step::PathStep::After,
+        ))
+    }
+
+    fn try_prepend_step(
+        &mut self,
+        path_id: &Self::PathHandle,
+        to_prepend: Handle,
+    ) -> HandleGraphResult<Self::StepHandle> {
+        self.check_path_step(*path_id, to_prepend)?;
+
+        let update = self
+            .paths
+            .with_path_mut_ctx(*path_id, |path| {
+                vec![path.prepend_handle(to_prepend)]
+            })
+            .unwrap();
+
+        let step_ix = match update[0] {
+            paths::StepUpdate::Insert { step, .. } => step,
+            paths::StepUpdate::Remove { step, .. } => step,
+        };
+
+        self.apply_node_occurrences_iter(*path_id, update);
+        Ok(Self::step_handle_or(
+            *path_id,
+            step_ix,
+            step::PathStep::Before,
+        ))
+    }
+
+    fn try_rewrite_segment(
+        &mut self,
+        begin: &Self::StepHandle,
+        end: &Self::StepHandle,
+        new_segment: Vec<Handle>,
+    ) -> HandleGraphResult<(Self::StepHandle, Self::StepHandle)> {
+        let invalid_segment = HandleGraphError::InvalidSegment {
+            begin: *begin,
+            end: *end,
+        };
+
+        if begin.path_id() != end.path_id() {
+            return Err(invalid_segment);
+        }
+
+        let path_id = begin.path_id();
+        for &handle in new_segment.iter() {
+            self.check_path_step(path_id, handle)?;
+        }
+
+        let from = self.step_handle_ix(begin).unwrap();
+        let to = self.step_handle_ix(end).unwrap();
+
+        let path = self.paths.path_ref(path_id).unwrap();
+        if path.handle_at(from).is_none() || path.handle_at(to).is_none() {
+            return Err(invalid_segment);
+        }
+
+        let mut new_range = None;
+        let updates = self
+            .paths
+            .with_path_mut_ctx(path_id, |path| {
+                match path.rewrite_segment(from, to, &new_segment) {
+                    Some((updates, first, after)) => {
+                        new_range = Some((first, after));
+                        updates
+                    }
+                    None => Vec::new(),
+                }
+            })
+            .unwrap();
+        self.apply_node_occurrences_iter(path_id, updates);
+
+        // the end of the returned range is the step after the new
+        // segment, rather than its last step
+        let (first, after) = new_range.ok_or(invalid_segment)?;
+        Ok((
+            Self::step_handle_or(path_id, first, step::PathStep::After),
+            Self::step_handle_or(path_id, after, step::PathStep::After),
+        ))
+    }
+
+    fn paths_iter<'a>(
+        &'a self,
+    ) -> Box<dyn Iterator<Item = &'a Self::PathHandle> + 'a> {
+        Box::new(self.paths.path_names.path_ids())
+    }
+
+    fn occurrences_iter<'a>(
+        &'a self,
+        handle: Handle,
+    ) -> Box<dyn Iterator<Item = Self::StepHandle> + 'a> {
+        Box::new(self.handle_occurrences(handle).map(|(path_id, ix)| {
+            Self::step_handle_or(path_id, ix, step::PathStep::After)
+        }))
+    }
+
+    fn steps_iter<'a>(
+        &'a self,
+        path_handle: &'a Self::PathHandle,
+    ) -> Box<dyn Iterator<Item = Self::StepHandle> + 'a> {
+        let path = self.paths.path_ref(*path_handle).unwrap();
+        Box::new(path.steps().map(move |(ix, _)| {
+            Self::step_handle_or(*path_handle, ix, step::PathStep::After)
+        }))
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::packed::PackedElement;
+    use crate::pathhandlegraph::PathRefMut;
+    use quickcheck::quickcheck;
 
     fn hnd(x: u64) -> Handle {
         Handle::pack(x, false)
@@ -498,7 +1061,7 @@ mod tests {
 
         let prep_path =
             |graph: &mut PackedGraph, name: &[u8], steps: Vec<u64>| {
-                let path = graph.paths.create_path(name);
+                let path = graph.paths.create_path(name, false);
                 let hnds = vec_hnd(steps);
                 (path, hnds)
             };
@@ -782,7 +1345,7 @@ mod tests {
 
         let prep_path =
             |graph: &mut PackedGraph, name: &[u8], steps: Vec<u64>| {
-                let path = graph.paths.create_path(name);
+                let path = graph.paths.create_path(name, false);
                 let hnds = vec_hnd(steps);
                 (path, hnds)
             };
@@ -959,4 +1522,1204 @@ mod tests {
             ]
         );
     }
+
+    #[test]
+    fn packedgraph_fallible_construction() {
+        use crate::error::HandleGraphError;
+
+        let mut graph = PackedGraph::new();
+        let h1 = graph.append_handle(b"GTCA");
+        let h2 = graph.append_handle(b"AAG");
+        let h4 = Handle::pack(4, false);
+
+        let id = |x: u64| NodeId::from(x);
+
+        assert_eq!(
+            graph.try_create_handle(b"A", 2u64),
+            Err(HandleGraphError::DuplicateNode(id(2)))
+        );
+        assert_eq!(
+            graph.try_create_handle(b"A", 0u64),
+            Err(HandleGraphError::NullNodeId)
+        );
+        assert_eq!(
+            graph.try_create_handle(b"", 3u64),
+            Err(HandleGraphError::EmptySequence(id(3)))
+        );
+        assert_eq!(graph.node_count(), 2);
+        assert_eq!(graph.sequence(h2), b"AAG");
+
+        assert_eq!(
+            graph.try_create_edge(Edge(h4, h1)),
+            Err(HandleGraphError::MissingNode(id(4)))
+        );
+        assert_eq!(graph.edge_count(), 0);
+        assert_eq!(graph.try_create_edge(Edge(h1, h2)), Ok(()));
+        assert!(graph.has_edge(h1, h2));
+
+        let invalid =
+            |handle, offset| HandleGraphError::InvalidOffset { handle, offset };
+        assert_eq!(graph.try_divide_handle(h1, vec![0]), Err(invalid(h1, 0)));
+        assert_eq!(graph.try_divide_handle(h1, vec![4]), Err(invalid(h1, 4)));
+        assert_eq!(
+            graph.try_divide_handle(h1.flip(), vec![3, 2]),
+            Err(invalid(h1.flip(), 2))
+        );
+        assert_eq!(
+            graph.try_divide_handle(h4, vec![1]),
+            Err(HandleGraphError::MissingNode(id(4)))
+        );
+        assert_eq!(graph.node_count(), 2);
+
+        let handles = graph.try_divide_handle(h1, vec![1, 3]).unwrap();
+        assert_eq!(handles.len(), 3);
+        assert_eq!(graph.sequence(h1), b"G");
+
+        let path = graph.create_path_handle(b"path", false);
+        let missing = PathId(path.0 + 1);
+        let unknown = HandleGraphError::UnknownPath(missing);
+        assert_eq!(graph.try_append_step(&missing, h1), Err(unknown.clone()));
+        assert_eq!(graph.try_prepend_step(&missing, h1), Err(unknown.clone()));
+        assert_eq!(
+            graph.try_path_handle_to_name(&missing),
+            Err(unknown.clone())
+        );
+        assert_eq!(graph.try_destroy_path(&missing), Err(unknown));
+
+        let h9 = Handle::pack(9, false);
+        assert_eq!(
+            graph.try_append_step(&path, h9),
+            Err(HandleGraphError::MissingNode(id(9)))
+        );
+
+        let first = graph.try_append_step(&path, h1).unwrap();
+        let last = graph.try_append_step(&path, h2).unwrap();
+        let other = StepHandle::new(missing, step::PathStep::Step(0));
+        assert_eq!(
+            graph.try_rewrite_segment(&first, &other, vec![h1]),
+            Err(HandleGraphError::InvalidSegment {
+                begin: first,
+                end: other
+            })
+        );
+        assert_eq!(
+            graph.try_rewrite_segment(&last, &first, vec![h1]),
+            Err(HandleGraphError::InvalidSegment {
+                begin: last,
+                end: first
+            })
+        );
+        assert_eq!(
+            graph.try_rewrite_segment(&first, &last, vec![h9]),
+            Err(HandleGraphError::MissingNode(id(9)))
+        );
+        assert_eq!(graph.step_count(&path), 2);
+
+        assert_eq!(graph.try_path_handle_to_name(&path), Ok(&b"path"[..]));
+        assert_eq!(graph.try_destroy_path(&path), Ok(()));
+        assert_eq!(
+            graph.try_destroy_path(&path),
+            Err(HandleGraphError::UnknownPath(path))
+        );
+    }
+
+    #[test]
+    fn packedgraph_divide_handle_paths() {
+        let r_hnd = |x: u64| Handle::pack(x, true);
+
+        let mut graph = test_graph_with_paths();
+
+        let path_5 = graph.create_path_handle(b"path5", false);
+        for &h in [r_hnd(6), r_hnd(4), r_hnd(8), r_hnd(2), r_hnd(1)].iter() {
+            graph.append_step(&path_5, h);
+        }
+
+        let path_handles = |graph: &PackedGraph, name: &[u8]| {
+            let id = graph.name_to_path_handle(name).unwrap();
+            graph
+                .steps_iter(&id)
+                .map(|s| graph.handle_of_step(&s).unwrap())
+                .collect::<Vec<_>>()
+        };
+
+        let occurs_count = |graph: &PackedGraph, id: u64| {
+            graph.occurrences_iter(hnd(id)).count()
+        };
+
+        let seqs_before = path_sequences(&graph);
+
+        // Node 2 is in the middle of paths 2, 3, and 5
+        let new_hs = graph.divide_handle(hnd(2), vec![3, 7, 9]);
+        assert_eq!(new_hs, vec![hnd(2), hnd(10), hnd(11), hnd(12)]);
+
+        assert_eq!(seqs_before, path_sequences(&graph));
+
+        assert_eq!(
+            path_handles(&graph, b"path2"),
+            vec![5, 2, 10, 11, 12, 8, 4, 6]
+                .into_iter()
+                .map(hnd)
+                .collect::<Vec<_>>()
+        );
+
+        assert_eq!(
+            path_handles(&graph, b"path5"),
+            vec![6, 4, 8, 12, 11, 10, 2, 1]
+                .into_iter()
+                .map(r_hnd)
+                .collect::<Vec<_>>()
+        );
+
+        assert_eq!(occurs_count(&graph, 2), 3);
+        assert_eq!(occurs_count(&graph, 10), 3);
+        assert_eq!(occurs_count(&graph, 12), 3);
+
+        // Node 6 is at the end of every path except path5, where it
+        // is the first step
+        let new_hs = graph.divide_handle(hnd(6), vec![1]);
+        assert_eq!(new_hs, vec![hnd(6), hnd(13)]);
+
+        assert_eq!(seqs_before, path_sequences(&graph));
+
+        let path_4 = path_handles(&graph, b"path4");
+        assert_eq!(&path_4[path_4.len() - 2..], &[hnd(6), hnd(13)]);
+
+        let path_5 = path_handles(&graph, b"path5");
+        assert_eq!(&path_5[..2], &[r_hnd(13), r_hnd(6)]);
+
+        assert_eq!(occurs_count(&graph, 13), 5);
+
+        // The path ends are updated along with the steps
+        let path_1 = graph.name_to_path_handle(b"path1").unwrap();
+        let back = graph.path_back(&path_1);
+        assert_eq!(graph.handle_of_step(&back), Some(hnd(13)));
+
+        let path_5 = graph.name_to_path_handle(b"path5").unwrap();
+        let begin = graph.path_begin(&path_5);
+        assert_eq!(graph.handle_of_step(&begin), Some(r_hnd(13)));
+    }
+
+    // returns the spelled sequence of each path, sorted by path name
+    fn path_sequences(graph: &PackedGraph) -> Vec<(Vec<u8>, Vec<u8>)> {
+        let mut seqs = graph
+            .paths_iter()
+            .map(|&id| {
+                let name = graph.path_handle_to_name(&id).to_vec();
+                let seq = graph
+                    .steps_iter(&id)
+                    .flat_map(|s| {
+                        let h = graph.handle_of_step(&s).unwrap();
+                        graph.sequence_iter(h)
+                    })
+                    .collect::<Vec<_>>();
+                (name, seq)
+            })
+            .collect::<Vec<_>>();
+        seqs.sort();
+        seqs
+    }
+
+    // checks that every occurrence record of every node points to a
+    // step on that node
+    fn occurrences_valid(graph: &PackedGraph) -> bool {
+        graph.all_handles().all(|h| {
+            graph.occurrences_iter(h).all(|step| {
+                graph.handle_of_step(&step).map(|s| s.id()) == Some(h.id())
+            })
+        })
+    }
+
+    quickcheck! {
+        fn prop_apply_orientation_paths(
+            steps: Vec<(u8, bool)>,
+            flips: Vec<u8>
+        ) -> bool {
+            let mut graph = test_graph_with_paths();
+
+            let path = graph.create_path_handle(b"path5", false);
+            for (n, rev) in steps {
+                let id = u64::from(n % 9) + 1;
+                graph.append_step(&path, Handle::pack(id, rev));
+            }
+
+            let seqs_before = path_sequences(&graph);
+
+            for n in flips {
+                let id = u64::from(n % 9) + 1;
+                let handle = Handle::pack(id, true);
+                let flipped = graph.apply_orientation(handle);
+                if flipped != handle.flip() {
+                    return false;
+                }
+            }
+
+            seqs_before == path_sequences(&graph) && occurrences_valid(&graph)
+        }
+    }
+
+    #[test]
+    fn packedgraph_apply_orientation() {
+        let r_hnd = |x: u64| Handle::pack(x, true);
+
+        let mut graph = test_graph_with_paths();
+
+        let seqs_before = path_sequences(&graph);
+
+        let flipped = graph.apply_orientation(r_hnd(8));
+        assert_eq!(flipped, hnd(8));
+        assert_eq!(graph.sequence(hnd(8)), b"ATGTT".to_vec());
+
+        assert_eq!(seqs_before, path_sequences(&graph));
+        assert!(occurrences_valid(&graph));
+
+        let path_1 = graph.name_to_path_handle(b"path1").unwrap();
+        let steps = graph
+            .steps_iter(&path_1)
+            .map(|s| graph.handle_of_step(&s).unwrap())
+            .collect::<Vec<_>>();
+        assert_eq!(steps, vec![hnd(1), r_hnd(8), hnd(4), hnd(6)]);
+    }
+
+    #[test]
+    fn packedgraph_defragment() {
+        use succinct::SpaceUsage;
+
+        let mut graph = test_graph_with_paths();
+
+        // path5 (9 1 8) is removed along with node 7
+        let path_5 = graph.create_path_handle(b"path5", false);
+        for &h in [hnd(1), hnd(7), hnd(8)].iter() {
+            graph.append_step(&path_5, h);
+        }
+
+        graph.remove_handle(hnd(7));
+        graph.remove_edge(Edge(hnd(4), hnd(9)));
+
+        let path_2 = graph.name_to_path_handle(b"path2").unwrap();
+        let begin = graph.path_begin(&path_2);
+        let step_8 = graph.next_step(&graph.next_step(&begin));
+        graph.rewrite_segment(&step_8, &step_8, vec![hnd(3)]);
+
+        let path_3 = graph.name_to_path_handle(b"path3").unwrap();
+        graph.destroy_path(&path_3);
+
+        let nodes = |graph: &PackedGraph| {
+            let mut nodes = graph
+                .all_handles()
+                .map(|h| (h, graph.sequence(h)))
+                .collect::<Vec<_>>();
+            nodes.sort();
+            nodes
+        };
+
+        let edges = |graph: &PackedGraph| {
+            let mut edges = graph.all_edges().collect::<Vec<_>>();
+            edges.sort();
+            edges
+        };
+
+        let occurs_count = |graph: &PackedGraph| {
+            graph
+                .all_handles()
+                .map(|h| graph.occurrences_iter(h).count())
+                .collect::<Vec<_>>()
+        };
+
+        let nodes_before = nodes(&graph);
+        let edges_before = edges(&graph);
+        let paths_before = path_sequences(&graph);
+        let occurs_before = occurs_count(&graph);
+        let bytes_before = graph.heap_bytes();
+
+        graph.defragment();
+
+        assert_eq!(nodes_before, nodes(&graph));
+        assert_eq!(edges_before, edges(&graph));
+        assert_eq!(paths_before, path_sequences(&graph));
+        assert_eq!(occurs_before, occurs_count(&graph));
+        assert!(occurrences_valid(&graph));
+        assert!(graph.heap_bytes() < bytes_before);
+
+        // Path IDs are preserved
+        assert_eq!(graph.name_to_path_handle(b"path2"), Some(path_2));
+        assert!(!graph.has_path(b"path3"));
+        assert_eq!(graph.step_count(&path_2), 5);
+
+        // No removed records are left
+        assert_eq!(graph.nodes.sequences().len(), 8);
+        assert_eq!(graph.edges.len(), 2 * edges_before.len());
+
+        // The defragmented graph can still be modified
+        let h = graph.append_handle(b"GATTACA");
+        graph.create_edge(Edge(hnd(6), h));
+        graph.append_step(&path_2, h);
+        assert_eq!(graph.step_count(&path_2), 6);
+        assert!(occurrences_valid(&graph));
+    }
+
+    #[test]
+    fn packedgraph_node_and_edge_counts() {
+        let mut graph = test_graph_with_paths();
+
+        let counts = |graph: &PackedGraph| {
+            assert_eq!(graph.node_count(), graph.all_handles().count());
+            assert_eq!(graph.edge_count(), graph.all_edges().count());
+            (graph.node_count(), graph.edge_count())
+        };
+
+        assert_eq!(counts(&graph), (9, 13));
+
+        // Node IDs don't have to be contiguous
+        let h20 = graph.create_handle(b"GATTACA", 20u64);
+        assert_eq!(counts(&graph), (10, 13));
+        assert!(graph.has_node(20u64));
+        assert!(!graph.has_node(15u64));
+
+        graph.create_edge(Edge(hnd(6), h20));
+        graph.create_edge(Edge(h20, h20));
+        assert_eq!(counts(&graph), (10, 15));
+
+        assert!(graph.remove_edge(Edge(h20, h20)));
+        assert!(!graph.remove_edge(Edge(h20, h20)));
+        assert_eq!(counts(&graph), (10, 14));
+
+        // Dividing a node adds the new nodes and the edges between
+        // the segments
+        graph.divide_handle(h20, vec![2, 4]);
+        assert_eq!(counts(&graph), (12, 16));
+
+        // Removing a node also removes its edges
+        graph.remove_handle(hnd(8));
+        assert!(!graph.has_node(8u64));
+        assert_eq!(counts(&graph), (11, 12));
+
+        graph.defragment();
+        assert_eq!(counts(&graph), (11, 12));
+
+        let mut bytes = Vec::new();
+        graph.serialize(&mut bytes).unwrap();
+        let other = PackedGraph::deserialize(bytes.as_slice()).unwrap();
+        assert_eq!(counts(&other), (11, 12));
+
+        graph.clear_graph();
+        assert_eq!(counts(&graph), (0, 0));
+    }
+
+    #[test]
+    fn packedgraph_sequence_random_access() {
+        let mut graph = PackedGraph::new();
+        let h1 = graph.append_handle(b"GTCCACTTNGTGT");
+        let h2 = graph.append_handle(b"A");
+        let h3 = graph.append_handle(b"ACGTTTGCA");
+
+        // Split sequences have nonzero offsets
+        let parts = graph.divide_handle(h3, vec![3, 5]);
+
+        let handles = vec![h1, h2]
+            .into_iter()
+            .chain(parts)
+            .flat_map(|h| vec![h, h.flip()])
+            .collect::<Vec<_>>();
+
+        for handle in handles {
+            let seq = graph.sequence_iter(handle).collect::<Vec<_>>();
+            let len = seq.len();
+            assert_eq!(graph.node_len(handle), len);
+
+            for (ix, &base) in seq.iter().enumerate() {
+                assert_eq!(graph.base(handle, ix), base);
+            }
+
+            for start in 0..=len {
+                for sub_len in 0..=(len - start + 1) {
+                    let expected = seq
+                        .iter()
+                        .skip(start)
+                        .take(sub_len)
+                        .copied()
+                        .collect::<Vec<_>>();
+                    let iter = graph.subsequence_iter(handle, start, sub_len);
+                    assert_eq!(iter.len(), expected.len());
+                    assert_eq!(iter.collect::<Vec<_>>(), expected);
+                    assert_eq!(
+                        graph.subsequence(handle, start, sub_len),
+                        expected
+                    );
+                }
+            }
+        }
+
+        assert_eq!(graph.subsequence(h1, 3, 6), b"CACTTN");
+        assert_eq!(graph.subsequence(h1.flip(), 3, 6), b"CNAAGT");
+        assert_eq!(graph.base(h1.flip(), 0), b'A');
+    }
+
+    #[test]
+    fn packedgraph_sequence_encodings() {
+        let seq_1 = b"ACGTRYSWKMBDHVN-";
+        let seq_2 = b"acgtNNryACGTbdhv";
+        let seq_3 = b"GAXTU.";
+
+        let build = |encoding: SequenceEncoding| {
+            let mut graph = PackedGraph::with_encoding(encoding);
+            let h1 = graph.append_handle(seq_1);
+            let h2 = graph.append_handle(seq_2);
+            let h3 = graph.append_handle(seq_3);
+            graph.create_edge(Edge(h1, h2));
+            graph.create_edge(Edge(h2, h3));
+            (graph, [h1, h2, h3])
+        };
+
+        let (graph, [h1, h2, h3]) = build(SequenceEncoding::Dna);
+        assert_eq!(graph.encoding(), SequenceEncoding::Dna);
+        assert_eq!(graph.sequence(h1), b"ACGTNNNNNNNNNNNN");
+        assert_eq!(graph.sequence(h2), b"ACGTNNNNACGTNNNN");
+        assert_eq!(
+            graph.sequence_iter(h3.flip()).collect::<Vec<_>>(),
+            b"NNANTC"
+        );
+
+        let (graph, [h1, h2, h3]) = build(SequenceEncoding::Iupac);
+        assert_eq!(graph.sequence(h1), seq_1);
+        assert_eq!(
+            graph.sequence_iter(h1.flip()).collect::<Vec<_>>(),
+            b"-NBDHVKMWSRYACGT"
+        );
+        assert_eq!(graph.sequence(h2), b"ACGTNNRYACGTBDHV");
+        assert_eq!(graph.sequence(h3), b"GANTTN");
+        assert_eq!(graph.base(h1.flip(), 5), b'V');
+
+        let (mut graph, [h1, h2, h3]) =
+            build(SequenceEncoding::IupacSoftMasked);
+        assert_eq!(graph.sequence(h1), seq_1);
+        assert_eq!(graph.sequence(h2), seq_2);
+        assert_eq!(
+            graph.sequence_iter(h2.flip()).collect::<Vec<_>>(),
+            b"bdhvACGTryNNacgt"
+        );
+        assert_eq!(graph.sequence(h3), b"GANTTN");
+        assert_eq!(graph.subsequence(h2.flip(), 8, 4), b"ryNN");
+        assert_eq!(graph.base(h2, 7), b'y');
+
+        // Every complement is an involution that keeps the case
+        let all_bases = (0..=255u8).collect::<Vec<_>>();
+        let h4 = graph.append_handle(&all_bases);
+        let rev = graph.sequence_iter(h4.flip()).collect::<Vec<_>>();
+        let h5 = graph.append_handle(&rev);
+        assert_eq!(
+            graph.sequence_iter(h5.flip()).collect::<Vec<_>>(),
+            graph.sequence(h4)
+        );
+        assert!(rev
+            .iter()
+            .zip(all_bases.iter().rev())
+            .all(|(a, b)| a.is_ascii_lowercase() == b.is_ascii_lowercase()));
+
+        // The encoding is kept when the graph is modified, serialized
+        // and defragmented
+        let parts = graph.divide_handle(h2, vec![4, 10]);
+        assert_eq!(graph.sequence(parts[1]), b"NNryAC");
+        graph.remove_handle(h4);
+        graph.defragment();
+        assert_eq!(graph.encoding(), SequenceEncoding::IupacSoftMasked);
+        assert_eq!(
+            graph.sequence_iter(parts[1].flip()).collect::<Vec<_>>(),
+            b"GTryNN"
+        );
+
+        let mut bytes: Vec<u8> = Vec::new();
+        graph.serialize(&mut bytes).unwrap();
+        let other = PackedGraph::deserialize(bytes.as_slice()).unwrap();
+        assert_eq!(other.encoding(), SequenceEncoding::IupacSoftMasked);
+
+        let view = PackedGraphView::new(&bytes).unwrap();
+        for handle in graph.all_handles() {
+            for &h in [handle, handle.flip()].iter() {
+                assert_eq!(
+                    other.sequence_iter(h).collect::<Vec<_>>(),
+                    graph.sequence_iter(h).collect::<Vec<_>>()
+                );
+                assert_eq!(
+                    view.sequence_iter(h).collect::<Vec<_>>(),
+                    graph.sequence_iter(h).collect::<Vec<_>>()
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn packedgraph_clear_graph() {
+        let mut graph = test_graph_with_paths();
+        graph.clear_graph();
+
+        assert_eq!(graph.node_count(), 0);
+        assert_eq!(graph.all_edges().count(), 0);
+        assert_eq!(PathHandleGraph::path_count(&graph), 0);
+
+        let h = graph.append_handle(b"GTCA");
+        assert_eq!(h, hnd(1));
+        assert_eq!(graph.sequence(h), b"GTCA".to_vec());
+
+        // The sequence encoding is kept
+        let mut graph = PackedGraph::with_encoding(SequenceEncoding::Iupac);
+        graph.append_handle(b"GTCA");
+        graph.clear_graph();
+
+        assert_eq!(graph.encoding(), SequenceEncoding::Iupac);
+        let h = graph.append_handle(b"ACNNRYT");
+        assert_eq!(graph.sequence(h), b"ACNNRYT".to_vec());
+    }
+
+    #[test]
+    fn packedgraph_compact_paths() {
+        use succinct::SpaceUsage;
+
+        let mut graph = test_graph_with_paths();
+        assert_eq!(graph.compact_paths(), None);
+
+        // Temporary paths that are added and removed
+        for i in 0..100 {
+            let name = format!("temp{}", i);
+            let path = graph.create_path_handle(name.as_bytes(), false);
+            for n in 1..=9 {
+                graph.append_step(&path, hnd(n));
+            }
+            graph.destroy_path(&path);
+        }
+
+        let path_2 = graph.name_to_path_handle(b"path2").unwrap();
+        graph.destroy_path(&path_2);
+
+        let circ = graph.create_path_handle(b"circ", true);
+        graph.append_step(&circ, hnd(3));
+
+        let paths_before = path_sequences(&graph);
+        let bytes_before = graph.paths.heap_bytes();
+
+        let id_map = graph.compact_paths().unwrap();
+
+        let mut id_map = id_map.into_iter().collect::<Vec<_>>();
+        id_map.sort();
+        assert_eq!(
+            id_map,
+            vec![
+                (PathId(0), PathId(0)),
+                (PathId(2), PathId(1)),
+                (PathId(3), PathId(2)),
+                (PathId(104), PathId(3)),
+            ]
+        );
+
+        assert_eq!(graph.paths.len(), 4);
+        assert_eq!(PathHandleGraph::path_count(&graph), 4);
+        assert!(graph.paths.heap_bytes() < bytes_before);
+        assert_eq!(paths_before, path_sequences(&graph));
+        assert!(occurrences_valid(&graph));
+
+        assert_eq!(graph.name_to_path_handle(b"path3"), Some(PathId(1)));
+        assert_eq!(graph.name_to_path_handle(b"circ"), Some(PathId(3)));
+        assert!(graph.is_circular(&PathId(3)));
+        assert!(!graph.is_circular(&PathId(2)));
+
+        let path_ids = graph
+            .occurrences_iter(hnd(3))
+            .map(|s| s.path_id())
+            .collect::<Vec<_>>();
+        assert_eq!(path_ids.len(), 2);
+        assert!(path_ids.contains(&PathId(2)));
+        assert!(path_ids.contains(&PathId(3)));
+
+        // New paths are given the next free ID
+        let path_5 = graph.create_path_handle(b"path5", false);
+        assert_eq!(path_5, PathId(4));
+        graph.append_step(&path_5, hnd(1));
+        assert!(occurrences_valid(&graph));
+    }
+
+    #[test]
+    fn packedgraph_serialize_roundtrip() {
+        let mut graph = test_graph_with_paths();
+        graph.remove_handle(hnd(7));
+
+        let mut bytes: Vec<u8> = Vec::new();
+        graph.serialize(&mut bytes).unwrap();
+
+        let other = PackedGraph::deserialize(bytes.as_slice()).unwrap();
+
+        let mut handles = graph.all_handles().collect::<Vec<_>>();
+        let mut other_handles = other.all_handles().collect::<Vec<_>>();
+        handles.sort();
+        other_handles.sort();
+        assert_eq!(handles, other_handles);
+
+        let mut edges = graph.all_edges().collect::<Vec<_>>();
+        let mut other_edges = other.all_edges().collect::<Vec<_>>();
+        edges.sort();
+        other_edges.sort();
+        assert_eq!(edges, other_edges);
+
+        for &h in handles.iter() {
+            assert_eq!(graph.sequence(h), other.sequence(h));
+            assert_eq!(
+                get_occurs(&graph, u64::from(h.id())),
+                get_occurs(&other, u64::from(h.id()))
+            );
+        }
+
+        for ix in 0..graph.paths.len() {
+            let id = PathId(ix as u64);
+            let steps = |g: &PackedGraph| {
+                g.paths
+                    .path_ref(id)
+                    .unwrap()
+                    .steps()
+                    .map(|(_, step)| step.handle)
+                    .collect::<Vec<_>>()
+            };
+            assert_eq!(steps(&graph), steps(&other));
+            let name = |g: &PackedGraph| {
+                g.paths
+                    .path_names
+                    .name_iter(id)
+                    .unwrap()
+                    .collect::<Vec<_>>()
+            };
+            assert_eq!(name(&graph), name(&other));
+        }
+
+        // Serializing the deserialized graph produces identical output
+        let mut other_bytes: Vec<u8> = Vec::new();
+        other.serialize(&mut other_bytes).unwrap();
+        assert_eq!(bytes, other_bytes);
+    }
+
+    #[test]
+    fn packedgraph_deserialize_corrupt() {
+        let graph = test_graph_with_paths();
+
+        let mut bytes: Vec<u8> = Vec::new();
+        graph.serialize(&mut bytes).unwrap();
+
+        // Flipping a bit in the body fails the checksum
+        let mut corrupt = bytes.clone();
+        let mid = corrupt.len() / 2;
+        corrupt[mid] ^= 1;
+        let result = PackedGraph::deserialize(corrupt.as_slice());
+        assert!(result.is_err());
+
+        // As does anything that isn't a serialized graph
+        let result = PackedGraph::deserialize(&b"S\t1\tGTCA\n"[..]);
+        assert!(result.is_err());
+
+        // Truncated input is an error too
+        let result = PackedGraph::deserialize(&bytes[..bytes.len() - 8]);
+        assert!(result.is_err());
+    }
+
+    #[test]
+    fn packedgraph_path_handle_graph() {
+        let mut graph = test_graph_with_paths();
+
+        let path_steps = |graph: &PackedGraph, id: PathId| {
+            graph
+                .steps_iter(&id)
+                .map(|s| u64::from(graph.handle_of_step(&s).unwrap().id()))
+                .collect::<Vec<_>>()
+        };
+
+        assert_eq!(PathHandleGraph::path_count(&graph), 4);
+        assert!(graph.has_path(b"path2"));
+        assert!(!graph.has_path(b"path5"));
+
+        let path_2 = graph.name_to_path_handle(b"path2").unwrap();
+        assert_eq!(graph.path_handle_to_name(&path_2), b"path2");
+        assert_eq!(graph.step_count(&path_2), 5);
+        assert_eq!(path_steps(&graph, path_2), vec![5, 2, 8, 4, 6]);
+
+        // 5 + 10 + 5 + 4 + 4
+        assert_eq!(graph.path_bases_len(&path_2), Some(28));
+
+        // Step through the path forward and back
+        let begin = graph.path_begin(&path_2);
+        assert!(!graph.has_previous_step(&begin));
+        assert_eq!(graph.previous_step(&begin), graph.path_front_end(&path_2));
+
+        let mut step = begin;
+        let mut forward = Vec::new();
+        while !step.step().is_after() {
+            forward.push(u64::from(graph.handle_of_step(&step).unwrap().id()));
+            step = graph.next_step(&step);
+        }
+        assert_eq!(forward, vec![5, 2, 8, 4, 6]);
+
+        let back = graph.path_back(&path_2);
+        assert!(!graph.has_next_step(&back));
+        assert_eq!(graph.next_step(&back), graph.path_end(&path_2));
+        assert_eq!(graph.previous_step(&graph.path_end(&path_2)), back);
+
+        let step_8 = graph.next_step(&graph.next_step(&begin));
+        assert_eq!(graph.position_of_step(&step_8), Some(15));
+        assert_eq!(graph.step_at_position(&path_2, 15), Some(step_8));
+        assert_eq!(graph.step_at_position(&path_2, 19), Some(step_8));
+        assert_eq!(
+            graph.step_at_position(&path_2, 28),
+            Some(graph.path_end(&path_2))
+        );
+
+        // Append and prepend, updating the occurrences
+        let new_back = graph.append_step(&path_2, hnd(9));
+        let new_front = graph.prepend_step(&path_2, hnd(7));
+        assert_eq!(graph.path_back(&path_2), new_back);
+        assert_eq!(graph.path_begin(&path_2), new_front);
+        assert_eq!(path_steps(&graph, path_2), vec![7, 5, 2, 8, 4, 6, 9]);
+        assert_eq!(graph.step_count(&path_2), 7);
+
+        let occurs_on = |graph: &PackedGraph, id: u64| {
+            graph
+                .occurrences_iter(hnd(id))
+                .filter(|s| s.path_id() == path_2)
+                .count()
+        };
+        assert_eq!(occurs_on(&graph, 7), 1);
+        assert_eq!(occurs_on(&graph, 9), 1);
+
+        // Replace 2 8 4 with 3
+        let step_2 = graph.next_step(&graph.next_step(&new_front));
+        let step_4 = graph.next_step(&graph.next_step(&step_2));
+        let (first, after) =
+            graph.rewrite_segment(&step_2, &step_4, vec![hnd(3)]);
+        assert_eq!(path_steps(&graph, path_2), vec![7, 5, 3, 6, 9]);
+        assert_eq!(graph.handle_of_step(&first), Some(hnd(3)));
+        assert_eq!(graph.handle_of_step(&after), Some(hnd(6)));
+        assert_eq!(graph.step_count(&path_2), 5);
+        assert_eq!(occurs_on(&graph, 2), 0);
+        assert_eq!(occurs_on(&graph, 8), 0);
+        assert_eq!(occurs_on(&graph, 3), 1);
+
+        // Rewriting the whole path, including its head and tail
+        let (first, after) = graph.rewrite_segment(
+            &graph.path_begin(&path_2),
+            &graph.path_back(&path_2),
+            vec![hnd(1), hnd(2)],
+        );
+        assert_eq!(path_steps(&graph, path_2), vec![1, 2]);
+        assert_eq!(graph.handle_of_step(&first), Some(hnd(1)));
+        assert_eq!(after, graph.path_end(&path_2));
+        assert_eq!(graph.path_begin(&path_2), first);
+
+        graph.destroy_path(&path_2);
+        assert_eq!(PathHandleGraph::path_count(&graph), 3);
+        assert!(!graph.has_path(b"path2"));
+        assert_eq!(occurs_on(&graph, 1), 0);
+    }
+
+    #[test]
+    fn packedgraph_path_position_index() {
+        let mut graph = test_graph_with_paths();
+
+        // The step indices and offsets of the path, found by walking
+        // the path
+        let walk_path = |graph: &PackedGraph, id: PathId| {
+            let path = graph.paths.path_ref(id).unwrap();
+            let mut offset = 0;
+            let mut result = Vec::new();
+            for (ix, step) in path.steps() {
+                result.push((ix, offset));
+                offset += graph.node_len(step.handle);
+            }
+            (result, offset)
+        };
+
+        let check_path = |graph: &PackedGraph, id: PathId| {
+            let (steps, len) = walk_path(graph, id);
+            let index = graph.path_position_index(id).unwrap();
+            assert_eq!(index.len(), len);
+            assert_eq!(index.step_count(), steps.len());
+
+            for (i, &(ix, offset)) in steps.iter().enumerate() {
+                assert_eq!(index.position_of_step(ix), Some(offset));
+                let end = steps.get(i + 1).map(|&(_, o)| o).unwrap_or(len);
+                for pos in offset..end {
+                    assert_eq!(index.step_at_position(pos), Some(ix));
+                }
+            }
+            assert_eq!(index.step_at_position(len), None);
+        };
+
+        let path_ids = AllPathIds::all_path_ids(&graph).collect::<Vec<_>>();
+        graph.index_path_positions();
+        for &id in path_ids.iter() {
+            check_path(&graph, id);
+        }
+
+        let path_2 = graph.get_path_id(b"path2").unwrap();
+        assert_eq!(graph.path_position_index(path_2).unwrap().len(), 28);
+
+        // Modifying a path drops its index, and dividing a node
+        // modifies every path on it
+        graph.append_step(&path_2, hnd(9));
+        graph.prepend_step(&path_2, hnd(7));
+        check_path(&graph, path_2);
+        assert_eq!(graph.path_bases_len(&path_2), Some(36));
+
+        let (steps, _) = walk_path(&graph, path_2);
+        let removed = steps[3].0;
+        let removed_len = steps[4].1 - steps[3].1;
+        graph.with_path_mut_ctx(path_2, |path_ref| {
+            path_ref.remove_step(removed).into_iter().collect()
+        });
+        let index = graph.path_position_index(path_2).unwrap();
+        assert_eq!(index.position_of_step(removed), None);
+        assert_eq!(index.len(), 36 - removed_len);
+
+        graph.divide_handle(hnd(2), vec![3, 7]);
+        for &id in path_ids.iter() {
+            check_path(&graph, id);
+        }
+
+        // The indices aren't serialized, and are rebuilt when needed
+        let mut bytes: Vec<u8> = Vec::new();
+        graph.serialize(&mut bytes).unwrap();
+        let other = PackedGraph::deserialize(bytes.as_slice()).unwrap();
+        for &id in path_ids.iter() {
+            check_path(&other, id);
+        }
+
+        graph.clear_path_position_indices();
+        graph.remove_path(path_2);
+        graph.compact_paths();
+        for id in AllPathIds::all_path_ids(&graph) {
+            check_path(&graph, id);
+        }
+    }
+
+    #[test]
+    fn packedgraph_circular_paths() {
+        use crate::conversion::{from_gfa, to_gfa};
+
+        let mut graph = test_graph_with_paths();
+
+        let circ = graph.create_path_handle(b"circ", true);
+        for &h in [hnd(1), hnd(8), hnd(4)].iter() {
+            graph.append_step(&circ, h);
+        }
+
+        let path_1 = graph.name_to_path_handle(b"path1").unwrap();
+        assert!(graph.is_circular(&circ));
+        assert!(!graph.is_circular(&path_1));
+
+        // The last step is followed by the first, and vice versa
+        let begin = graph.path_begin(&circ);
+        let back = graph.path_back(&circ);
+        assert!(graph.has_next_step(&back));
+        assert!(graph.has_previous_step(&begin));
+        assert_eq!(graph.next_step(&back), begin);
+        assert_eq!(graph.previous_step(&begin), back);
+
+        let path = graph.paths.path_ref(circ).unwrap();
+        let last = path.last_step();
+        assert_eq!(path.next_step(last), Some(path.first_step()));
+
+        // Linear paths are unaffected
+        let back_1 = graph.path_back(&path_1);
+        assert!(!graph.has_next_step(&back_1));
+        assert_eq!(graph.next_step(&back_1), graph.path_end(&path_1));
+        let path = graph.paths.path_ref(path_1).unwrap();
+        assert_eq!(path.next_step(path.last_step()), None);
+
+        // Positions wrap around the 4 + 5 + 4 bases of the path
+        let step_8 = graph.next_step(&begin);
+        assert_eq!(graph.path_bases_len(&circ), Some(13));
+        assert_eq!(graph.step_at_position(&circ, 7), Some(step_8));
+        assert_eq!(graph.step_at_position(&circ, 13), Some(begin));
+        assert_eq!(graph.step_at_position(&circ, 18), Some(step_8));
+        assert_eq!(
+            graph.step_at_position(&path_1, 100),
+            Some(graph.path_end(&path_1))
+        );
+
+        // Circularity is preserved through GFA
+        let gfa = to_gfa(&graph);
+        let circ_lines = gfa
+            .paths
+            .iter()
+            .filter(|p| crate::conversion::is_circular_path(p))
+            .map(|p| p.path_name.to_vec())
+            .collect::<Vec<_>>();
+        assert_eq!(circ_lines, vec![b"circ".to_vec()]);
+
+        let other: PackedGraph = from_gfa(&gfa);
+        let other_circ = other.name_to_path_handle(b"circ").unwrap();
+        let other_path_1 = other.name_to_path_handle(b"path1").unwrap();
+        assert!(other.is_circular(&other_circ));
+        assert!(!other.is_circular(&other_path_1));
+    }
+
+    #[test]
+    fn packedgraph_gfa_roundtrip() {
+        use crate::conversion::{from_gfa, to_gfa};
+        use gfa::{gfa::GFA, parser::GFAParser};
+
+        let parser = GFAParser::new();
+        let gfa: GFA<usize, ()> = parser.parse_file("./lil.gfa").unwrap();
+
+        let graph: PackedGraph = from_gfa(&gfa);
+
+        assert_eq!(graph.node_count(), 15);
+        assert_eq!(graph.all_edges().count(), 20);
+        assert_eq!(PathHandleGraph::path_count(&graph), 3);
+
+        let path_x = graph.name_to_path_handle(b"x").unwrap();
+        let steps = graph
+            .steps_iter(&path_x)
+            .map(|s| u64::from(graph.handle_of_step(&s).unwrap().id()))
+            .collect::<Vec<_>>();
+        assert_eq!(steps, vec![1, 3, 5, 6, 8, 9, 11, 12, 14, 15]);
+
+        let out = to_gfa(&graph);
+        assert_eq!(out.segments.len(), gfa.segments.len());
+        assert_eq!(out.links.len(), gfa.links.len());
+
+        let mut in_paths = gfa
+            .paths
+            .iter()
+            .map(|p| {
+                let steps = p
+                    .iter()
+                    .map(|(seg, orient)| Handle::new(seg, orient))
+                    .collect::<Vec<_>>();
+                (p.path_name.clone(), steps)
+            })
+            .collect::<Vec<_>>();
+        let mut out_paths = out
+            .paths
+            .iter()
+            .map(|p| {
+                let steps = p
+                    .iter()
+                    .map(|(seg, orient)| Handle::new(seg, orient))
+                    .collect::<Vec<_>>();
+                (p.path_name.clone(), steps)
+            })
+            .collect::<Vec<_>>();
+        in_paths.sort();
+        out_paths.sort();
+        assert_eq!(in_paths, out_paths);
+    }
+
+    #[test]
+    fn packedgraph_embedded_paths() {
+        let mut graph = PackedGraph::new();
+        for seq in [b"GTCA", b"AAGT", b"CCTA"].iter() {
+            graph.append_handle(&seq[..]);
+        }
+        graph.create_edge(Edge(hnd(1), hnd(2)));
+        graph.create_edge(Edge(hnd(2), hnd(3)));
+        graph.create_edge(Edge(hnd(3), hnd(1)));
+        graph.create_edge(Edge(hnd(1), hnd(3)));
+
+        let p1 = graph.create_path(b"path1", false);
+        let p2 = graph.create_path(b"path2", true);
+
+        {
+            let mut path = graph.get_path_mut(p1).unwrap();
+            path.append_step(hnd(2));
+            path.append_step(hnd(3));
+            path.prepend_step(hnd(1));
+        }
+        {
+            let mut path = graph.get_path_mut(p2).unwrap();
+            for &id in [1, 2, 3].iter() {
+                path.append_step(hnd(id));
+            }
+        }
+
+        assert!(graph.validate().is_valid());
+
+        let path_handles = |graph: &PackedGraph, id: PathId| {
+            let path = EmbeddedPaths::get_path(graph, id).unwrap();
+            path.steps()
+                .map(|(_, step)| u64::from(step.handle.id()))
+                .collect::<Vec<_>>()
+        };
+
+        assert_eq!(EmbeddedPaths::path_count(&graph), 2);
+        assert_eq!(graph.lookup_path_id(b"path2"), Some(p2));
+        assert_eq!(
+            EmbeddedPaths::get_path_name(&graph, p1)
+                .unwrap()
+                .collect::<Vec<_>>(),
+            b"path1"
+        );
+        assert_eq!(path_handles(&graph, p1), vec![1, 2, 3]);
+        assert_eq!(path_handles(&graph, p2), vec![1, 2, 3]);
+
+        let steps_on = |graph: &PackedGraph, id: u64| {
+            let mut steps = graph.steps_on_handle(hnd(id)).collect::<Vec<_>>();
+            steps.sort();
+            steps
+        };
+
+        let step = |path: PathId, ix: usize| {
+            StepHandle::new(path, step::PathStep::Step(ix))
+        };
+
+        // Prepended steps are stored after the existing steps
+        assert_eq!(steps_on(&graph, 2), vec![step(p1, 0), step(p2, 1)]);
+        assert_eq!(steps_on(&graph, 1), vec![step(p1, 2), step(p2, 0)]);
+
+        // Removing a step also removes its occurrence
+        {
+            let mut path = graph.get_path_mut(p2).unwrap();
+            let removed = path.remove_step(PathStepIx::from_zero_based(1usize));
+            assert_eq!(
+                removed,
+                Some(StepUpdate::Remove {
+                    handle: hnd(2),
+                    step: PathStepIx::from_zero_based(1usize)
+                })
+            );
+            path.set_circularity(false);
+        }
+
+        assert!(graph.validate().is_valid());
+        assert_eq!(path_handles(&graph, p2), vec![1, 3]);
+        assert!(!EmbeddedPaths::get_path(&graph, p2).unwrap().circular());
+        assert_eq!(steps_on(&graph, 2), vec![step(p1, 0)]);
+
+        graph.remove_path(p1);
+        assert_eq!(EmbeddedPaths::path_count(&graph), 1);
+        assert!(EmbeddedPaths::get_path(&graph, p1).is_none());
+        assert!(EmbeddedPaths::get_path_name(&graph, p1).is_none());
+        assert!(graph.get_path_mut(p1).is_none());
+        assert!(steps_on(&graph, 2).is_empty());
+        assert!(graph.validate().is_valid());
+    }
+
+    #[test]
+    fn packedgraph_parallel_iterators() {
+        use rayon::prelude::*;
+
+        let mut graph = PackedGraph::new();
+
+        // Enough nodes for several chunks, starting past the first
+        // chunk, with some nodes removed
+        let ids = (5000..15000u64).collect::<Vec<_>>();
+        let seqs: [&[u8]; 3] = [b"GTCA", b"AAGTGCTAGT", b"ATA"];
+        for &id in ids.iter() {
+            graph.create_handle(seqs[id as usize % 3], id);
+        }
+        for w in ids.windows(2) {
+            graph.create_edge(Edge(hnd(w[0]), hnd(w[1])));
+        }
+        for &id in ids.iter().step_by(7) {
+            graph.create_edge(Edge(hnd(id), hnd(id + 3)));
+        }
+        for &id in ids.iter().step_by(1000) {
+            graph.remove_handle(hnd(id + 1));
+        }
+
+        for i in 0..10 {
+            let name = format!("path{}", i);
+            let path = graph.create_path(name.as_bytes(), false);
+            let mut path = graph.get_path_mut(path).unwrap();
+            path.append_step(hnd(5010 + i));
+        }
+
+        let mut handles = graph.all_handles().collect::<Vec<_>>();
+        let mut handles_par = graph.all_handles_par().collect::<Vec<_>>();
+        handles.sort();
+        handles_par.sort();
+        assert_eq!(handles.len(), 10000 - 10);
+        assert_eq!(handles, handles_par);
+
+        let mut edges = graph.all_edges().collect::<Vec<_>>();
+        let mut edges_par = graph.all_edges_par().collect::<Vec<_>>();
+        edges.sort();
+        edges_par.sort();
+        assert_eq!(edges.len(), graph.edge_count());
+        assert_eq!(edges, edges_par);
+
+        let mut path_ids = AllPathIds::all_path_ids(&graph).collect::<Vec<_>>();
+        let mut path_ids_par = graph.all_path_ids_par().collect::<Vec<_>>();
+        path_ids.sort();
+        path_ids_par.sort();
+        assert_eq!(path_ids.len(), 10);
+        assert_eq!(path_ids, path_ids_par);
+
+        let total_len: usize = graph
+            .all_handles_par()
+            .map(|h| graph.sequence_iter(h).count())
+            .sum();
+        assert_eq!(total_len, graph.total_length());
+
+        let empty = PackedGraph::new();
+        assert_eq!(empty.all_handles_par().count(), 0);
+        assert_eq!(empty.all_edges_par().count(), 0);
+        assert_eq!(empty.all_path_ids_par().count(), 0);
+    }
+
+    #[test]
+    fn packedgraph_with_all_paths_mut_par() {
+        let mut graph = test_graph_with_paths();
+
+        let path_handles = |graph: &PackedGraph, name: &[u8]| {
+            let id = graph.paths.path_names.get_path_id(name).unwrap();
+            graph
+                .steps_iter(&id)
+                .filter_map(|s| graph.handle_of_step(&s))
+                .map(|h| u64::from(h.id()))
+                .collect::<Vec<_>>()
+        };
+
+        let path_4 = graph.paths.path_names.get_path_id(b"path4").unwrap();
+        graph.remove_path(path_4);
+
+        // Build the position indices first, so that any index left
+        // stale by the edit would be used below
+        graph.index_path_positions();
+
+        // Replace the first step of each path with node 9
+        graph.with_all_paths_mut_par(|_path_id, path| {
+            let first = path.as_path_ref().first_step().0;
+            let mut updates = Vec::new();
+            updates.extend(path.remove_step(first));
+            updates.push(path.prepend_step(hnd(9)));
+            updates
+        });
+
+        assert_eq!(path_handles(&graph, b"path1"), vec![9, 8, 4, 6]);
+        assert_eq!(path_handles(&graph, b"path2"), vec![9, 2, 8, 4, 6]);
+        assert_eq!(path_handles(&graph, b"path3"), vec![9, 2, 8, 4, 9, 6]);
+
+        // The removed path is left alone
+        assert_eq!(graph.paths.path_ref(path_4).map(|p| p.len()), Some(0));
+        assert_eq!(graph.occurrences_iter(hnd(7)).count(), 0);
+
+        assert_eq!(graph.occurrences_iter(hnd(1)).count(), 0);
+        assert_eq!(graph.occurrences_iter(hnd(5)).count(), 0);
+        assert_eq!(graph.occurrences_iter(hnd(9)).count(), 4);
+        assert!(occurrences_valid(&graph));
+
+        // Every step has exactly one occurrence
+        let step_count = graph
+            .paths_iter()
+            .map(|id| graph.steps_iter(id).count())
+            .sum::<usize>();
+        let occur_count = graph
+            .all_handles()
+            .map(|h| graph.occurrences_iter(h).count())
+            .sum::<usize>();
+        assert_eq!(occur_count, step_count);
+
+        for &id in graph.paths_iter() {
+            let mut pos = 0;
+            for step in graph.steps_iter(&id) {
+                assert_eq!(graph.position_of_step(&step), Some(pos));
+                assert_eq!(graph.step_at_position(&id, pos), Some(step));
+                pos += graph.node_len(graph.handle_of_step(&step).unwrap());
+            }
+            assert_eq!(graph.path_bases_len(&id), Some(pos));
+        }
+    }
 }
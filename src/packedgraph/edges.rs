@@ -1,5 +1,7 @@
 use crate::{handle::Handle, packed::*};
 
+use std::io::{self, Read, Write};
+
 use fnv::FnvHashMap;
 
 use std::num::NonZeroUsize;
@@ -72,6 +74,22 @@ crate::impl_space_usage!(EdgeLists, [record_vec, removed_records]);
 
 pub type EdgeRecord = (Handle, EdgeListIx);
 
+impl Serializable for EdgeLists {
+    fn serialize<W: Write>(&self, writer: &mut W) -> io::Result<()> {
+        self.record_vec.serialize(writer)?;
+        self.removed_records.serialize(writer)
+    }
+
+    fn deserialize<R: Read>(reader: &mut R) -> io::Result<Self> {
+        let record_vec = PagedIntVec::deserialize(reader)?;
+        let removed_records = Vec::deserialize(reader)?;
+        Ok(Self {
+            record_vec,
+            removed_records,
+        })
+    }
+}
+
 impl PackedList for EdgeLists {
     type ListPtr = EdgeListIx;
     type ListRecord = EdgeRecord;
@@ -151,6 +169,25 @@ impl EdgeLists {
         num_records - self.removed_records.len()
     }
 
+    /// Returns the number of edge records, including removed ones.
+    #[inline]
+    pub(super) fn record_count(&self) -> usize {
+        self.record_vec.len() / EdgeVecIx::RECORD_WIDTH
+    }
+
+    #[inline]
+    pub(super) fn removed_records(&self) -> &[EdgeListIx] {
+        &self.removed_records
+    }
+
+    /// Reserve space for `additional` more edges, each of which
+    /// takes up one record in the edge list of both of its handles.
+    pub(super) fn reserve(&mut self, additional: usize) {
+        let len = self.record_vec.len();
+        self.record_vec
+            .reserve(len + 2 * EdgeVecIx::RECORD_WIDTH * additional);
+    }
+
     /// Get the handle for the record at the index, if the index is
     /// not null.
     #[inline]
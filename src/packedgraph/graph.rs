@@ -5,17 +5,21 @@ pub(super) use super::{
     nodes::{GraphVecIx, NodeIdIndexMap, NodeRecords},
     occurrences::{NodeOccurrences, OccurListIx, OccurRecord},
     paths::{PackedGraphPaths, PathStepIx},
-    sequence::{PackedSeqIter, SeqRecordIx, Sequences},
+    sequence::{PackedSeqIter, SeqRecordIx, SequenceEncoding, Sequences},
 };
 
+use std::io::{self, Read, Write};
+
 use fnv::{FnvHashMap, FnvHashSet};
 
 use crate::handle::{Direction, Edge, Handle, NodeId};
 
 use crate::pathhandlegraph::PathId;
 
-use crate::handlegraph::HandleNeighbors;
+use crate::handlegraph::{AllHandles, HandleNeighbors, HandleSequences};
+use crate::mutablehandlegraph::AdditiveHandleGraph;
 
+use crate::packed::serialize::*;
 use crate::packed::traits::*;
 
 use super::list;
@@ -32,28 +36,215 @@ pub struct PackedGraph {
     pub(super) edges: EdgeLists,
     pub(super) occurrences: NodeOccurrences,
     pub(super) paths: PackedGraphPaths,
+    pub(super) edge_count: usize,
 }
 
 crate::impl_space_usage!(PackedGraph, [nodes, edges, occurrences, paths]);
 
 impl Default for PackedGraph {
     fn default() -> Self {
-        let nodes = Default::default();
-        let edges = Default::default();
-        let occurrences = Default::default();
-        let paths = Default::default();
+        Self::with_encoding(Default::default())
+    }
+}
+
+/// Magic bytes at the start of every serialized `PackedGraph`.
+pub(crate) const SERIALIZED_MAGIC: [u8; 8] = *b"PKDGRAPH";
+
+/// The version of the binary format written by
+/// `PackedGraph::serialize`. Bump whenever the layout of any of the
+/// serialized collections changes.
+pub const FORMAT_VERSION: u64 = 2;
+
+impl PackedGraph {
+    pub fn new() -> Self {
+        Default::default()
+    }
+
+    /// Create an empty graph that stores its sequences using
+    /// `encoding`. Graphs created with `new` use
+    /// `SequenceEncoding::Dna`.
+    pub fn with_encoding(encoding: SequenceEncoding) -> Self {
         PackedGraph {
+            nodes: NodeRecords::with_encoding(encoding),
+            edges: Default::default(),
+            occurrences: Default::default(),
+            paths: Default::default(),
+            edge_count: 0,
+        }
+    }
+
+    /// The encoding used to store the sequences in the graph.
+    #[inline]
+    pub fn encoding(&self) -> SequenceEncoding {
+        self.nodes.sequences().encoding()
+    }
+
+    /// Write the graph to `writer` in the binary `PackedGraph`
+    /// format.
+    ///
+    /// The output starts with a magic string and the format version,
+    /// followed by the packed collections of the graph, written
+    /// verbatim, and ends with a checksum of everything after the
+    /// header.
+    pub fn serialize<W: Write>(&self, writer: W) -> io::Result<()> {
+        let mut writer = io::BufWriter::new(writer);
+        writer.write_all(&SERIALIZED_MAGIC)?;
+        write_u64(&mut writer, FORMAT_VERSION)?;
+
+        let mut body = ChecksumWriter::new(writer);
+        self.nodes.serialize(&mut body)?;
+        self.edges.serialize(&mut body)?;
+        self.occurrences.serialize(&mut body)?;
+        self.paths.serialize(&mut body)?;
+
+        let checksum = body.checksum();
+        let mut writer = body.into_inner();
+        write_u64(&mut writer, checksum)?;
+        writer.flush()
+    }
+
+    /// Read a graph that was written using `PackedGraph::serialize`.
+    ///
+    /// Returns an error of kind `InvalidData` if the input is not a
+    /// serialized `PackedGraph`, was written using a different
+    /// format version, or fails the checksum.
+    pub fn deserialize<R: Read>(reader: R) -> io::Result<Self> {
+        let mut reader = io::BufReader::new(reader);
+
+        let mut magic = [0u8; 8];
+        reader.read_exact(&mut magic)?;
+        if magic != SERIALIZED_MAGIC {
+            return Err(invalid_data("not a serialized PackedGraph"));
+        }
+
+        let version = read_u64(&mut reader)?;
+        if version != FORMAT_VERSION {
+            return Err(invalid_data("unsupported PackedGraph format version"));
+        }
+
+        let mut body = ChecksumReader::new(reader);
+        let nodes = NodeRecords::deserialize(&mut body)?;
+        let edges = EdgeLists::deserialize(&mut body)?;
+        let occurrences = NodeOccurrences::deserialize(&mut body)?;
+        let paths = PackedGraphPaths::deserialize(&mut body)?;
+
+        let checksum = body.checksum();
+        let mut reader = body.into_inner();
+        if read_u64(&mut reader)? != checksum {
+            return Err(invalid_data("PackedGraph checksum mismatch"));
+        }
+
+        // The edge count isn't serialized, as each edge is stored as
+        // two edge records
+        let edge_count = edges.len() / 2;
+
+        Ok(PackedGraph {
             nodes,
             edges,
             occurrences,
             paths,
+            edge_count,
+        })
+    }
+
+    /// Rebuild all the packed collections in the graph so that they
+    /// no longer contain the records of removed nodes, edges, path
+    /// steps, and occurrences.
+    ///
+    /// Node IDs, path IDs and path names are preserved, but the
+    /// internal record indices are renumbered, so any step handles
+    /// obtained before defragmenting are invalidated.
+    pub fn defragment(&mut self) {
+        let mut graph = PackedGraph::with_encoding(self.encoding());
+
+        for handle in self.all_handles() {
+            let seq = self.sequence(handle);
+            graph.create_handle(&seq, handle.id());
+        }
+
+        // Copy the edge lists record by record, rather than through
+        // `create_edge`, so that the stored handles and their order
+        // are preserved exactly
+        for handle in self.all_handles() {
+            let old_rec = self.nodes.handle_record(handle).unwrap();
+            let new_rec = graph.nodes.handle_record(handle).unwrap();
+
+            for &dir in [Direction::Left, Direction::Right].iter() {
+                let old_list = self.nodes.get_edge_list(old_rec, dir);
+                let records = self
+                    .edges
+                    .iter(old_list)
+                    .map(|(_, (handle, _))| handle)
+                    .collect::<Vec<_>>();
+
+                let new_list = records
+                    .into_iter()
+                    .rev()
+                    .fold(EdgeListIx::null(), |next, handle| {
+                        graph.edges.append_record(handle, next)
+                    });
+
+                graph.nodes.set_edge_list(new_rec, dir, new_list);
+            }
         }
+
+        graph.edge_count = self.edge_count;
+
+        let mut paths = std::mem::take(&mut self.paths);
+        let updates = paths.defragment();
+        graph.paths = paths;
+
+        for (path_id, steps) in updates {
+            graph.apply_node_occurrences_iter(path_id, steps);
+        }
+
+        *self = graph;
     }
-}
 
-impl PackedGraph {
-    pub fn new() -> Self {
-        Default::default()
+    /// Release the slots of all paths that have been removed from
+    /// the graph, renumbering the remaining paths so that the path
+    /// IDs are tightly packed while preserving their order. The node
+    /// occurrences are updated to use the new IDs.
+    ///
+    /// Returns a map from the old to the new ID of each remaining
+    /// path, or None if no paths have been removed, in which case all
+    /// IDs are unchanged. Any path or step handles obtained before
+    /// compacting must be translated using the map.
+    pub fn compact_paths(&mut self) -> Option<FnvHashMap<PathId, PathId>> {
+        let id_map = self.paths.compact_paths()?;
+        self.occurrences.update_path_ids(&id_map);
+        Some(id_map)
+    }
+
+    /// Get the position index of the path, building it first if it
+    /// doesn't exist, which takes time linear in the number of steps.
+    /// Returns `None` if the path doesn't exist.
+    ///
+    /// The index is kept until the path is modified, and is used by
+    /// the positional queries of `PathHandleGraph`.
+    pub fn path_position_index(
+        &self,
+        id: PathId,
+    ) -> Option<&paths::PathPositionIndex> {
+        self.paths
+            .position_index(id, |handle| self.node_len(handle))
+    }
+
+    /// Build the position indices of all paths that don't have one,
+    /// in parallel.
+    pub fn index_path_positions(&self) {
+        use rayon::prelude::*;
+
+        let path_ids = self.paths.path_names.path_ids().collect::<Vec<_>>();
+        path_ids.into_par_iter().for_each(|&id| {
+            self.path_position_index(id);
+        });
+    }
+
+    /// Drop the position indices of all paths, freeing the memory
+    /// they use. They will be rebuilt when needed.
+    pub fn clear_path_position_indices(&mut self) {
+        self.paths.clear_position_indices();
     }
 
     pub(super) fn remove_edge_impl(&mut self, edge: Edge) -> Option<()> {
@@ -100,6 +291,8 @@ impl PackedGraph {
         self.nodes
             .set_edge_list(right_gix, right_edge_dir, new_right_head);
 
+        self.edge_count -= 1;
+
         Some(())
     }
 
@@ -155,86 +348,34 @@ impl PackedGraph {
         Some(())
     }
 
-    pub(super) fn apply_node_occurrence_consumer<'a>(
-        receiver: std::sync::mpsc::Receiver<(PathId, Vec<paths::StepUpdate>)>,
-        nodes: &'a mut NodeRecords,
-        occurrences: &'a mut NodeOccurrences,
-    ) {
-        use paths::StepUpdate;
-        while let Ok((path_id, updates)) = receiver.recv() {
-            for step_update in updates {
-                match step_update {
-                    StepUpdate::Insert { handle, step } => {
-                        let rec_id = nodes.handle_record(handle).unwrap();
-                        let vec_ix = rec_id.to_zero_based().unwrap();
-
-                        let occur_ix =
-                            nodes.node_occurrence_map.get_unpack(vec_ix);
-
-                        let new_occur_ix =
-                            occurrences.append_entry(path_id, step, occur_ix);
-
-                        nodes
-                            .node_occurrence_map
-                            .set_pack(vec_ix, new_occur_ix);
-                    }
-                    StepUpdate::Remove { handle, step } => {
-                        let rec_id = nodes.handle_record(handle).unwrap();
-                        let vec_ix = rec_id.to_zero_based().unwrap();
-
-                        let occur_head =
-                            nodes.node_occurrence_map.get_unpack(vec_ix);
-
-                        let new_occur_ix = occurrences
-                            .iter_mut(occur_head)
-                            .remove_record_with(|_, record| {
-                                record.path_id == path_id
-                                    && record.offset == step
-                            });
-
-                        if let Some(new_head) = new_occur_ix {
-                            if new_head != occur_head {
-                                nodes
-                                    .node_occurrence_map
-                                    .set_pack(vec_ix, new_head);
-                            }
-                        }
-                    }
-                }
-            }
-        }
-    }
-
-    pub(super) fn apply_node_occurrence(
-        &mut self,
+    /// Update the occurrences of the node of a step that was added
+    /// to or removed from a path.
+    pub(super) fn apply_occurrence_update(
+        nodes: &mut NodeRecords,
+        occurrences: &mut NodeOccurrences,
         path_id: PathId,
         step_update: paths::StepUpdate,
     ) {
         use paths::StepUpdate;
         match step_update {
             StepUpdate::Insert { handle, step } => {
-                let rec_id = self.nodes.handle_record(handle).unwrap();
+                let rec_id = nodes.handle_record(handle).unwrap();
                 let vec_ix = rec_id.to_zero_based().unwrap();
 
-                let occur_ix =
-                    self.nodes.node_occurrence_map.get_unpack(vec_ix);
+                let occur_ix = nodes.node_occurrence_map.get_unpack(vec_ix);
 
                 let new_occur_ix =
-                    self.occurrences.append_entry(path_id, step, occur_ix);
+                    occurrences.append_entry(path_id, step, occur_ix);
 
-                self.nodes
-                    .node_occurrence_map
-                    .set_pack(vec_ix, new_occur_ix);
+                nodes.node_occurrence_map.set_pack(vec_ix, new_occur_ix);
             }
             StepUpdate::Remove { handle, step } => {
-                let rec_id = self.nodes.handle_record(handle).unwrap();
+                let rec_id = nodes.handle_record(handle).unwrap();
                 let vec_ix = rec_id.to_zero_based().unwrap();
 
-                let occur_head =
-                    self.nodes.node_occurrence_map.get_unpack(vec_ix);
+                let occur_head = nodes.node_occurrence_map.get_unpack(vec_ix);
 
-                let new_occur_ix = self
-                    .occurrences
+                let new_occur_ix = occurrences
                     .iter_mut(occur_head)
                     .remove_record_with(|_, record| {
                         record.path_id == path_id && record.offset == step
@@ -242,15 +383,26 @@ impl PackedGraph {
 
                 if let Some(new_head) = new_occur_ix {
                     if new_head != occur_head {
-                        self.nodes
-                            .node_occurrence_map
-                            .set_pack(vec_ix, new_head);
+                        nodes.node_occurrence_map.set_pack(vec_ix, new_head);
                     }
                 }
             }
         }
     }
 
+    pub(super) fn apply_node_occurrence(
+        &mut self,
+        path_id: PathId,
+        step_update: paths::StepUpdate,
+    ) {
+        Self::apply_occurrence_update(
+            &mut self.nodes,
+            &mut self.occurrences,
+            path_id,
+            step_update,
+        );
+    }
+
     pub(super) fn apply_node_occurrences_iter<I>(
         &mut self,
         path_id: PathId,
@@ -291,7 +443,14 @@ impl PackedGraph {
         }
     }
 
-    pub(super) fn with_all_paths_mut_ctx<'a, F>(&'a mut self, f: F)
+    /// Run `f` on every path in the graph in parallel, then update
+    /// the node occurrences with the `StepUpdate`s it returned, in
+    /// one batch once all paths have been processed.
+    ///
+    /// `f` must return the update for every step it adds to or
+    /// removes from its path, or the occurrences will be out of sync
+    /// with the paths. Paths that have been removed are skipped.
+    pub fn with_all_paths_mut_par<F>(&mut self, f: F)
     where
         for<'b> F: Fn(
                 PathId,
@@ -304,35 +463,4 @@ impl PackedGraph {
             self.apply_node_occurrences_iter(path_id, steps);
         }
     }
-
-    pub(super) fn with_all_paths_mut_ctx_<F>(&mut self, f: F)
-    where
-        for<'b> F: Fn(
-                PathId,
-                &mut paths::PackedPathRefMut<'b>,
-            ) -> Vec<paths::StepUpdate>
-            + Sync,
-    {
-        use rayon::prelude::*;
-        use std::sync::mpsc;
-        use std::thread;
-
-        let (sender, receiver) =
-            mpsc::channel::<(PathId, Vec<paths::StepUpdate>)>();
-
-        let mut paths = &mut self.paths;
-        let mut nodes = &mut self.nodes;
-        let mut occurrences = &mut self.occurrences;
-
-        let mut mut_ctx = paths.get_multipath_mut_ctx();
-        let refs_mut = mut_ctx.ref_muts_par();
-
-        refs_mut.for_each_with(sender, |s, path| {
-            let path_id = path.path_id;
-            let updates = f(path_id, path);
-            s.send((path_id, updates)).unwrap();
-        });
-
-        Self::apply_node_occurrence_consumer(receiver, nodes, occurrences);
-    }
 }
@@ -14,77 +14,7 @@ pub use list::*;
 
 use crate::packed::traits::PackedElement;
 
-/// An index that is 1-based, and uses 0 to denote missing data/the
-/// empty record/the empty list.
-///
-/// Can be constructed from zero-based indices (e.g. when using the
-/// length of a collection to produce the new index, and the
-/// collection is empty), or unwrapped 1-based indices.
-pub trait OneBasedIndex: Copy + Sized {
-    /// Construct a 1-based index from a 0-based index, shifting
-    /// it into the 1-based index. The resulting index will
-    /// never be the null identifier.
-    fn from_zero_based<I: Into<usize>>(ix: I) -> Self;
-
-    /// Construct a 1-based index from a 0-based index for records
-    /// that are `width` elements long, adjusting for the record width
-    /// and shifting it into a 1-based index. The resulting index will
-    /// never be the null identifier.
-    fn from_record_start<I: Into<usize>>(ix: I, width: usize) -> Self;
-
-    /// Construct a 1-based index from a 1-based index. If the input
-    /// is zero, the resulting index will be the null
-    /// identifier.
-    fn from_one_based<I: Into<usize>>(ix: I) -> Self;
-
-    /// Transform the 1-based index into a 0-based index that can be
-    /// used to retrieve the element corresponding to this identifier
-    /// from a collection.
-    ///
-    /// Returns `None` if the index is the null index.
-    fn to_zero_based(self) -> Option<usize>;
-
-    /// Transform the 1-based index into a 0-based index that can be
-    /// used to retrieve a `width` elements long record in a
-    /// collection at the index corresponding to this identifier.
-    ///
-    /// Returns `None` if the index is the null index.
-    fn to_record_start(self, width: usize) -> Option<usize>;
-
-    /// Transform the 1-based index into a 0-based index that can be
-    /// used to retrieve the `ix`th field in a `width` elements long
-    /// record in a collection at the index corresponding to this
-    /// identifier.
-    ///
-    /// Returns `None` if the index is the null index.
-    fn to_record_ix(self, width: usize, ix: usize) -> Option<usize>;
-
-    /// Build a 1-based index from a `u64` that was stored in a collection.
-    fn from_vector_value(v: u64) -> Self;
-
-    /// Transform the 1-based index into a `u64` that can be stored in
-    /// a collection.
-    fn to_vector_value(self) -> u64;
-
-    /// `true` if this identifier is the null identifier.
-    fn is_null(&self) -> bool;
-
-    /// Returns the null identifier.
-    fn null() -> Self;
-}
-
-/// Any `OneBasedIndex` can be stored in any kind of packed collection.
-impl<T: OneBasedIndex> PackedElement for T {
-    #[inline]
-    fn unpack(v: u64) -> Self {
-        Self::from_vector_value(v)
-    }
-
-    #[inline]
-    fn pack(self) -> u64 {
-        self.to_vector_value()
-    }
-}
+pub use crate::packed::OneBasedIndex;
 
 /// The identifier and index for all node-related records in the
 /// PackedGraph.
@@ -200,95 +130,8 @@ where
     result
 }
 
-#[macro_export]
-macro_rules! impl_one_based_index {
-    ($for:ty) => {
-        impl OneBasedIndex for $for {
-            #[inline]
-            fn from_zero_based<I: Into<usize>>(ix: I) -> Self {
-                Self(NonZeroUsize::new(ix.into() + 1))
-            }
-            #[inline]
-            fn from_record_start<I: Into<usize>>(ix: I, width: usize) -> Self {
-                Self(NonZeroUsize::new((ix.into() / width) + 1))
-            }
-
-            #[inline]
-            fn from_one_based<I: Into<usize>>(ix: I) -> Self {
-                Self(NonZeroUsize::new(ix.into()))
-            }
-
-            #[inline]
-            fn to_zero_based(self) -> Option<usize> {
-                self.0.map(|u| u.get() - 1)
-            }
-
-            #[inline]
-            fn to_record_start(self, width: usize) -> Option<usize> {
-                self.0.map(|u| (u.get() - 1) * width)
-            }
-
-            #[inline]
-            fn to_record_ix(self, width: usize, ix: usize) -> Option<usize> {
-                self.0.map(|u| ((u.get() - 1) * width) + ix)
-            }
-
-            #[inline]
-            fn from_vector_value(v: u64) -> Self {
-                Self(NonZeroUsize::new(v as usize))
-            }
-
-            #[inline]
-            fn to_vector_value(self) -> u64 {
-                match self.0 {
-                    None => 0,
-                    Some(x) => x.get() as u64,
-                }
-            }
-
-            #[inline]
-            fn is_null(&self) -> bool {
-                self.0.is_none()
-            }
-
-            #[inline]
-            fn null() -> Self {
-                Self(None)
-            }
-        }
-    };
-}
-
-#[macro_export]
-macro_rules! impl_one_based_index_default {
-    ($index:ty) => {
-        impl Default for $index {
-            #[inline]
-            fn default() -> Self {
-                Self::null()
-            }
-        }
-    };
-}
-
-#[macro_export]
-macro_rules! impl_space_usage_stack_newtype {
-    ($type:ty) => {
-        impl succinct::SpaceUsage for $type {
-            #[inline]
-            fn is_stack_only() -> bool {
-                true
-            }
-            #[inline]
-            fn heap_bytes(&self) -> usize {
-                0
-            }
-        }
-    };
-}
-
-impl_one_based_index!(NodeRecordId);
-impl_space_usage_stack_newtype!(NodeRecordId);
+crate::impl_one_based_index!(NodeRecordId);
+crate::impl_space_usage_stack_newtype!(NodeRecordId);
 // impl_one_based_index_default!(NodeRecordId);
 
 #[cfg(test)]
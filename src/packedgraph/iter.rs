@@ -1,9 +1,20 @@
 use crate::handle::Handle;
 
-use super::EdgeLists;
+use super::{EdgeLists, PackedGraph};
 
 use super::index::list;
 
+/// Parallel iterator over a PackedGraph, split into chunks by
+/// index. Each chunk is mapped to a sequential iterator `I`, and the
+/// chunks are iterated through in parallel.
+pub type PackedGraphPar<'a, I> = rayon::iter::FlattenIter<
+    rayon::iter::MapWith<
+        rayon::range::Iter<usize>,
+        &'a PackedGraph,
+        fn(&mut &'a PackedGraph, usize) -> I,
+    >,
+>;
+
 /// Iterator over a PackedGraph's handles. For every non-zero value in
 /// the PackedDeque holding the PackedGraph's node ID mappings, the
 /// corresponding index is mapped back to the original ID and yielded
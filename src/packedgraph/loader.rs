@@ -0,0 +1,334 @@
+use bstr::{BString, ByteSlice};
+
+use gfa::{
+    gfa::Line,
+    optfields::{OptFields, OptionalFields},
+    parser::{GFAParser, GFAResult},
+};
+
+use rayon::prelude::*;
+
+use crate::{
+    conversion::{is_circular_path, GFAConversionResult},
+    error::HandleGraphError,
+    handle::{Edge, Handle, NodeId},
+    mutablehandlegraph::AdditiveHandleGraph,
+    pathgraph::PathHandleGraph,
+};
+
+use super::graph::PackedGraph;
+use super::sequence::SequenceEncoding;
+
+/// A GFA path line, parsed into the steps to append to the path.
+struct ParsedPath {
+    name: BString,
+    circular: bool,
+    steps: Vec<Handle>,
+}
+
+/// Parse the lines in parallel, keeping the lines for which `f`
+/// returns `Some`, in their original order.
+fn parse_lines_par<T, U, F>(lines: &[&[u8]], f: F) -> GFAResult<Vec<U>>
+where
+    T: OptFields + Send + Sync,
+    U: Send,
+    F: Fn(Line<usize, T>) -> Option<U> + Sync,
+{
+    let parser: GFAParser<usize, T> = GFAParser::new();
+    lines
+        .par_iter()
+        .filter_map(|line| parser.parse_gfa_line(line).map(&f).transpose())
+        .collect()
+}
+
+impl PackedGraph {
+    /// Build a graph from the segments, links, and paths in the GFA
+    /// lines, using multiple threads. Other lines are skipped.
+    ///
+    /// The lines are first sorted by type, then each type is parsed
+    /// in parallel, and the graph storage is sized to fit. The nodes
+    /// and edges are then added one at a time on the calling thread,
+    /// as they share the packed node and edge records. Finally, the
+    /// steps of all paths are appended in parallel.
+    ///
+    /// The resulting graph has the same contents as the one produced
+    /// by `conversion::from_gfa`, which is also what `fill_gfa_lines`
+    /// produces if all segments come before all links, and all
+    /// links before all paths. Invalid lines produce the same errors
+    /// as `conversion::try_from_gfa`, but if there are several, it's
+    /// not specified which one is returned.
+    pub fn from_gfa_lines_par<L>(lines: &[L]) -> GFAConversionResult<Self>
+    where
+        L: AsRef<[u8]> + Sync,
+    {
+        Self::from_gfa_lines_par_with_encoding(lines, Default::default())
+    }
+
+    /// Build a graph from the GFA lines, like `from_gfa_lines_par`,
+    /// storing the segment sequences using `encoding`.
+    pub fn from_gfa_lines_par_with_encoding<L>(
+        lines: &[L],
+        encoding: SequenceEncoding,
+    ) -> GFAConversionResult<Self>
+    where
+        L: AsRef<[u8]> + Sync,
+    {
+        let mut segment_lines: Vec<&[u8]> = Vec::new();
+        let mut link_lines: Vec<&[u8]> = Vec::new();
+        let mut path_lines: Vec<&[u8]> = Vec::new();
+
+        for line in lines {
+            let line = line.as_ref();
+            match line.first() {
+                Some(b'S') => segment_lines.push(line),
+                Some(b'L') => link_lines.push(line),
+                Some(b'P') => path_lines.push(line),
+                _ => (),
+            }
+        }
+
+        let segments = parse_lines_par(&segment_lines, |line| match line {
+            Line::<usize, ()>::Segment(segment) => Some(segment),
+            _ => None,
+        })?;
+
+        let links = parse_lines_par(&link_lines, |line| match line {
+            Line::<usize, ()>::Link(link) => {
+                let left = Handle::new(link.from_segment, link.from_orient);
+                let right = Handle::new(link.to_segment, link.to_orient);
+                Some(Edge(left, right))
+            }
+            _ => None,
+        })?;
+
+        // Only the optional fields of paths are needed, to find the
+        // circular paths
+        let paths = parse_lines_par(&path_lines, |line| match line {
+            Line::<usize, OptionalFields>::Path(path) => Some(ParsedPath {
+                circular: is_circular_path(&path),
+                steps: path
+                    .iter()
+                    .map(|(seg, orient)| Handle::new(seg, orient))
+                    .collect(),
+                name: path.path_name,
+            }),
+            _ => None,
+        })?;
+
+        let mut graph = PackedGraph::with_encoding(encoding);
+
+        let total_bases = segments.iter().map(|s| s.sequence.len()).sum();
+        let total_steps = paths.iter().map(|p| p.steps.len()).sum();
+
+        graph.nodes.reserve(segments.len(), total_bases);
+        graph.edges.reserve(links.len());
+        graph.occurrences.reserve(total_steps);
+
+        // Adding nodes and edges updates the shared records, so
+        // this part of the load is sequential
+        for segment in segments {
+            let id = NodeId::from(segment.name);
+            graph.try_create_handle(&segment.sequence, id)?;
+        }
+
+        for edge in links {
+            graph.try_create_edge(edge)?;
+        }
+
+        let missing_node = paths
+            .par_iter()
+            .flat_map(|path| path.steps.par_iter())
+            .find_first(|handle| !graph.nodes.has_node(handle.id()));
+
+        if let Some(handle) = missing_node {
+            return Err(HandleGraphError::MissingNode(handle.id()).into());
+        }
+
+        for path in paths.iter() {
+            graph.create_path_handle(&path.name, path.circular);
+        }
+
+        // The graph is empty before the paths are created, so the
+        // path IDs are the indices into `paths`
+        graph.with_all_paths_mut_par(|path_id, path_ref| {
+            let steps = &paths[path_id.0 as usize].steps;
+            path_ref.path.reserve(steps.len());
+            steps
+                .iter()
+                .map(|&handle| path_ref.append_handle(handle))
+                .collect()
+        });
+
+        Ok(graph)
+    }
+
+    /// Read a GFA file into memory and build a graph from it, using
+    /// `from_gfa_lines_par`.
+    pub fn from_gfa_file_par<P>(path: P) -> GFAConversionResult<Self>
+    where
+        P: AsRef<std::path::Path>,
+    {
+        Self::from_gfa_file_par_with_encoding(path, Default::default())
+    }
+
+    /// Read a GFA file into memory and build a graph from it, using
+    /// `from_gfa_lines_par_with_encoding`.
+    pub fn from_gfa_file_par_with_encoding<P>(
+        path: P,
+        encoding: SequenceEncoding,
+    ) -> GFAConversionResult<Self>
+    where
+        P: AsRef<std::path::Path>,
+    {
+        let bytes = std::fs::read(path)?;
+        let lines = bytes.lines().collect::<Vec<_>>();
+        Self::from_gfa_lines_par_with_encoding(&lines, encoding)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use gfa::gfa::GFA;
+
+    use crate::{
+        conversion::{self, GFAConversionError},
+        handlegraph::*,
+        pathhandlegraph::*,
+    };
+
+    fn gfa_lines() -> Vec<String> {
+        let mut lines = vec!["H\tVN:Z:1.0".to_string()];
+
+        let bases = [b'A', b'C', b'G', b'T'];
+        for id in 1..=600 {
+            let seq = (0..(id % 7 + 1))
+                .map(|i| bases[(id + i) % 4] as char)
+                .collect::<String>();
+            lines.push(format!("S\t{}\t{}", id, seq));
+        }
+
+        for id in 1..600 {
+            lines.push(format!("L\t{}\t+\t{}\t+\t0M", id, id + 1));
+            if id % 3 == 0 && id + 5 <= 600 {
+                lines.push(format!("L\t{}\t+\t{}\t+\t0M", id, id + 5));
+            }
+        }
+
+        for p in 0..12 {
+            let steps = (1..=600)
+                .filter(|id| (id + p) % (p + 2) != 0)
+                .map(|id| {
+                    let orient = if (id * p) % 5 == 0 { '-' } else { '+' };
+                    format!("{}{}", id, orient)
+                })
+                .collect::<Vec<_>>()
+                .join(",");
+            let tag = if p % 4 == 0 { "\tTP:Z:circular" } else { "" };
+            lines.push(format!("P\tpath{}\t{}\t*{}", p, steps, tag));
+        }
+
+        lines
+    }
+
+    #[test]
+    fn parallel_gfa_loader() {
+        let lines = gfa_lines();
+
+        let gfa = GFAParser::new()
+            .parse_lines(lines.iter().map(|l| l.as_bytes()))
+            .unwrap();
+        let sequential: PackedGraph =
+            conversion::from_gfa::<_, OptionalFields>(&gfa);
+
+        let graph = PackedGraph::from_gfa_lines_par(&lines).unwrap();
+
+        assert_eq!(graph.node_count(), sequential.node_count());
+        assert_eq!(graph.edge_count(), sequential.edge_count());
+        assert_eq!(PathHandleGraph::path_count(&graph), 12);
+        assert_eq!(conversion::to_gfa(&graph), conversion::to_gfa(&sequential));
+
+        for handle in sequential.all_handles() {
+            let occurs = |g: &PackedGraph| {
+                g.handle_occurrences(handle).collect::<Vec<_>>()
+            };
+            assert_eq!(occurs(&graph), occurs(&sequential));
+        }
+
+        // Other line types are skipped, and the order of the lines of
+        // each type is preserved
+        let mut shuffled = lines.clone();
+        shuffled.reverse();
+        shuffled.push("C\t1\t+\t2\t+\t0\t1M".to_string());
+        shuffled.push("W\tHG002\t1\tchr1\t0\t2\t>1>2".to_string());
+        shuffled.push(String::new());
+        let graph = PackedGraph::from_gfa_lines_par(&shuffled).unwrap();
+        assert_eq!(graph.node_count(), 600);
+        assert_eq!(PathHandleGraph::path_count(&graph), 12);
+        let first_path = graph.get_path_id(b"path11").unwrap();
+        assert_eq!(first_path, PathId(0));
+
+        let invalid = ["S\t1\tACGT", "S\tx\tGG"];
+        assert!(PackedGraph::from_gfa_lines_par(&invalid).is_err());
+
+        let graph_error =
+            |lines: &[&str]| match PackedGraph::from_gfa_lines_par(lines) {
+                Err(GFAConversionError::Graph(err)) => Some(err),
+                _ => None,
+            };
+
+        assert_eq!(
+            graph_error(&["S\t1\tACGT", "S\t1\tGG"]),
+            Some(HandleGraphError::DuplicateNode(NodeId::from(1u64)))
+        );
+        assert_eq!(
+            graph_error(&["S\t1\tACGT", "L\t1\t+\t2\t+\t0M"]),
+            Some(HandleGraphError::MissingNode(NodeId::from(2u64)))
+        );
+        assert_eq!(
+            graph_error(&["S\t1\tACGT", "P\tp1\t1+,3-\t*"]),
+            Some(HandleGraphError::MissingNode(NodeId::from(3u64)))
+        );
+    }
+
+    #[test]
+    fn gfa_roundtrip_with_encoding() {
+        let lines = [
+            "S\t1\tACGTacgt",
+            "S\t2\tNNRYkm",
+            "S\t3\tggaTTC",
+            "L\t1\t+\t2\t+\t0M",
+            "L\t2\t+\t3\t+\t0M",
+            "P\tp1\t1+,2-,3+\t*",
+        ];
+
+        let gfa: GFA<usize, OptionalFields> = GFAParser::new()
+            .parse_lines(lines.iter().map(|l| l.as_bytes()))
+            .unwrap();
+
+        let encoding = SequenceEncoding::IupacSoftMasked;
+
+        let graph =
+            PackedGraph::from_gfa_lines_par_with_encoding(&lines, encoding)
+                .unwrap();
+        assert_eq!(graph.encoding(), encoding);
+
+        let mut sequential = PackedGraph::with_encoding(encoding);
+        conversion::fill_gfa(&mut sequential, &gfa).unwrap();
+
+        for graph in [graph, sequential].iter() {
+            let out = conversion::to_gfa(graph);
+            assert_eq!(out.segments, gfa.segments);
+            assert_eq!(out.links, gfa.links);
+            assert_eq!(out.paths.len(), 1);
+            assert_eq!(out.paths[0].segment_names, gfa.paths[0].segment_names);
+        }
+
+        // The default encoding can't store the soft-masked bases
+        let graph = PackedGraph::from_gfa_lines_par(&lines).unwrap();
+        let out = conversion::to_gfa(&graph);
+        assert_eq!(out.segments[1].sequence, "NNNNNN");
+        assert_eq!(out.segments[2].sequence, "GGATTC");
+    }
+}
@@ -1,16 +1,19 @@
+use std::io::{self, Read, Write};
+
 use crate::{
     handle::{Direction, Handle, NodeId},
     packed::*,
 };
 
 use crate::packed;
+use crate::packed::serialize::{read_u64, write_u64};
 
 use super::{
     edges::EdgeListIx,
     graph::NARROW_PAGE_WIDTH,
     index::{NodeRecordId, OneBasedIndex, RecordIndex},
     occurrences::OccurListIx,
-    sequence::{SeqRecordIx, Sequences},
+    sequence::{SeqRecordIx, SequenceEncoding, Sequences},
 };
 
 /// The index into the underlying packed vector that is used to
@@ -78,10 +81,26 @@ impl NodeIdIndexMap {
         self.deque.iter()
     }
 
+    pub(super) fn iter_range(
+        &self,
+        start: usize,
+        end: usize,
+    ) -> packed::deque::Iter<'_> {
+        self.deque.iter_range(start, end)
+    }
+
+    /// The number of node IDs in the map's range, including IDs of
+    /// nodes that don't exist.
     pub(super) fn len(&self) -> usize {
         self.deque.len()
     }
 
+    /// Reserve space for `additional` more node IDs, assuming they
+    /// are contiguous with the existing IDs.
+    fn reserve(&mut self, additional: usize) {
+        self.deque.reserve(self.deque.len() + additional);
+    }
+
     fn clear_node_id(&mut self, id: NodeId) {
         let ix = u64::from(id) - self.min_id;
         self.deque.set(ix as usize, 0);
@@ -154,6 +173,25 @@ impl NodeIdIndexMap {
     }
 }
 
+impl Serializable for NodeIdIndexMap {
+    fn serialize<W: Write>(&self, writer: &mut W) -> io::Result<()> {
+        self.deque.serialize(writer)?;
+        write_u64(writer, self.max_id)?;
+        write_u64(writer, self.min_id)
+    }
+
+    fn deserialize<R: Read>(reader: &mut R) -> io::Result<Self> {
+        let deque = PackedDeque::deserialize(reader)?;
+        let max_id = read_u64(reader)?;
+        let min_id = read_u64(reader)?;
+        Ok(Self {
+            deque,
+            max_id,
+            min_id,
+        })
+    }
+}
+
 #[derive(Debug, Clone)]
 pub struct NodeRecords {
     records_vec: PagedIntVec,
@@ -176,10 +214,16 @@ crate::impl_space_usage!(
 
 impl Default for NodeRecords {
     fn default() -> NodeRecords {
+        Self::with_encoding(Default::default())
+    }
+}
+
+impl NodeRecords {
+    pub(super) fn with_encoding(encoding: SequenceEncoding) -> Self {
         Self {
             records_vec: PagedIntVec::new(NARROW_PAGE_WIDTH),
             id_index_map: Default::default(),
-            sequences: Default::default(),
+            sequences: Sequences::with_encoding(encoding),
             removed_nodes: Vec::new(),
             node_occurrence_map: PagedIntVec::new(
                 super::graph::NARROW_PAGE_WIDTH,
@@ -188,6 +232,31 @@ impl Default for NodeRecords {
     }
 }
 
+impl Serializable for NodeRecords {
+    fn serialize<W: Write>(&self, writer: &mut W) -> io::Result<()> {
+        self.records_vec.serialize(writer)?;
+        self.id_index_map.serialize(writer)?;
+        self.sequences.serialize(writer)?;
+        self.removed_nodes.serialize(writer)?;
+        self.node_occurrence_map.serialize(writer)
+    }
+
+    fn deserialize<R: Read>(reader: &mut R) -> io::Result<Self> {
+        let records_vec = PagedIntVec::deserialize(reader)?;
+        let id_index_map = NodeIdIndexMap::deserialize(reader)?;
+        let sequences = Sequences::deserialize(reader)?;
+        let removed_nodes = Vec::deserialize(reader)?;
+        let node_occurrence_map = PagedIntVec::deserialize(reader)?;
+        Ok(Self {
+            records_vec,
+            id_index_map,
+            sequences,
+            removed_nodes,
+            node_occurrence_map,
+        })
+    }
+}
+
 impl NodeRecords {
     #[inline]
     pub fn min_id(&self) -> u64 {
@@ -203,14 +272,33 @@ impl NodeRecords {
         self.id_index_map.iter()
     }
 
+    /// Iterate through the node ID map entries from `start` up to,
+    /// but not including, `end`, where the entry at index `i` belongs
+    /// to the node ID `i + min_id`.
+    pub fn nodes_iter_range(
+        &self,
+        start: usize,
+        end: usize,
+    ) -> packed::deque::Iter<'_> {
+        self.id_index_map.iter_range(start, end)
+    }
+
+    /// The number of entries in the node ID map.
+    pub fn node_id_range_len(&self) -> usize {
+        self.id_index_map.len()
+    }
+
     #[inline]
     pub fn has_node<I: Into<NodeId>>(&self, id: I) -> bool {
         self.id_index_map.has_node(id)
     }
 
+    /// Returns the number of nodes in the graph. Every node has its
+    /// own record, so this is the number of records that haven't
+    /// been removed.
     #[inline]
     pub fn node_count(&self) -> usize {
-        self.id_index_map.len()
+        self.record_count() - self.removed_nodes.len()
     }
 
     #[inline]
@@ -226,6 +314,31 @@ impl NodeRecords {
         rec_id
     }
 
+    /// Returns the number of node records, including removed ones.
+    #[inline]
+    pub(super) fn record_count(&self) -> usize {
+        self.records_vec.len() / GraphVecIx::RECORD_WIDTH
+    }
+
+    /// Iterate through the node IDs in the graph, in order, together
+    /// with the index of each node's record.
+    pub(super) fn node_record_ids(
+        &self,
+    ) -> impl Iterator<Item = (NodeId, NodeRecordId)> + '_ {
+        let min_id = self.id_index_map.min_id;
+        self.id_index_map
+            .iter()
+            .enumerate()
+            .filter_map(move |(ix, rec_id)| {
+                let rec_id = NodeRecordId::unpack(rec_id);
+                if rec_id.is_null() {
+                    None
+                } else {
+                    Some((NodeId::from(min_id + ix as u64), rec_id))
+                }
+            })
+    }
+
     pub(super) fn sequences(&self) -> &Sequences {
         &self.sequences
     }
@@ -234,6 +347,24 @@ impl NodeRecords {
         &mut self.sequences
     }
 
+    /// Reserve space for `additional` more nodes, with a total of
+    /// `additional_bases` bases in their sequences.
+    pub(super) fn reserve(
+        &mut self,
+        additional: usize,
+        additional_bases: usize,
+    ) {
+        let records_len = self.records_vec.len();
+        self.records_vec
+            .reserve(records_len + GraphVecIx::RECORD_WIDTH * additional);
+
+        let occur_len = self.node_occurrence_map.len();
+        self.node_occurrence_map.reserve(occur_len + additional);
+
+        self.id_index_map.reserve(additional);
+        self.sequences.reserve(additional, additional_bases);
+    }
+
     /// Append a new node graph record, using the provided
     /// `NodeRecordId` no ensure that the record index is correctly
     /// synced.
@@ -263,8 +394,13 @@ impl NodeRecords {
             return None;
         }
 
-        // append the sequence and graph records
-        self.sequences.append_empty_record();
+        // append the sequence and graph records; the sequence record
+        // may already exist if it was created when splitting the
+        // sequence of another node
+        let seq_ix = SeqRecordIx::from_one_based_ix(next_ix)?;
+        if seq_ix.at_0() >= self.sequences.len() {
+            self.sequences.append_empty_record();
+        }
         let record_ix = self.append_node_graph_record(next_ix)?;
 
         Some(record_ix)
@@ -282,7 +418,7 @@ impl NodeRecords {
 
         // clear node record/edge list heads
         self.records_vec.set(rec_ix, 0);
-        self.records_vec.set(rec_ix, 1);
+        self.records_vec.set(rec_ix + 1, 0);
 
         // clear sequence record
         self.sequences.clear_record(seq_ix);
@@ -5,8 +5,11 @@ use crate::handle::{Handle, NodeId};
 
 use super::graph::{NARROW_PAGE_WIDTH, WIDE_PAGE_WIDTH};
 
+use std::io::{self, Read, Write};
 use std::num::NonZeroUsize;
 
+use fnv::FnvHashMap;
+
 #[allow(unused_imports)]
 use super::{NodeRecordId, OneBasedIndex, PathStepIx, RecordIndex};
 
@@ -62,7 +65,49 @@ impl Default for NodeOccurrences {
     }
 }
 
+impl Serializable for NodeOccurrences {
+    fn serialize<W: Write>(&self, writer: &mut W) -> io::Result<()> {
+        self.path_ids.serialize(writer)?;
+        self.node_occur_offsets.serialize(writer)?;
+        self.node_occur_next.serialize(writer)?;
+        self.removed_records.serialize(writer)
+    }
+
+    fn deserialize<R: Read>(reader: &mut R) -> io::Result<Self> {
+        let path_ids = PagedIntVec::deserialize(reader)?;
+        let node_occur_offsets = PagedIntVec::deserialize(reader)?;
+        let node_occur_next = PagedIntVec::deserialize(reader)?;
+        let removed_records = Vec::deserialize(reader)?;
+        Ok(Self {
+            path_ids,
+            node_occur_offsets,
+            node_occur_next,
+            removed_records,
+        })
+    }
+}
+
 impl NodeOccurrences {
+    /// Reserve space for `additional` more occurrence records.
+    pub(super) fn reserve(&mut self, additional: usize) {
+        let len = self.path_ids.len() + additional;
+        self.path_ids.reserve(len);
+        self.node_occur_offsets.reserve(len);
+        self.node_occur_next.reserve(len);
+    }
+
+    /// Returns the number of occurrence records, including removed
+    /// ones.
+    #[inline]
+    pub(super) fn record_count(&self) -> usize {
+        self.path_ids.len()
+    }
+
+    #[inline]
+    pub(super) fn removed_records(&self) -> &[OccurListIx] {
+        &self.removed_records
+    }
+
     pub(super) fn append_record(&mut self) -> OccurListIx {
         let node_rec_ix = OccurListIx::from_zero_based(self.path_ids.len());
 
@@ -179,6 +224,21 @@ impl NodeOccurrences {
         }
     }
 
+    /// Replace the path ID of every occurrence record using the
+    /// provided map, as returned by `PackedGraphPaths::compact_paths`.
+    /// IDs that aren't in the map are left unchanged.
+    pub(super) fn update_path_ids(
+        &mut self,
+        id_map: &FnvHashMap<PathId, PathId>,
+    ) {
+        for ix in 0..self.path_ids.len() {
+            let path_id = PathId(self.path_ids.get(ix));
+            if let Some(new_id) = id_map.get(&path_id) {
+                self.path_ids.set_pack(ix, new_id.0);
+            }
+        }
+    }
+
     pub(crate) fn iter(&self, head: OccurListIx) -> list::Iter<'_, Self> {
         list::Iter::new(self, head)
     }
@@ -1,6 +1,8 @@
 #![allow(dead_code)]
 #![allow(unused_imports)]
 
+use std::io::{self, Read, Write};
+
 use rayon::prelude::*;
 
 use crate::{
@@ -12,17 +14,20 @@ use fnv::{FnvHashMap, FnvHashSet};
 
 use super::{NodeRecordId, OneBasedIndex, PackedDoubleList, RecordIndex};
 
-use super::NodeIdIndexMap;
+use super::{NodeIdIndexMap, NodeOccurrences, NodeRecords, PackedGraph};
 
 use crate::pathhandlegraph::*;
 
 use crate::packed;
+use crate::packed::serialize::{invalid_data, read_usize, write_usize};
 use crate::packed::*;
 
 mod packedpath;
+mod positions;
 mod properties;
 
 pub use self::packedpath::*;
+pub use self::positions::*;
 pub use self::properties::*;
 
 pub use self::packedpath::StepUpdate;
@@ -31,7 +36,12 @@ pub use self::packedpath::StepUpdate;
 pub struct PackedPathNames {
     // TODO compress the names; don't store entire Vec<u8>s
     name_id_map: FnvHashMap<Vec<u8>, PathId>,
-    names: PackedIntVec,
+    // The IDs of the paths that haven't been removed, in ascending
+    // order, which is also the order the paths were created in
+    path_ids: Vec<PathId>,
+    // The names are stored contiguously, rather than packed, so that
+    // they can be borrowed as slices
+    names: Vec<u8>,
     lengths: PackedIntVec,
     offsets: PagedIntVec,
 }
@@ -54,7 +64,8 @@ impl succinct::SpaceUsage for PackedPathNames {
 
         map_values_size
             + map_keys_size
-            + self.names.heap_bytes()
+            + self.path_ids.capacity() * PathId::stack_bytes()
+            + self.names.capacity()
             + self.lengths.heap_bytes()
             + self.offsets.heap_bytes()
     }
@@ -64,6 +75,7 @@ impl Default for PackedPathNames {
     fn default() -> Self {
         PackedPathNames {
             name_id_map: Default::default(),
+            path_ids: Default::default(),
             names: Default::default(),
             lengths: Default::default(),
             offsets: PagedIntVec::new(super::graph::NARROW_PAGE_WIDTH),
@@ -71,23 +83,89 @@ impl Default for PackedPathNames {
     }
 }
 
+impl Serializable for PackedPathNames {
+    fn serialize<W: Write>(&self, writer: &mut W) -> io::Result<()> {
+        // Keep the packed layout in the serialized format
+        let names = self
+            .names
+            .iter()
+            .map(|&b| b as u64)
+            .collect::<PackedIntVec>();
+        names.serialize(writer)?;
+        self.lengths.serialize(writer)?;
+        self.offsets.serialize(writer)?;
+
+        // The names themselves are already stored in `names`, so only
+        // the IDs of the paths that haven't been removed need to be
+        // stored
+        self.path_ids.serialize(writer)
+    }
+
+    fn deserialize<R: Read>(reader: &mut R) -> io::Result<Self> {
+        let names = PackedIntVec::deserialize(reader)?
+            .iter()
+            .map(|b| b as u8)
+            .collect::<Vec<_>>();
+        let lengths = PackedIntVec::deserialize(reader)?;
+        let offsets = PagedIntVec::deserialize(reader)?;
+        let path_ids: Vec<PathId> = Vec::deserialize(reader)?;
+
+        let mut result = Self {
+            name_id_map: Default::default(),
+            path_ids: Vec::with_capacity(path_ids.len()),
+            names,
+            lengths,
+            offsets,
+        };
+
+        if result.offsets.len() != result.lengths.len() {
+            return Err(invalid_data("invalid path name records"));
+        }
+
+        for id in path_ids {
+            let ix = id.0 as usize;
+            let ascending = result.path_ids.last().is_none_or(|&p| p < id);
+            if ix >= result.lengths.len() || !ascending {
+                return Err(invalid_data("invalid path name ID"));
+            }
+            let offset: usize = result.offsets.get_unpack(ix);
+            let len: usize = result.lengths.get_unpack(ix);
+            if offset
+                .checked_add(len)
+                .is_none_or(|end| end > result.names.len())
+            {
+                return Err(invalid_data("invalid path name record"));
+            }
+            let name = result.name_iter(id).unwrap().collect::<Vec<_>>();
+            result.name_id_map.insert(name, id);
+            result.path_ids.push(id);
+        }
+
+        Ok(result)
+    }
+}
+
 impl PackedPathNames {
     pub(super) fn add_name(&mut self, name: &[u8]) -> PathId {
         let path_id = PathId(self.lengths.len() as u64);
 
         self.name_id_map.insert(name.into(), path_id);
+        self.path_ids.push(path_id);
 
         let name_len = name.len() as u64;
         let name_offset = self.names.len() as u64;
         self.lengths.append(name_len);
         self.offsets.append(name_offset);
 
-        name.iter().for_each(|&b| self.names.append(b as u64));
+        self.names.extend_from_slice(name);
 
         path_id
     }
 
     pub(super) fn remove_id(&mut self, id: PathId) -> Option<()> {
+        let path_ix = self.path_ids.binary_search(&id).ok()?;
+        self.path_ids.remove(path_ix);
+
         let name = self.name_iter(id)?.collect::<Vec<_>>();
         let _id = self.name_id_map.remove(&name);
         let ix = id.0 as usize;
@@ -98,20 +176,44 @@ impl PackedPathNames {
         Some(())
     }
 
+    /// Returns `true` if `id` is the ID of a path that hasn't been
+    /// removed.
+    pub(super) fn contains_id(&self, id: PathId) -> bool {
+        self.path_ids.binary_search(&id).is_ok()
+    }
+
+    /// The IDs of the paths that haven't been removed, in the order
+    /// the paths were created.
+    pub(super) fn path_ids(&self) -> std::slice::Iter<'_, PathId> {
+        self.path_ids.iter()
+    }
+
+    /// Get the name of the path with the given ID as a slice, or
+    /// `None` if the path doesn't exist or has been removed.
+    pub(super) fn name_slice(&self, id: PathId) -> Option<&[u8]> {
+        if !self.contains_id(id) {
+            return None;
+        }
+        self.name_record(id)
+    }
+
     pub(super) fn name_iter(
         &self,
         id: PathId,
-    ) -> Option<packed::vector::IterView<'_, u8>> {
+    ) -> Option<std::iter::Copied<std::slice::Iter<'_, u8>>> {
+        self.name_record(id).map(|name| name.iter().copied())
+    }
+
+    fn name_record(&self, id: PathId) -> Option<&[u8]> {
         let vec_ix = id.0 as usize;
         if vec_ix >= self.lengths.len() {
             return None;
         }
 
-        let offset = self.offsets.get_unpack(vec_ix);
-        let len = self.lengths.get_unpack(vec_ix);
-        let iter = self.names.iter_slice(offset, len).view();
+        let offset: usize = self.offsets.get_unpack(vec_ix);
+        let len: usize = self.lengths.get_unpack(vec_ix);
 
-        Some(iter)
+        self.names.get(offset..offset + len)
     }
 }
 
@@ -120,9 +222,13 @@ pub struct PackedGraphPaths {
     paths: Vec<PackedPath>,
     pub(super) path_props: PathProperties,
     pub(super) path_names: PackedPathNames,
+    positions: PathPositions,
 }
 
-crate::impl_space_usage!(PackedGraphPaths, [paths, path_props, path_names]);
+crate::impl_space_usage!(
+    PackedGraphPaths,
+    [paths, path_props, path_names, positions]
+);
 
 impl Default for PackedGraphPaths {
     fn default() -> Self {
@@ -130,10 +236,48 @@ impl Default for PackedGraphPaths {
             paths: Vec::new(),
             path_props: Default::default(),
             path_names: Default::default(),
+            positions: Default::default(),
         }
     }
 }
 
+impl Serializable for PackedGraphPaths {
+    fn serialize<W: Write>(&self, writer: &mut W) -> io::Result<()> {
+        write_usize(writer, self.paths.len())?;
+        for path in self.paths.iter() {
+            path.serialize(writer)?;
+        }
+        self.path_props.serialize(writer)?;
+        self.path_names.serialize(writer)
+    }
+
+    fn deserialize<R: Read>(reader: &mut R) -> io::Result<Self> {
+        let num_paths = read_usize(reader)?;
+        let paths = (0..num_paths)
+            .map(|_| PackedPath::deserialize(reader))
+            .collect::<io::Result<Vec<_>>>()?;
+        let path_props = PathProperties::deserialize(reader)?;
+        let path_names = PackedPathNames::deserialize(reader)?;
+
+        if path_props.len() != num_paths
+            || path_names.lengths.len() != num_paths
+        {
+            return Err(invalid_data("inconsistent number of paths"));
+        }
+
+        // The position indices aren't serialized, and are rebuilt
+        // when needed
+        let positions = PathPositions::with_paths(num_paths);
+
+        Ok(Self {
+            paths,
+            path_props,
+            path_names,
+            positions,
+        })
+    }
+}
+
 pub struct PathMutContext<'a> {
     path_ref_mut: PackedPathRefMut<'a>,
     path_properties: &'a mut PathProperties,
@@ -206,6 +350,73 @@ impl<'a> MultiPathMutContext<'a> {
     }
 }
 
+/// A mutable reference to a single path in a `PackedGraph`, that
+/// also updates the node occurrences as steps are added and removed,
+/// so the returned `StepUpdate`s don't need to be applied.
+pub struct PackedGraphPathMut<'a> {
+    path: MultiPathMutContext<'a>,
+    nodes: &'a mut NodeRecords,
+    occurrences: &'a mut NodeOccurrences,
+}
+
+impl<'a> PackedGraphPathMut<'a> {
+    pub(super) fn new(
+        path: MultiPathMutContext<'a>,
+        nodes: &'a mut NodeRecords,
+        occurrences: &'a mut NodeOccurrences,
+    ) -> Self {
+        Self {
+            path,
+            nodes,
+            occurrences,
+        }
+    }
+
+    fn path_ref_mut(&mut self) -> &mut PackedPathRefMut<'a> {
+        self.path.paths.first_mut().unwrap()
+    }
+
+    fn apply_update(&mut self, step_update: &StepUpdate) {
+        let path_id = self.path.paths[0].path_id;
+        PackedGraph::apply_occurrence_update(
+            self.nodes,
+            self.occurrences,
+            path_id,
+            step_update.clone(),
+        );
+    }
+}
+
+impl<'a> PathBase for PackedGraphPathMut<'a> {
+    type Step = (PathStepIx, PackedStep);
+
+    type StepIx = PathStepIx;
+}
+
+impl<'a> PathRefMut for PackedGraphPathMut<'a> {
+    fn append_step(&mut self, handle: Handle) -> StepUpdate {
+        let step_update = self.path_ref_mut().append_step(handle);
+        self.apply_update(&step_update);
+        step_update
+    }
+
+    fn prepend_step(&mut self, handle: Handle) -> StepUpdate {
+        let step_update = self.path_ref_mut().prepend_step(handle);
+        self.apply_update(&step_update);
+        step_update
+    }
+
+    fn remove_step(&mut self, step: Self::StepIx) -> Option<StepUpdate> {
+        let step_update = self.path_ref_mut().remove_step(step)?;
+        self.apply_update(&step_update);
+        Some(step_update)
+    }
+
+    fn set_circularity(&mut self, circular: bool) {
+        self.path_ref_mut().set_circularity(circular);
+    }
+}
+
 impl<'a> Drop for MultiPathMutContext<'a> {
     fn drop(&mut self) {
         for path in self.paths.iter() {
@@ -225,21 +436,32 @@ impl<'a> Drop for MultiPathMutContext<'a> {
 }
 
 impl PackedGraphPaths {
-    pub(super) fn create_path(&mut self, name: &[u8]) -> PathId {
-        let path_id = self.paths.len() as u64;
+    pub(super) fn create_path(
+        &mut self,
+        name: &[u8],
+        circular: bool,
+    ) -> PathId {
+        let path_id = PathId(self.paths.len() as u64);
         let packed_path = PackedPath::new();
         self.paths.push(packed_path);
+        self.positions.append_path();
 
         self.path_props.append_record();
         self.path_names.add_name(name);
 
-        PathId(path_id)
+        self.path_properties_mut(path_id).set_circular(circular);
+
+        path_id
     }
 
     pub(super) fn remove_path(
         &mut self,
         id: PathId,
     ) -> Option<Vec<StepUpdate>> {
+        if !self.path_names.contains_id(id) {
+            return None;
+        }
+
         let ix = id.0;
 
         let mut steps = {
@@ -261,16 +483,99 @@ impl PackedGraphPaths {
 
         self.path_props.clear_record(id);
 
-        // can't remove the path quite yet, as all the elements to the
-        // right will be shifted back -- need to fix that somehow
-        // self.paths.remove(ix);
+        // The path can't be removed from the vector as all the
+        // elements to the right would be shifted back, but its step
+        // storage can be released; `compact_paths` reclaims the slot
+        self.paths[ix as usize] = PackedPath::new();
+
         Some(step_updates)
     }
 
+    /// Remove the slots of all removed paths, renumbering the
+    /// remaining paths so that the path IDs are tightly packed while
+    /// preserving their order. Returns a map from the old to the new
+    /// ID of each remaining path, which must be used to update any
+    /// stored path IDs, e.g. in the node occurrences.
+    ///
+    /// Returns None if there are no removed paths.
+    pub(super) fn compact_paths(
+        &mut self,
+    ) -> Option<FnvHashMap<PathId, PathId>> {
+        let kept = self.path_names.path_ids().copied().collect::<Vec<_>>();
+
+        if kept.len() == self.paths.len() {
+            return None;
+        }
+
+        let mut paths = Vec::with_capacity(kept.len());
+        let mut path_props = PathProperties::default();
+        let mut path_names = PackedPathNames::default();
+
+        let mut id_map = FnvHashMap::default();
+
+        for old_id in kept {
+            let name = self.path_names.name_iter(old_id)?.collect::<Vec<_>>();
+            let new_id = path_names.add_name(&name);
+
+            let path = std::mem::replace(
+                &mut self.paths[old_id.0 as usize],
+                PackedPath::new(),
+            );
+            paths.push(path);
+
+            path_props.append_record();
+            path_props.set_record(new_id, &self.path_props.get_record(old_id));
+
+            id_map.insert(old_id, new_id);
+        }
+
+        self.positions = PathPositions::with_paths(paths.len());
+        self.paths = paths;
+        self.path_props = path_props;
+        self.path_names = path_names;
+
+        Some(id_map)
+    }
+
     pub fn len(&self) -> usize {
         self.paths.len()
     }
 
+    /// Rebuild the step storage of every path so that it doesn't
+    /// contain any removed steps, renumbering the steps of each path
+    /// in order from the head. Returns the step updates that must be
+    /// applied to a fresh set of node occurrences.
+    #[must_use]
+    pub(super) fn defragment(&mut self) -> Vec<(PathId, Vec<StepUpdate>)> {
+        (0..self.paths.len())
+            .filter_map(|ix| {
+                let id = PathId(ix as u64);
+
+                let handles = self
+                    .path_ref(id)?
+                    .steps()
+                    .map(|(_, step)| step.handle)
+                    .collect::<Vec<_>>();
+
+                self.paths[ix] = PackedPath::new();
+
+                let mut props = self.path_properties_mut(id);
+                props.set_head(PathStepIx::null());
+                props.set_tail(PathStepIx::null());
+                props.set_deleted_steps(0);
+
+                let updates = self.with_path_mut_ctx(id, |path_ref| {
+                    handles
+                        .into_iter()
+                        .map(|handle| path_ref.append_handle(handle))
+                        .collect()
+                })?;
+
+                Some((id, updates))
+            })
+            .collect()
+    }
+
     pub(super) fn path_ref<'a>(
         &'a self,
         id: PathId,
@@ -281,10 +586,32 @@ impl PackedGraphPaths {
         Some(PackedPathRef::new(path_id, path, properties))
     }
 
+    /// Get the position index of the path, building it first if it
+    /// doesn't exist. `node_len` must return the length of the node
+    /// of each step.
+    pub(super) fn position_index<F>(
+        &self,
+        id: PathId,
+        node_len: F,
+    ) -> Option<&PathPositionIndex>
+    where
+        F: Fn(Handle) -> usize,
+    {
+        let path = self.path_ref(id)?;
+        self.positions
+            .get_or_build(id, || PathPositionIndex::new(path, node_len))
+    }
+
+    /// Drop the position indices of all paths.
+    pub(super) fn clear_position_indices(&mut self) {
+        self.positions.invalidate_all();
+    }
+
     pub(super) fn path_properties_mut<'a>(
         &'a mut self,
         id: PathId,
     ) -> PathPropertyMut<'a> {
+        self.positions.invalidate(id);
         self.path_props.record_mut(id)
     }
 
@@ -310,6 +637,7 @@ impl PackedGraphPaths {
         id: PathId,
     ) -> Option<MultiPathMutContext<'a>> {
         let path = self.paths.get_mut(id.0 as usize)?;
+        self.positions.invalidate(id);
         let properties = self.path_props.get_record(id);
         let path_properties = &mut self.path_props;
 
@@ -323,6 +651,7 @@ impl PackedGraphPaths {
     pub(super) fn get_multipath_mut_ctx<'a>(
         &'a mut self,
     ) -> MultiPathMutContext<'a> {
+        self.positions.invalidate_all();
         let path_properties = &mut self.path_props;
 
         let paths = self
@@ -363,10 +692,17 @@ impl PackedGraphPaths {
     where
         F: Fn(PathId, &mut PackedPathRefMut<'a>) -> Vec<StepUpdate> + Sync,
     {
+        // Removed paths keep their slots in `paths`, so they must be
+        // skipped explicitly
+        let removed = (0..self.paths.len())
+            .map(|ix| !self.path_names.contains_id(PathId(ix as u64)))
+            .collect::<Vec<_>>();
+
         let mut mut_ctx = self.get_multipath_mut_ctx();
         let refs_mut = mut_ctx.ref_muts_par();
 
         let results = refs_mut
+            .filter(|path| !removed[path.path_id.0 as usize])
             .map(|path| {
                 let path_id = path.path_id;
                 let steps = f(path_id, path);
@@ -423,17 +759,15 @@ impl PackedGraphPaths {
     }
 }
 impl<'a> AllPathIds for &'a PackedPathNames {
-    type PathIds = std::iter::Copied<
-        std::collections::hash_map::Values<'a, Vec<u8>, PathId>,
-    >;
+    type PathIds = std::iter::Copied<std::slice::Iter<'a, PathId>>;
 
     fn all_path_ids(self) -> Self::PathIds {
-        self.name_id_map.values().copied()
+        self.path_ids.iter().copied()
     }
 }
 
 impl<'a> PathNames for &'a PackedPathNames {
-    type PathName = packed::vector::IterView<'a, u8>;
+    type PathName = std::iter::Copied<std::slice::Iter<'a, u8>>;
 
     fn get_path_name(self, id: PathId) -> Option<Self::PathName> {
         self.name_iter(id)
@@ -579,7 +913,7 @@ mod tests {
 
         let mut paths = PackedGraphPaths::default();
 
-        let path_1 = paths.create_path(b"path1");
+        let path_1 = paths.create_path(b"path1", false);
 
         let pre_record = paths.path_props.get_record(path_1);
 
@@ -632,13 +966,56 @@ mod tests {
         assert_eq!(steps_rev, expected_steps);
     }
 
+    #[test]
+    fn packedpathnames_name_slices() {
+        let mut names = PackedPathNames::default();
+
+        let path_1 = names.add_name(b"path1");
+        let empty = names.add_name(b"");
+        let path_3 = names.add_name(b"another path");
+
+        assert_eq!(names.name_slice(path_1), Some(&b"path1"[..]));
+        assert_eq!(names.name_slice(empty), Some(&b""[..]));
+        assert_eq!(names.name_slice(path_3), Some(&b"another path"[..]));
+        assert_eq!(names.name_slice(PathId(3)), None);
+
+        names.remove_id(path_1);
+        assert_eq!(names.name_slice(path_1), None);
+        assert_eq!(names.name_slice(path_3), Some(&b"another path"[..]));
+
+        let mut bytes = Vec::new();
+        names.serialize(&mut bytes).unwrap();
+        let names =
+            PackedPathNames::deserialize(&mut bytes.as_slice()).unwrap();
+
+        assert_eq!(names.name_slice(path_1), None);
+        assert_eq!(names.name_slice(empty), Some(&b""[..]));
+        assert_eq!(names.name_slice(path_3), Some(&b"another path"[..]));
+        assert_eq!(names.get_path_id(b"another path"), Some(path_3));
+
+        // IDs are listed in the order the paths were created
+        let mut names = names;
+        let path_4 = names.add_name(b"a");
+        let ids = names.all_path_ids().collect::<Vec<_>>();
+        assert_eq!(ids, vec![empty, path_3, path_4]);
+
+        // Removing the path with the empty name removes it
+        names.remove_id(empty);
+        assert!(!names.contains_id(empty));
+        assert_eq!(names.name_slice(empty), None);
+        assert_eq!(
+            names.all_path_ids().collect::<Vec<_>>(),
+            vec![path_3, path_4]
+        );
+    }
+
     #[test]
     fn packedgraphpaths_path_with_mut_ctx() {
         let hnd = |x: u64| Handle::pack(x, false);
 
         let mut paths = PackedGraphPaths::default();
 
-        let path_1 = paths.create_path(b"path1");
+        let path_1 = paths.create_path(b"path1", false);
 
         let _steps = paths.with_path_mut_ctx(path_1, |ref_mut| {
             vec![1, 2, 3, 4, 3, 5]
@@ -670,9 +1047,9 @@ mod tests {
 
         let mut paths = PackedGraphPaths::default();
 
-        let path_1 = paths.create_path(b"path1");
-        let path_2 = paths.create_path(b"path2");
-        let path_3 = paths.create_path(b"path3");
+        let path_1 = paths.create_path(b"path1", false);
+        let path_2 = paths.create_path(b"path2", false);
+        let path_3 = paths.create_path(b"path3", false);
 
         let nodes_1 = vec_hnd(vec![1, 2, 3, 4, 5]);
         let nodes_2 = vec_hnd(vec![6, 2, 3, 7, 5]);
@@ -781,9 +1158,9 @@ mod tests {
 
         let mut paths = PackedGraphPaths::default();
 
-        let path_1 = paths.create_path(b"path1");
-        let path_2 = paths.create_path(b"path2");
-        let path_3 = paths.create_path(b"path3");
+        let path_1 = paths.create_path(b"path1", false);
+        let path_2 = paths.create_path(b"path2", false);
+        let path_3 = paths.create_path(b"path3", false);
 
         let vec_hnd = |v: Vec<u64>| v.into_iter().map(hnd).collect::<Vec<_>>();
 
@@ -2,6 +2,7 @@
 
 use crate::handle::Handle;
 
+use std::io::{self, Read, Write};
 use std::num::NonZeroUsize;
 
 use super::super::graph::NARROW_PAGE_WIDTH;
@@ -15,21 +16,16 @@ use list::{PackedDoubleList, PackedList, PackedListMut};
 
 use crate::pathhandlegraph::{PathBase, PathId, PathRef, PathRefMut, PathStep};
 
+pub use crate::pathhandlegraph::step::PathStepIx;
+
 use super::properties::*;
 
 use crate::packed::*;
 
-#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
-pub struct PathStepIx(Option<NonZeroUsize>);
-
-crate::impl_space_usage_stack_newtype!(PathStepIx);
-
 #[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
 #[repr(transparent)]
 pub struct PathLinkRecordIx(usize);
 
-crate::impl_one_based_index!(PathStepIx);
-
 impl RecordIndex for PathLinkRecordIx {
     const RECORD_WIDTH: usize = 2;
 
@@ -68,6 +64,25 @@ pub struct PackedPath {
 
 crate::impl_space_usage!(PackedPath, [steps, links, removed_steps]);
 
+impl Serializable for PackedPath {
+    fn serialize<W: Write>(&self, writer: &mut W) -> io::Result<()> {
+        self.steps.serialize(writer)?;
+        self.links.serialize(writer)?;
+        self.removed_steps.serialize(writer)
+    }
+
+    fn deserialize<R: Read>(reader: &mut R) -> io::Result<Self> {
+        let steps = RobustPagedIntVec::deserialize(reader)?;
+        let links = RobustPagedIntVec::deserialize(reader)?;
+        let removed_steps = Vec::deserialize(reader)?;
+        Ok(Self {
+            steps,
+            links,
+            removed_steps,
+        })
+    }
+}
+
 impl PackedPath {
     pub(super) fn new() -> Self {
         Self {
@@ -81,6 +96,12 @@ impl PackedPath {
         self.steps.len()
     }
 
+    /// Reserve space for `additional` more steps.
+    pub(crate) fn reserve(&mut self, additional: usize) {
+        self.steps.reserve(self.steps.len() + additional);
+        self.links.reserve(self.links.len() + 2 * additional);
+    }
+
     pub(super) fn append_handle(&mut self, handle: Handle) -> PathStepIx {
         let new_ix = PathStepIx::from_zero_based(self.len());
         self.steps.append(handle.pack());
@@ -130,6 +151,38 @@ impl PackedPath {
         Some(link)
     }
 
+    /// Get the index of the step after the given step in a path with
+    /// the given properties. The last step of a circular path is
+    /// followed by its head, otherwise a null index is returned.
+    fn next_step_wrapping(
+        &self,
+        properties: &PathPropertyRecord,
+        ix: PathStepIx,
+    ) -> Option<PathStepIx> {
+        let next = self.next_step(ix)?;
+        if next.is_null() && properties.circular {
+            Some(properties.head)
+        } else {
+            Some(next)
+        }
+    }
+
+    /// Get the index of the step before the given step in a path with
+    /// the given properties. The first step of a circular path is
+    /// preceded by its tail, otherwise a null index is returned.
+    fn prev_step_wrapping(
+        &self,
+        properties: &PathPropertyRecord,
+        ix: PathStepIx,
+    ) -> Option<PathStepIx> {
+        let prev = self.prev_step(ix)?;
+        if prev.is_null() && properties.circular {
+            Some(properties.tail)
+        } else {
+            Some(prev)
+        }
+    }
+
     pub(super) fn insert_after(
         &mut self,
         ix: PathStepIx,
@@ -182,6 +235,15 @@ impl PackedPath {
         Some(new_ix)
     }
 
+    /// Flip the orientation of the handle at the given step.
+    pub(super) fn flip_step(&mut self, ix: PathStepIx) -> Option<Handle> {
+        let step_ix = ix.to_record_start(1)?;
+        let handle: Handle = self.steps.get_unpack(step_ix);
+        let flipped = handle.flip();
+        self.steps.set_pack(step_ix, flipped);
+        Some(flipped)
+    }
+
     pub fn iter(
         &self,
         head: PathStepIx,
@@ -311,6 +373,44 @@ impl<'a> PackedPathRef<'a> {
     pub(super) fn properties<'b>(&'b self) -> &'b PathPropertyRecord {
         &self.properties
     }
+
+    #[inline]
+    pub(crate) fn head(&self) -> PathStepIx {
+        self.properties.head
+    }
+
+    #[inline]
+    pub(crate) fn tail(&self) -> PathStepIx {
+        self.properties.tail
+    }
+
+    /// Get the handle at the given step, or `None` if the index is
+    /// out of bounds or the step has been removed.
+    pub(crate) fn handle_at(&self, ix: PathStepIx) -> Option<Handle> {
+        let handle = self.path.step_record(ix)?;
+        // Removed steps are zeroed out, and there is no node with ID 0
+        if u64::from(handle.id()) == 0 {
+            None
+        } else {
+            Some(handle)
+        }
+    }
+
+    /// Get the index of the step after the given step; a null index
+    /// means the step is the last step on a linear path. On circular
+    /// paths the last step is followed by the first.
+    #[inline]
+    pub(crate) fn next_step_ix(&self, ix: PathStepIx) -> Option<PathStepIx> {
+        self.path.next_step_wrapping(&self.properties, ix)
+    }
+
+    /// Get the index of the step before the given step; a null index
+    /// means the step is the first step on a linear path. On circular
+    /// paths the first step is preceded by the last.
+    #[inline]
+    pub(crate) fn prev_step_ix(&self, ix: PathStepIx) -> Option<PathStepIx> {
+        self.path.prev_step_wrapping(&self.properties, ix)
+    }
 }
 
 /// A representation of a step that's added to a path, that must be
@@ -356,7 +456,7 @@ impl<'a> PathRef for PackedPathRef<'a> {
     }
 
     fn len(self) -> usize {
-        self.path.steps.len()
+        self.path.len() - self.path.removed_steps.len()
     }
 
     fn circular(self) -> bool {
@@ -364,25 +464,39 @@ impl<'a> PathRef for PackedPathRef<'a> {
     }
 
     fn first_step(self) -> Self::Step {
+        assert!(
+            !self.properties.head.is_null(),
+            "first_step called on an empty path"
+        );
         let head = self.properties.head;
         let step = self.path.get_step(head);
         (head, step)
     }
 
     fn last_step(self) -> Self::Step {
+        assert!(
+            !self.properties.tail.is_null(),
+            "last_step called on an empty path"
+        );
         let tail = self.properties.tail;
         let step = self.path.get_step(tail);
         (tail, step)
     }
 
     fn next_step(self, step: Self::Step) -> Option<Self::Step> {
-        let next = self.path.next_step(step.0)?;
+        let next = self
+            .path
+            .next_step_wrapping(&self.properties, step.0)
+            .filter(|ix| !ix.is_null())?;
         let next_step = self.path.get_step(next);
         Some((next, next_step))
     }
 
     fn prev_step(self, step: Self::Step) -> Option<Self::Step> {
-        let prev = self.path.prev_step(step.0)?;
+        let prev = self
+            .path
+            .prev_step_wrapping(&self.properties, step.0)
+            .filter(|ix| !ix.is_null())?;
         let prev_step = self.path.get_step(prev);
         Some((prev, prev_step))
     }
@@ -401,7 +515,9 @@ impl<'a> PackedPathRefMut<'a> {
         }
     }
 
-    pub(crate) fn as_path_ref<'b>(&'b self) -> PackedPathRef<'b> {
+    /// Get an immutable reference to the path, e.g. to iterate over
+    /// its steps while it's being modified.
+    pub fn as_path_ref<'b>(&'b self) -> PackedPathRef<'b> {
         let path_id = self.path_id;
         let path = &self.path;
         let properties = self.properties;
@@ -500,7 +616,7 @@ impl<'a> PackedPathRefMut<'a> {
             self.properties.tail = step;
         }
 
-        if let Some(head_prev_ix) = head.to_record_ix(2, 01) {
+        if let Some(head_prev_ix) = head.to_record_ix(2, 0) {
             // add back link from old head to new step
             self.path.links.set_pack(head_prev_ix, step);
         }
@@ -512,6 +628,61 @@ impl<'a> PackedPathRefMut<'a> {
         update
     }
 
+    /// Flip the orientation of the step at `ix`. The occurrence
+    /// records only refer to the node, so they don't need to be
+    /// updated.
+    pub(crate) fn flip_step(&mut self, ix: PathStepIx) -> Option<Handle> {
+        self.path.flip_step(ix)
+    }
+
+    /// Replace the step at `ix` by a step on each of the handles in
+    /// `fragments`, the forward-oriented pieces of the step's node
+    /// after it has been divided. The existing step becomes the step
+    /// on the first fragment, which must be the node's original
+    /// handle, and if the step traverses the node in reverse, the
+    /// fragments are traversed in reverse order.
+    #[must_use]
+    pub(crate) fn divide_step(
+        &mut self,
+        ix: PathStepIx,
+        fragments: &[Handle],
+    ) -> Option<Vec<StepUpdate>> {
+        let step_handle = self.path.step_record(ix)?;
+
+        let mut updates = Vec::with_capacity(fragments.len());
+
+        if step_handle.is_reverse() {
+            // Inserting each step right before the existing step
+            // places them in the order they're inserted
+            let mut first = ix;
+            for &handle in fragments.iter().skip(1).rev() {
+                let handle = handle.flip();
+                let step = self.path.insert_before(ix, handle)?;
+                if first == ix {
+                    first = step;
+                }
+                updates.push(StepUpdate::Insert { handle, step });
+            }
+
+            if self.properties.head == ix {
+                self.properties.head = first;
+            }
+        } else {
+            let mut last = ix;
+            for &handle in fragments.iter().skip(1) {
+                let step = self.path.insert_after(last, handle)?;
+                last = step;
+                updates.push(StepUpdate::Insert { handle, step });
+            }
+
+            if self.properties.tail == ix {
+                self.properties.tail = last;
+            }
+        }
+
+        Some(updates)
+    }
+
     fn remove_step_at_index(
         &mut self,
         rem_step_ix: PathStepIx,
@@ -538,6 +709,66 @@ impl<'a> PackedPathRefMut<'a> {
             step: rem_step_ix,
         })
     }
+
+    /// Replace the steps from `from` to `to`, inclusive, with steps
+    /// on the handles in `new_segment`. Returns the step updates
+    /// along with the first step of the new segment and the step
+    /// after the segment, either of which may be null if the new
+    /// segment is empty or ends the path.
+    ///
+    /// Returns `None` if `to` cannot be reached from `from`.
+    #[must_use]
+    pub(crate) fn rewrite_segment(
+        &mut self,
+        from: PathStepIx,
+        to: PathStepIx,
+        new_segment: &[Handle],
+    ) -> Option<(Vec<StepUpdate>, PathStepIx, PathStepIx)> {
+        let mut to_remove = Vec::new();
+        let mut cur = from;
+        loop {
+            if cur.is_null() {
+                return None;
+            }
+            to_remove.push(cur);
+            if cur == to {
+                break;
+            }
+            cur = self.path.next_step(cur)?;
+        }
+
+        let after = self.path.next_step(to)?;
+
+        let mut updates =
+            Vec::with_capacity(to_remove.len() + new_segment.len());
+
+        // Insert the new steps in order before the start of the old
+        // segment, so that they end up between it and its predecessor
+        let mut first_new = PathStepIx::null();
+        for &handle in new_segment {
+            let step = self.path.insert_before(from, handle)?;
+            if first_new.is_null() {
+                first_new = step;
+            }
+            updates.push(StepUpdate::Insert { handle, step });
+        }
+
+        if self.properties.head == from && !first_new.is_null() {
+            self.properties.head = first_new;
+        }
+
+        for step in to_remove {
+            updates.push(self.remove_step_at_index(step)?);
+        }
+
+        let first = if first_new.is_null() {
+            after
+        } else {
+            first_new
+        };
+
+        Some((updates, first, after))
+    }
 }
 
 impl<'a> PathRef for PackedPathRefMut<'a> {
@@ -550,7 +781,7 @@ impl<'a> PathRef for PackedPathRefMut<'a> {
     }
 
     fn len(self) -> usize {
-        self.path.steps.len()
+        self.path.len() - self.path.removed_steps.len()
     }
 
     fn circular(self) -> bool {
@@ -558,25 +789,39 @@ impl<'a> PathRef for PackedPathRefMut<'a> {
     }
 
     fn first_step(self) -> Self::Step {
+        assert!(
+            !self.properties.head.is_null(),
+            "first_step called on an empty path"
+        );
         let head = self.properties.head;
         let step = self.path.get_step(head);
         (head, step)
     }
 
     fn last_step(self) -> Self::Step {
+        assert!(
+            !self.properties.tail.is_null(),
+            "last_step called on an empty path"
+        );
         let tail = self.properties.tail;
         let step = self.path.get_step(tail);
         (tail, step)
     }
 
     fn next_step(self, step: Self::Step) -> Option<Self::Step> {
-        let next = self.path.next_step(step.0)?;
+        let next = self
+            .path
+            .next_step_wrapping(&self.properties, step.0)
+            .filter(|ix| !ix.is_null())?;
         let next_step = self.path.get_step(next);
         Some((next, next_step))
     }
 
     fn prev_step(self, step: Self::Step) -> Option<Self::Step> {
-        let prev = self.path.prev_step(step.0)?;
+        let prev = self
+            .path
+            .prev_step_wrapping(&self.properties, step.0)
+            .filter(|ix| !ix.is_null())?;
         let prev_step = self.path.get_step(prev);
         Some((prev, prev_step))
     }
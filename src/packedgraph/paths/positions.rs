@@ -0,0 +1,176 @@
+use std::sync::OnceLock;
+
+use crate::handle::Handle;
+
+use crate::pathhandlegraph::{PathId, PathRef};
+
+use super::{OneBasedIndex, PackedPathRef, PathStepIx};
+
+use crate::packed::*;
+
+/// An index over the base positions of the steps on a path, used to
+/// find the step at a given position, and the position of a given
+/// step, in logarithmic and constant time, respectively.
+///
+/// The index is a snapshot of the path and the lengths of the nodes
+/// it visits, and must be rebuilt if either changes.
+#[derive(Debug, Clone)]
+pub struct PathPositionIndex {
+    // The step indices, in path order
+    steps: PackedIntVec,
+    // The base offset of each step, in path order
+    offsets: PackedIntVec,
+    // The rank of each step in path order plus one, indexed by the
+    // step index, with 0 for steps that aren't on the path
+    ranks: PackedIntVec,
+    length: usize,
+}
+
+crate::impl_space_usage!(PathPositionIndex, [steps, offsets, ranks]);
+
+impl PathPositionIndex {
+    pub(super) fn new<F>(path: PackedPathRef<'_>, node_len: F) -> Self
+    where
+        F: Fn(Handle) -> usize,
+    {
+        let mut steps = PackedIntVec::new();
+        let mut offsets = PackedIntVec::new();
+        let mut ranks = vec![0u64; path.path.len()];
+
+        let mut length = 0;
+
+        for (rank, (ix, step)) in path.steps().enumerate() {
+            steps.append(ix.pack());
+            offsets.append(length as u64);
+            if let Some(ix) = ix.to_zero_based() {
+                ranks[ix] = rank as u64 + 1;
+            }
+            length += node_len(step.handle);
+        }
+
+        Self {
+            steps,
+            offsets,
+            ranks: ranks.into_iter().collect(),
+            length,
+        }
+    }
+
+    /// The total length of the path in bases.
+    #[inline]
+    pub fn len(&self) -> usize {
+        self.length
+    }
+
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.length == 0
+    }
+
+    /// The number of steps on the path.
+    #[inline]
+    pub fn step_count(&self) -> usize {
+        self.steps.len()
+    }
+
+    /// Get the offset of the first base of the step from the start of
+    /// the path, or `None` if the step isn't on the path.
+    pub fn position_of_step(&self, step: PathStepIx) -> Option<usize> {
+        let ix = step.to_zero_based()?;
+        if ix >= self.ranks.len() {
+            return None;
+        }
+        let rank = self.ranks.get(ix).checked_sub(1)?;
+        Some(self.offsets.get_unpack(rank as usize))
+    }
+
+    /// Get the step that covers the base at `pos`, or `None` if the
+    /// position is past the end of the path.
+    pub fn step_at_position(&self, pos: usize) -> Option<PathStepIx> {
+        if pos >= self.length {
+            return None;
+        }
+
+        // Find the last step that starts at or before `pos`, which
+        // can't be an empty node, as the next step would start at
+        // the same offset
+        let mut lo = 0;
+        let mut hi = self.offsets.len();
+        while hi - lo > 1 {
+            let mid = lo + (hi - lo) / 2;
+            if self.offsets.get_unpack::<usize>(mid) <= pos {
+                lo = mid;
+            } else {
+                hi = mid;
+            }
+        }
+
+        Some(self.steps.get_unpack(lo))
+    }
+}
+
+/// The position indices of all paths in a graph. Each index is built
+/// the first time it's needed, and dropped when the path is
+/// modified.
+#[derive(Debug, Clone, Default)]
+pub struct PathPositions {
+    indices: Vec<OnceLock<PathPositionIndex>>,
+}
+
+impl succinct::SpaceUsage for PathPositions {
+    #[inline]
+    fn is_stack_only() -> bool {
+        false
+    }
+
+    #[inline]
+    fn heap_bytes(&self) -> usize {
+        let built: usize = self
+            .indices
+            .iter()
+            .filter_map(|index| index.get())
+            .map(|index| index.heap_bytes())
+            .sum();
+
+        self.indices.capacity()
+            * std::mem::size_of::<OnceLock<PathPositionIndex>>()
+            + built
+    }
+}
+
+impl PathPositions {
+    /// Create a set of indices for `count` paths, none of which are
+    /// built yet.
+    pub(super) fn with_paths(count: usize) -> Self {
+        Self {
+            indices: (0..count).map(|_| OnceLock::new()).collect(),
+        }
+    }
+
+    pub(super) fn append_path(&mut self) {
+        self.indices.push(OnceLock::new());
+    }
+
+    pub(super) fn get_or_build<F>(
+        &self,
+        id: PathId,
+        build: F,
+    ) -> Option<&PathPositionIndex>
+    where
+        F: FnOnce() -> PathPositionIndex,
+    {
+        Some(self.indices.get(id.0 as usize)?.get_or_init(build))
+    }
+
+    pub(super) fn invalidate(&mut self, id: PathId) {
+        if let Some(index) = self.indices.get_mut(id.0 as usize) {
+            index.take();
+        }
+    }
+
+    pub(super) fn invalidate_all(&mut self) {
+        self.indices.iter_mut().for_each(|index| {
+            index.take();
+        });
+    }
+}
@@ -2,6 +2,8 @@
 
 // use crate::handle::{Direction, Edge, Handle, NodeId};
 
+use std::io::{self, Read, Write};
+
 use super::super::graph::{NARROW_PAGE_WIDTH, WIDE_PAGE_WIDTH};
 
 use crate::pathhandlegraph::PathId;
@@ -123,6 +125,31 @@ impl Default for PathProperties {
     }
 }
 
+impl Serializable for PathProperties {
+    fn serialize<W: Write>(&self, writer: &mut W) -> io::Result<()> {
+        self.heads.serialize(writer)?;
+        self.tails.serialize(writer)?;
+        self.deleted.serialize(writer)?;
+        self.circular.serialize(writer)?;
+        self.deleted_steps.serialize(writer)
+    }
+
+    fn deserialize<R: Read>(reader: &mut R) -> io::Result<Self> {
+        let heads = PagedIntVec::deserialize(reader)?;
+        let tails = PagedIntVec::deserialize(reader)?;
+        let deleted = PackedIntVec::deserialize(reader)?;
+        let circular = PackedIntVec::deserialize(reader)?;
+        let deleted_steps = PackedIntVec::deserialize(reader)?;
+        Ok(Self {
+            heads,
+            tails,
+            deleted,
+            circular,
+            deleted_steps,
+        })
+    }
+}
+
 impl PathProperties {
     pub(super) fn append_record(&mut self) {
         self.heads.append(0);
@@ -178,21 +205,25 @@ impl PathProperties {
         }
     }
 
-    /*
-    fn set_record(&mut self, id: PathId, record: &PathPropertyRecord) -> bool {
+    pub(super) fn set_record(
+        &mut self,
+        id: PathId,
+        record: &PathPropertyRecord,
+    ) -> bool {
         if id.0 >= self.len() as u64 {
             return false;
         }
 
         let ix = id.0 as usize;
-        self.heads.set(ix, record.head_ptr.pack());
-        self.tails.set(ix, record.tail_ptr.pack());
-        self.deleted.set(ix, record.deleted.pack());
-        self.circular.set(ix, record.circular.pack());
-        self.deleted_steps.set(ix, record.deleted_steps.pack());
+        self.heads.set_pack(ix, record.head);
+        self.tails.set_pack(ix, record.tail);
+        self.deleted.set_pack(ix, record.deleted);
+        self.circular.set_pack(ix, record.circular);
+        self.deleted_steps.set_pack(ix, record.deleted_steps);
         true
     }
 
+    /*
     fn get_record(&self, id: PathId) -> Option<PathPropertyRecord> {
         if id.0 >= self.len() as u64 {
             return None;
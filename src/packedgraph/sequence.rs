@@ -1,3 +1,6 @@
+use std::io::{self, Read, Write};
+
+use crate::packed::serialize::{self, read_u64, write_u64};
 use crate::packed::*;
 
 use super::graph::{NodeRecordId, RecordIndex};
@@ -37,6 +40,140 @@ const fn decode_dna_base(byte: u64) -> u8 {
     }
 }
 
+// IUPAC codes are stored as a bitmask of the bases they can stand
+// for, with A, C, G, T as bits 0 to 3, so that the complement is the
+// mask with the bits reversed. The gap is the empty mask. Bit 4 is
+// set for lowercase bases in soft-masked sequences.
+const IUPAC_BASES: [u8; 16] = *b"-ACMGRSVTWYHKDBN";
+
+const IUPAC_N: u64 = 15;
+
+const SOFT_MASK_BIT: u64 = 1 << 4;
+
+#[inline]
+const fn encode_iupac_base(base: u8) -> u64 {
+    match base.to_ascii_uppercase() {
+        b'A' => 1,
+        b'C' => 2,
+        b'G' => 4,
+        b'T' | b'U' => 8,
+        b'M' => 3,
+        b'R' => 5,
+        b'S' => 6,
+        b'V' => 7,
+        b'W' => 9,
+        b'Y' => 10,
+        b'H' => 11,
+        b'K' => 12,
+        b'D' => 13,
+        b'B' => 14,
+        b'-' => 0,
+        _ => IUPAC_N,
+    }
+}
+
+#[inline]
+const fn iupac_complement(val: u64) -> u64 {
+    (val & SOFT_MASK_BIT)
+        | ((val & 1) << 3)
+        | ((val & 2) << 1)
+        | ((val & 4) >> 1)
+        | ((val & 8) >> 3)
+}
+
+#[inline]
+const fn decode_iupac_base(val: u64) -> u8 {
+    let base = IUPAC_BASES[(val & IUPAC_N) as usize];
+    if val & SOFT_MASK_BIT == 0 {
+        base
+    } else {
+        base.to_ascii_lowercase()
+    }
+}
+
+/// How the bases of the node sequences in a `PackedGraph` are
+/// stored. The encoding is chosen when the graph is created, and
+/// can't be changed afterward.
+#[derive(Default, Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum SequenceEncoding {
+    /// A, C, G, and T, in either case, are stored as uppercase
+    /// bases, and every other byte is stored as `N`. Uses 3 bits per
+    /// base.
+    #[default]
+    Dna,
+    /// All IUPAC nucleotide codes and the gap `-` are stored as
+    /// uppercase, and the ambiguity codes are complemented when the
+    /// sequence is reversed, e.g. `R` becomes `Y`. `U` is stored as
+    /// `T`, and any other byte as `N`. Uses 4 bits per base.
+    Iupac,
+    /// Like `Iupac`, but lowercase bases are preserved, so that
+    /// soft-masked sequences are kept as they are. Uses 5 bits per
+    /// base.
+    IupacSoftMasked,
+}
+
+impl SequenceEncoding {
+    #[inline]
+    pub(super) fn encode(self, base: u8) -> u64 {
+        match self {
+            SequenceEncoding::Dna => encode_dna_base(base),
+            SequenceEncoding::Iupac => encode_iupac_base(base),
+            SequenceEncoding::IupacSoftMasked => {
+                let val = encode_iupac_base(base);
+                if base.is_ascii_lowercase() {
+                    val | SOFT_MASK_BIT
+                } else {
+                    val
+                }
+            }
+        }
+    }
+
+    #[inline]
+    pub(super) fn decode(self, val: u64) -> u8 {
+        match self {
+            SequenceEncoding::Dna => decode_dna_base(val),
+            _ => decode_iupac_base(val),
+        }
+    }
+
+    #[inline]
+    pub(super) fn complement(self, val: u64) -> u64 {
+        match self {
+            SequenceEncoding::Dna => encoded_complement(val),
+            _ => iupac_complement(val),
+        }
+    }
+
+    /// Decode the stored value, complementing it first if `reverse`
+    /// is true.
+    #[inline]
+    pub(super) fn decode_oriented(self, val: u64, reverse: bool) -> u8 {
+        if reverse {
+            self.decode(self.complement(val))
+        } else {
+            self.decode(val)
+        }
+    }
+
+    pub(super) fn to_u64(self) -> u64 {
+        match self {
+            SequenceEncoding::Dna => 0,
+            SequenceEncoding::Iupac => 1,
+            SequenceEncoding::IupacSoftMasked => 2,
+        }
+    }
+
+    pub(super) fn from_u64(val: u64) -> io::Result<Self> {
+        match val {
+            0 => Ok(SequenceEncoding::Dna),
+            1 => Ok(SequenceEncoding::Iupac),
+            2 => Ok(SequenceEncoding::IupacSoftMasked),
+            _ => Err(serialize::invalid_data("unknown sequence encoding")),
+        }
+    }
+}
+
 // An index into both the offset record and the length record for some
 // sequence. It's a simple index into a packed vector, but the order
 // must be the same as the node records vector in the graph.
@@ -52,6 +189,18 @@ impl SeqRecordIx {
     }
 }
 
+impl PackedElement for SeqRecordIx {
+    #[inline]
+    fn unpack(v: u64) -> Self {
+        SeqRecordIx(v as usize)
+    }
+
+    #[inline]
+    fn pack(self) -> u64 {
+        self.0 as u64
+    }
+}
+
 impl RecordIndex for SeqRecordIx {
     const RECORD_WIDTH: usize = 1;
 
@@ -77,6 +226,7 @@ pub struct Sequences {
     lengths: PackedIntVec,
     offsets: PagedIntVec,
     removed_records: Vec<SeqRecordIx>,
+    encoding: SequenceEncoding,
 }
 
 crate::impl_space_usage!(
@@ -86,23 +236,76 @@ crate::impl_space_usage!(
 
 impl Default for Sequences {
     fn default() -> Self {
+        Self::with_encoding(Default::default())
+    }
+}
+
+impl Serializable for Sequences {
+    fn serialize<W: Write>(&self, writer: &mut W) -> io::Result<()> {
+        self.sequences.serialize(writer)?;
+        self.lengths.serialize(writer)?;
+        self.offsets.serialize(writer)?;
+        self.removed_records.serialize(writer)?;
+        write_u64(writer, self.encoding.to_u64())
+    }
+
+    fn deserialize<R: Read>(reader: &mut R) -> io::Result<Self> {
+        let sequences = PackedIntVec::deserialize(reader)?;
+        let lengths = PackedIntVec::deserialize(reader)?;
+        let offsets = PagedIntVec::deserialize(reader)?;
+        let removed_records = Vec::deserialize(reader)?;
+        let encoding = SequenceEncoding::from_u64(read_u64(reader)?)?;
+        Ok(Self {
+            sequences,
+            lengths,
+            offsets,
+            removed_records,
+            encoding,
+        })
+    }
+}
+
+impl Sequences {
+    pub(super) fn with_encoding(encoding: SequenceEncoding) -> Self {
         Sequences {
             sequences: Default::default(),
             lengths: Default::default(),
             offsets: PagedIntVec::new(super::graph::NARROW_PAGE_WIDTH),
             removed_records: Vec::new(),
+            encoding,
         }
     }
-}
 
-impl Sequences {
-    /// Add a new, empty sequence record.
+    #[inline]
+    pub(super) fn encoding(&self) -> SequenceEncoding {
+        self.encoding
+    }
 
+    /// Add a new, empty sequence record.
     pub(super) fn append_empty_record(&mut self) {
         self.lengths.append(0);
         self.offsets.append(0);
     }
 
+    /// The number of sequence records, including removed records.
+    #[inline]
+    pub(super) fn len(&self) -> usize {
+        self.lengths.len()
+    }
+
+    /// Reserve space for `additional` more sequence records, with a
+    /// total of `additional_bases` bases.
+    pub(super) fn reserve(
+        &mut self,
+        additional: usize,
+        additional_bases: usize,
+    ) {
+        self.sequences
+            .reserve(self.sequences.len() + additional_bases);
+        self.lengths.reserve(self.lengths.len() + additional);
+        self.offsets.reserve(self.offsets.len() + additional);
+    }
+
     fn set_record(
         &mut self,
         seq_ix: SeqRecordIx,
@@ -123,6 +326,11 @@ impl Sequences {
         (offset, length)
     }
 
+    #[inline]
+    pub(super) fn removed_records(&self) -> &[SeqRecordIx] {
+        &self.removed_records
+    }
+
     pub(super) fn clear_record(&mut self, seq_ix: SeqRecordIx) {
         let ix = seq_ix.at_0();
 
@@ -154,8 +362,9 @@ impl Sequences {
 
         self.set_record(seq_ix, offset, len);
 
+        let encoding = self.encoding;
         seq.iter()
-            .for_each(|&b| self.sequences.append(encode_dna_base(b)));
+            .for_each(|&b| self.sequences.append(encoding.encode(b)));
 
         Some(seq_ix)
     }
@@ -170,13 +379,13 @@ impl Sequences {
     ) {
         let seq_ix = SeqRecordIx::from_one_based_ix(rec_id).unwrap();
 
-        let (old_len, offset) = self.get_record(seq_ix);
+        let (offset, old_len) = self.get_record(seq_ix);
 
         assert!(old_len == seq.len());
 
         for (i, b) in seq.iter().copied().enumerate() {
             let ix = offset + i;
-            self.sequences.set(ix, encode_dna_base(b));
+            self.sequences.set(ix, self.encoding.encode(b));
         }
     }
 
@@ -258,16 +467,48 @@ impl Sequences {
 
         PackedSeqIter {
             iter,
-            length: len,
             reverse,
+            encoding: self.encoding,
         }
     }
+
+    /// Get the base at `index` of the sequence for the provided
+    /// `NodeRecordId`, counting from the end and complemented if
+    /// `reverse` is true.
+    ///
+    /// Panics if the index is past the end of the sequence.
+    #[inline]
+    pub(super) fn base(
+        &self,
+        rec_id: NodeRecordId,
+        index: usize,
+        reverse: bool,
+    ) -> u8 {
+        let seq_ix = SeqRecordIx::from_one_based_ix(rec_id).unwrap();
+        let (offset, len) = self.get_record(seq_ix);
+
+        assert!(
+            index < len,
+            "base index {} out of bounds for sequence of length {}",
+            index,
+            len
+        );
+
+        let ix = if reverse {
+            offset + len - 1 - index
+        } else {
+            offset + index
+        };
+
+        self.encoding
+            .decode_oriented(self.sequences.get(ix), reverse)
+    }
 }
 
 pub struct PackedSeqIter<'a> {
     iter: packed::vector::Iter<'a>,
-    length: usize,
     reverse: bool,
+    encoding: SequenceEncoding,
 }
 
 impl<'a> Iterator for PackedSeqIter<'a> {
@@ -275,23 +516,32 @@ impl<'a> Iterator for PackedSeqIter<'a> {
 
     #[inline]
     fn next(&mut self) -> Option<u8> {
-        if self.reverse {
-            let base = self.iter.next_back()?;
-            Some(decode_dna_base(encoded_complement(base)))
+        let base = if self.reverse {
+            self.iter.next_back()?
         } else {
-            let base = self.iter.next()?;
-            Some(decode_dna_base(base))
-        }
+            self.iter.next()?
+        };
+        Some(self.encoding.decode_oriented(base, self.reverse))
     }
-}
 
-impl<'a> std::iter::ExactSizeIterator for PackedSeqIter<'a> {
     #[inline]
-    fn len(&self) -> usize {
-        self.length
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.iter.size_hint()
+    }
+
+    #[inline]
+    fn nth(&mut self, n: usize) -> Option<u8> {
+        let base = if self.reverse {
+            self.iter.nth_back(n)?
+        } else {
+            self.iter.nth(n)?
+        };
+        Some(self.encoding.decode_oriented(base, self.reverse))
     }
 }
 
+impl<'a> std::iter::ExactSizeIterator for PackedSeqIter<'a> {}
+
 #[cfg(test)]
 mod tests {
     use super::*;
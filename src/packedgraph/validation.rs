@@ -0,0 +1,387 @@
+use fnv::FnvHashSet;
+
+use crate::{
+    handle::{Direction, Edge, Handle, NodeId},
+    pathhandlegraph::{AllPathIds, PathId, PathRef},
+    validation::{RecordKind, ValidationIssue, ValidationReport},
+};
+
+use super::{
+    graph::PackedGraph,
+    index::{OneBasedIndex, RecordIndex},
+    EdgeListIx, OccurListIx, PathStepIx,
+};
+
+/// Find the records that are neither reachable nor removed, out of
+/// the first `count` records.
+fn orphan_records<I>(
+    kind: RecordKind,
+    count: usize,
+    reachable: &FnvHashSet<I>,
+    removed: &[I],
+) -> Vec<ValidationIssue<PathId>>
+where
+    I: OneBasedIndex + Copy + Eq + std::hash::Hash,
+{
+    let removed = removed.iter().copied().collect::<FnvHashSet<_>>();
+    (0..count)
+        .filter(|&ix| {
+            let ix = I::from_zero_based(ix);
+            !reachable.contains(&ix) && !removed.contains(&ix)
+        })
+        .map(|index| ValidationIssue::OrphanRecord { kind, index })
+        .collect()
+}
+
+impl PackedGraph {
+    /// Check that the internal records of the graph are consistent
+    /// with each other, and return a report of every inconsistency
+    /// that was found. This checks that:
+    ///
+    /// * every node ID maps to its own node record, and every node
+    ///   record belongs to a node or has been removed,
+    /// * every edge record leads to an existing node, and has a
+    ///   matching record in the edge list of that node,
+    /// * every path step is on an existing node, and consecutive
+    ///   steps are connected by edges,
+    /// * the node occurrences are exactly the steps of the paths,
+    /// * every edge and occurrence record is reachable from a node,
+    ///   or has been removed.
+    ///
+    /// Edges are reported as `Edge(source, target)`, where `target`
+    /// is the handle in the edge record, and `source` is the handle
+    /// whose right-hand edge list contains the record.
+    pub fn validate(&self) -> ValidationReport<PathId> {
+        let mut report = ValidationReport::new();
+        self.validate_nodes(&mut report);
+        self.validate_edges(&mut report);
+        self.validate_paths(&mut report);
+        report
+    }
+
+    fn validate_nodes(&self, report: &mut ValidationReport<PathId>) {
+        let record_count = self.nodes.record_count();
+        let mut used = FnvHashSet::default();
+
+        for (id, rec_id) in self.nodes.node_record_ids() {
+            match rec_id.to_zero_based() {
+                Some(ix) if ix < record_count && used.insert(rec_id) => (),
+                _ => report.push(ValidationIssue::InvalidNodeRecord(id)),
+            }
+        }
+
+        let removed = self
+            .nodes
+            .sequences()
+            .removed_records()
+            .iter()
+            .map(|&seq_ix| seq_ix.to_one_based_ix())
+            .collect::<Vec<_>>();
+
+        report.issues.extend(orphan_records(
+            RecordKind::Node,
+            record_count,
+            &used,
+            &removed,
+        ));
+    }
+
+    /// Returns `true` if the edge list on the `dir` side of the
+    /// node of `node` contains a record with the handle `target`.
+    fn edge_list_contains(
+        &self,
+        node: Handle,
+        dir: Direction,
+        target: Handle,
+    ) -> bool {
+        self.nodes.handle_record(node).is_some_and(|rec_id| {
+            let head = self.nodes.get_edge_list(rec_id, dir);
+            self.edges
+                .iter(head)
+                .any(|(_, (handle, _))| handle == target)
+        })
+    }
+
+    fn validate_edges(&self, report: &mut ValidationReport<PathId>) {
+        let mut reachable: FnvHashSet<EdgeListIx> = FnvHashSet::default();
+
+        for (id, rec_id) in self.nodes.node_record_ids() {
+            for &dir in [Direction::Left, Direction::Right].iter() {
+                let source = Handle::pack(id, dir == Direction::Left);
+                let head = self.nodes.get_edge_list(rec_id, dir);
+
+                for (edge_ix, (target, _)) in self.edges.iter(head) {
+                    // Stop if the list loops back on itself
+                    if !reachable.insert(edge_ix) {
+                        break;
+                    }
+
+                    let edge = Edge(source, target);
+
+                    if !self.nodes.has_node(target.id()) {
+                        report.push(ValidationIssue::DanglingEdge(edge));
+                        continue;
+                    }
+
+                    // The other record of the edge is stored on the
+                    // target node, on the side that depends on which
+                    // of the two records this one is
+                    let (back_dir, fwd_dir) = if target.is_reverse() {
+                        (Direction::Right, Direction::Left)
+                    } else {
+                        (Direction::Left, Direction::Right)
+                    };
+
+                    let symmetric = self
+                        .edge_list_contains(target, back_dir, source)
+                        || self.edge_list_contains(
+                            target,
+                            fwd_dir,
+                            source.flip(),
+                        );
+
+                    if !symmetric {
+                        report.push(ValidationIssue::AsymmetricEdge(edge));
+                    }
+                }
+            }
+        }
+
+        report.issues.extend(orphan_records(
+            RecordKind::Edge,
+            self.edges.record_count(),
+            &reachable,
+            self.edges.removed_records(),
+        ));
+    }
+
+    fn validate_paths(&self, report: &mut ValidationReport<PathId>) {
+        let mut reachable: FnvHashSet<OccurListIx> = FnvHashSet::default();
+        let mut occurrences: FnvHashSet<(PathId, PathStepIx, NodeId)> =
+            FnvHashSet::default();
+
+        for (id, rec_id) in self.nodes.node_record_ids() {
+            let head = self
+                .nodes
+                .node_record_occur(rec_id)
+                .unwrap_or_else(OccurListIx::null);
+
+            for (occur_ix, record) in self.occurrences.iter(head) {
+                if !reachable.insert(occur_ix) {
+                    break;
+                }
+                occurrences.insert((record.path_id, record.offset, id));
+            }
+        }
+
+        let mut path_ids = self.all_path_ids().collect::<Vec<_>>();
+        path_ids.sort();
+
+        for path_id in path_ids {
+            let path = match self.paths.path_ref(path_id) {
+                Some(path) => path,
+                None => continue,
+            };
+
+            // Bounded by the step count in case the steps loop
+            let steps = path
+                .steps()
+                .take(path.len())
+                .map(|(ix, step)| (ix, step.handle))
+                .collect::<Vec<_>>();
+
+            report.check_path_steps(
+                self,
+                path_id,
+                path.circular(),
+                steps.iter().map(|&(_, handle)| handle),
+            );
+
+            for (ix, handle) in steps {
+                if self.nodes.has_node(handle.id())
+                    && !occurrences.remove(&(path_id, ix, handle.id()))
+                {
+                    report.push(ValidationIssue::MissingOccurrence {
+                        path: path_id,
+                        handle,
+                    });
+                }
+            }
+        }
+
+        // Any remaining occurrences don't match a path step
+        let mut invalid = occurrences.into_iter().collect::<Vec<_>>();
+        invalid.sort_by_key(|&(path, _, node)| (node, path));
+
+        report
+            .issues
+            .extend(invalid.into_iter().map(|(path, _, node)| {
+                ValidationIssue::InvalidOccurrence { path, node }
+            }));
+
+        report.issues.extend(orphan_records(
+            RecordKind::Occurrence,
+            self.occurrences.record_count(),
+            &reachable,
+            self.occurrences.removed_records(),
+        ));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::{
+        mutablehandlegraph::*, packed::*, pathhandlegraph::MutEmbeddedPaths,
+    };
+
+    fn hnd(x: u64) -> Handle {
+        Handle::pack(x, false)
+    }
+
+    fn graph_with_path(steps: &[Handle], circular: bool) -> PackedGraph {
+        let mut graph = PackedGraph::new();
+        for seq in [b"GTCA", b"AAGT", b"CCTA", b"GGAT"].iter() {
+            graph.append_handle(&seq[..]);
+        }
+
+        graph.create_edge(Edge(hnd(1), hnd(2)));
+        graph.create_edge(Edge(hnd(2), hnd(3).flip()));
+        graph.create_edge(Edge(hnd(3).flip(), hnd(4)));
+        graph.create_edge(Edge(hnd(4), hnd(1)));
+
+        let path = graph.create_path(b"path1", circular);
+        graph.with_path_mut_ctx(path, |path_ref| {
+            steps.iter().map(|&h| path_ref.append_handle(h)).collect()
+        });
+
+        graph
+    }
+
+    #[test]
+    fn validate_consistent_graph() {
+        let steps = [hnd(1), hnd(2), hnd(3).flip(), hnd(4)];
+        let mut graph = graph_with_path(&steps, true);
+        assert_eq!(graph.validate(), ValidationReport::new());
+
+        // Paths can also traverse edges in reverse
+        let path = graph.create_path(b"path2", false);
+        graph.with_path_mut_ctx(path, |path_ref| {
+            vec![
+                path_ref.append_handle(hnd(3)),
+                path_ref.append_handle(hnd(2).flip()),
+            ]
+        });
+        assert!(graph.validate().is_valid());
+
+        // Removed records are not reported as orphans
+        graph.divide_handle(hnd(1), vec![2]);
+        graph.remove_path(path);
+        graph.remove_edge(Edge(hnd(4), hnd(1)));
+        graph.remove_handle(hnd(1));
+        assert_eq!(graph.validate(), ValidationReport::new());
+    }
+
+    #[test]
+    fn validate_reports_inconsistencies() {
+        use ValidationIssue as VI;
+
+        let steps = [hnd(1), hnd(2), hnd(3).flip()];
+        let mut graph = graph_with_path(&steps, false);
+
+        // An edge record on node 1 without a matching record on node
+        // 3, and one leading to a node that doesn't exist
+        let rec_1 = graph.nodes.handle_record(hnd(1)).unwrap();
+        let head = graph.nodes.get_edge_list(rec_1, Direction::Right);
+        let head = graph.edges.append_record(hnd(3), head);
+        let head = graph.edges.append_record(hnd(7), head);
+        graph.nodes.set_edge_list(rec_1, Direction::Right, head);
+
+        // An edge record that isn't in any edge list
+        let orphan = graph.edges.append_record(hnd(2), EdgeListIx::null());
+
+        assert_eq!(
+            graph.validate().issues,
+            vec![
+                VI::DanglingEdge(Edge(hnd(1), hnd(7))),
+                VI::AsymmetricEdge(Edge(hnd(1), hnd(3))),
+                VI::OrphanRecord {
+                    kind: RecordKind::Edge,
+                    index: orphan.to_zero_based().unwrap(),
+                },
+            ]
+        );
+
+        // A path step across a missing edge, and a node whose
+        // occurrences have been cut off
+        let mut graph = graph_with_path(&steps, false);
+        let path = graph.create_path(b"path2", false);
+        graph.with_path_mut_ctx(path, |path_ref| {
+            vec![
+                path_ref.append_handle(hnd(1)),
+                path_ref.append_handle(hnd(3)),
+            ]
+        });
+
+        let rec_3 = graph.nodes.handle_record(hnd(3)).unwrap();
+        let occur_head = graph.nodes.node_record_occur(rec_3).unwrap();
+        let occur_ixs = graph
+            .occurrences
+            .iter(occur_head)
+            .map(|(ix, _)| ix.to_zero_based().unwrap())
+            .collect::<Vec<_>>();
+        graph
+            .nodes
+            .node_occurrence_map
+            .set_pack(rec_3.to_zero_based().unwrap(), OccurListIx::null());
+
+        let mut expected = vec![
+            VI::MissingOccurrence {
+                path: PathId(0),
+                handle: hnd(3).flip(),
+            },
+            VI::MissingPathEdge {
+                path: PathId(1),
+                edge: Edge(hnd(1), hnd(3)),
+            },
+            VI::MissingOccurrence {
+                path: PathId(1),
+                handle: hnd(3),
+            },
+        ];
+        expected.extend(occur_ixs.into_iter().rev().map(|index| {
+            VI::OrphanRecord {
+                kind: RecordKind::Occurrence,
+                index,
+            }
+        }));
+
+        let report = graph.validate();
+        assert!(!report.is_valid());
+        assert_eq!(report.issues, expected);
+
+        // An occurrence on a node that the step isn't on
+        let graph = {
+            let mut graph = graph_with_path(&steps, false);
+            let rec_4 = graph.nodes.handle_record(hnd(4)).unwrap();
+            let head = graph.nodes.node_record_occur(rec_4).unwrap();
+            let first_step = PathStepIx::from_zero_based(0usize);
+            let head =
+                graph.occurrences.append_entry(PathId(0), first_step, head);
+            graph
+                .nodes
+                .node_occurrence_map
+                .set_pack(rec_4.to_zero_based().unwrap(), head);
+            graph
+        };
+
+        assert_eq!(
+            graph.validate().issues,
+            vec![VI::InvalidOccurrence {
+                path: PathId(0),
+                node: NodeId::from(4u64),
+            }]
+        );
+    }
+}
@@ -0,0 +1,617 @@
+use std::fs::File;
+use std::io;
+use std::path::Path;
+
+use memmap2::Mmap;
+
+use crate::{
+    handle::{Direction, Handle, NodeId},
+    handlegraph::*,
+    packed::serialize::invalid_data,
+    packed::view::*,
+    pathhandlegraph::{HandleOccurrences, OccurBase, PathId},
+};
+
+use super::graph::{FORMAT_VERSION, SERIALIZED_MAGIC};
+use super::index::list::{self, PackedList};
+use super::sequence::SequenceEncoding;
+use super::{
+    EdgeListIx, EdgeRecord, GraphVecIx, NodeRecordId, OccurListIx,
+    OneBasedIndex, PackedHandlesIter, PathStepIx, RecordIndex,
+};
+
+/// Read-only view of the sequence records of a serialized
+/// `PackedGraph`.
+#[derive(Debug, Clone)]
+pub struct SequencesView<'a> {
+    sequences: PackedIntVecView<'a>,
+    lengths: PackedIntVecView<'a>,
+    offsets: PagedIntVecView<'a>,
+    encoding: SequenceEncoding,
+}
+
+impl<'a> SequencesView<'a> {
+    fn from_cursor(cursor: &mut WordCursor<'a>) -> io::Result<Self> {
+        let sequences = PackedIntVecView::from_cursor(cursor)?;
+        let lengths = PackedIntVecView::from_cursor(cursor)?;
+        let offsets = PagedIntVecView::from_cursor(cursor)?;
+        cursor.skip_vec()?;
+        let encoding = SequenceEncoding::from_u64(cursor.read_u64()?)?;
+        Ok(Self {
+            sequences,
+            lengths,
+            offsets,
+            encoding,
+        })
+    }
+
+    /// Get the offset and length of a sequence record, or `None` if
+    /// the record doesn't exist or lies outside the stored bases.
+    #[inline]
+    fn get_record(&self, rec_id: NodeRecordId) -> Option<(usize, usize)> {
+        let ix = rec_id.to_zero_based()?;
+        if ix >= self.lengths.len() || ix >= self.offsets.len() {
+            return None;
+        }
+        let offset: usize = self.offsets.get_unpack(ix);
+        let length: usize = self.lengths.get_unpack(ix);
+        let end = offset.checked_add(length)?;
+        if end > self.sequences.len() {
+            return None;
+        }
+        Some((offset, length))
+    }
+
+    /// Check that every sequence record lies within the stored bases.
+    fn validate_records(&self) -> io::Result<()> {
+        if self.lengths.len() != self.offsets.len() {
+            return Err(invalid_data("invalid sequence records"));
+        }
+
+        let all_valid = (0..self.lengths.len()).all(|ix| {
+            let rec_id = NodeRecordId::from_zero_based(ix);
+            self.get_record(rec_id).is_some()
+        });
+
+        if all_valid {
+            Ok(())
+        } else {
+            Err(invalid_data("invalid sequence record"))
+        }
+    }
+}
+
+/// Iterator over the bases of a node in a `PackedGraphView`.
+pub struct PackedSeqViewIter<'a> {
+    sequences: &'a PackedIntVecView<'a>,
+    left_ix: usize,
+    right_ix: usize,
+    reverse: bool,
+    encoding: SequenceEncoding,
+}
+
+impl<'a> Iterator for PackedSeqViewIter<'a> {
+    type Item = u8;
+
+    #[inline]
+    fn next(&mut self) -> Option<u8> {
+        if self.left_ix >= self.right_ix {
+            return None;
+        }
+        let base = if self.reverse {
+            self.right_ix -= 1;
+            self.sequences.get(self.right_ix)
+        } else {
+            let base = self.sequences.get(self.left_ix);
+            self.left_ix += 1;
+            base
+        };
+        Some(self.encoding.decode_oriented(base, self.reverse))
+    }
+
+    #[inline]
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        let len = self.right_ix - self.left_ix;
+        (len, Some(len))
+    }
+}
+
+impl<'a> std::iter::ExactSizeIterator for PackedSeqViewIter<'a> {}
+
+/// Read-only view of the edge lists of a serialized `PackedGraph`.
+#[derive(Debug, Clone)]
+pub struct EdgeListsView<'a> {
+    record_vec: PagedIntVecView<'a>,
+}
+
+impl<'a> EdgeListsView<'a> {
+    fn from_cursor(cursor: &mut WordCursor<'a>) -> io::Result<Self> {
+        let record_vec = PagedIntVecView::from_cursor(cursor)?;
+        cursor.skip_vec()?;
+        Ok(Self { record_vec })
+    }
+}
+
+impl<'a> PackedList for EdgeListsView<'a> {
+    type ListPtr = EdgeListIx;
+    type ListRecord = EdgeRecord;
+
+    #[inline]
+    fn next_pointer(rec: &EdgeRecord) -> EdgeListIx {
+        rec.1
+    }
+
+    #[inline]
+    fn get_record(&self, ptr: EdgeListIx) -> Option<EdgeRecord> {
+        let h_ix = ptr.to_record_ix(2, 0)?;
+        let n_ix = ptr.to_record_ix(2, 1)?;
+        if n_ix >= self.record_vec.len() {
+            return None;
+        }
+        let handle = self.record_vec.get_unpack(h_ix);
+        let next = self.record_vec.get_unpack(n_ix);
+        Some((handle, next))
+    }
+}
+
+/// Iterator over the handles in an edge list of a `PackedGraphView`.
+pub struct EdgeListViewHandleIter<'a> {
+    edge_list_iter: list::Iter<'a, EdgeListsView<'a>>,
+}
+
+impl<'a> Iterator for EdgeListViewHandleIter<'a> {
+    type Item = Handle;
+
+    #[inline]
+    fn next(&mut self) -> Option<Handle> {
+        let (_, (handle, _)) = self.edge_list_iter.next()?;
+        Some(handle)
+    }
+}
+
+/// A record in the node occurrence lists of a `PackedGraphView`, in
+/// the form `(path, step, next)`.
+pub type OccurViewRecord = (PathId, PathStepIx, OccurListIx);
+
+/// Read-only view of the node occurrences of a serialized
+/// `PackedGraph`.
+#[derive(Debug, Clone)]
+pub struct NodeOccurrencesView<'a> {
+    path_ids: PagedIntVecView<'a>,
+    node_occur_offsets: PagedIntVecView<'a>,
+    node_occur_next: PagedIntVecView<'a>,
+}
+
+impl<'a> NodeOccurrencesView<'a> {
+    fn from_cursor(cursor: &mut WordCursor<'a>) -> io::Result<Self> {
+        let path_ids = PagedIntVecView::from_cursor(cursor)?;
+        let node_occur_offsets = PagedIntVecView::from_cursor(cursor)?;
+        let node_occur_next = PagedIntVecView::from_cursor(cursor)?;
+        cursor.skip_vec()?;
+        Ok(Self {
+            path_ids,
+            node_occur_offsets,
+            node_occur_next,
+        })
+    }
+}
+
+impl<'a> PackedList for NodeOccurrencesView<'a> {
+    type ListPtr = OccurListIx;
+    type ListRecord = OccurViewRecord;
+
+    #[inline]
+    fn next_pointer(rec: &OccurViewRecord) -> OccurListIx {
+        rec.2
+    }
+
+    #[inline]
+    fn get_record(&self, ptr: OccurListIx) -> Option<OccurViewRecord> {
+        let ix = ptr.to_zero_based()?;
+        if ix >= self.path_ids.len() {
+            return None;
+        }
+
+        let path_id = PathId(self.path_ids.get(ix));
+        let offset = self.node_occur_offsets.get_unpack(ix);
+        let next = self.node_occur_next.get_unpack(ix);
+        Some((path_id, offset, next))
+    }
+}
+
+/// Iterator over the path steps on a handle in a `PackedGraphView`.
+pub struct OccurrencesViewIter<'a> {
+    list_iter: list::Iter<'a, NodeOccurrencesView<'a>>,
+}
+
+impl<'a> Iterator for OccurrencesViewIter<'a> {
+    type Item = (PathId, PathStepIx);
+
+    #[inline]
+    fn next(&mut self) -> Option<Self::Item> {
+        let (_, (path_id, step_ix, _)) = self.list_iter.next()?;
+        Some((path_id, step_ix))
+    }
+}
+
+/// A read-only `PackedGraph` that's backed directly by the bytes of a
+/// graph serialized using `PackedGraph::serialize`, without copying
+/// any of the packed collections into memory.
+///
+/// Provides the `AllHandles`, `AllEdges`, `HandleNeighbors`,
+/// `HandleSequences` and `HandleOccurrences` traits. The embedded
+/// paths themselves are not available through the view.
+#[derive(Debug, Clone)]
+pub struct PackedGraphView<'a> {
+    bytes: &'a [u8],
+    records_vec: PagedIntVecView<'a>,
+    id_index_map: PackedDequeView<'a>,
+    max_id: u64,
+    min_id: u64,
+    sequences: SequencesView<'a>,
+    node_occurrence_map: PagedIntVecView<'a>,
+    edges: EdgeListsView<'a>,
+    occurrences: NodeOccurrencesView<'a>,
+}
+
+impl<'a> PackedGraphView<'a> {
+    /// Create a view over the serialized graph in `bytes`.
+    ///
+    /// Only the header and the layout of the packed collections are
+    /// validated, which is proportional to the number of pages in
+    /// the graph's collections rather than the size of the graph. Use
+    /// `validate` to check the integrity of the data; queries on a
+    /// view of corrupt data that hasn't been validated may panic.
+    pub fn new(bytes: &'a [u8]) -> io::Result<Self> {
+        let mut cursor = WordCursor::new(bytes);
+
+        if cursor.take_bytes(8)? != SERIALIZED_MAGIC {
+            return Err(invalid_data("not a serialized PackedGraph"));
+        }
+
+        if cursor.read_u64()? != FORMAT_VERSION {
+            return Err(invalid_data("unsupported PackedGraph format version"));
+        }
+
+        let records_vec = PagedIntVecView::from_cursor(&mut cursor)?;
+        let id_index_map = PackedDequeView::from_cursor(&mut cursor)?;
+        let max_id = cursor.read_u64()?;
+        let min_id = cursor.read_u64()?;
+        let sequences = SequencesView::from_cursor(&mut cursor)?;
+        cursor.skip_vec()?;
+        let node_occurrence_map = PagedIntVecView::from_cursor(&mut cursor)?;
+
+        let edges = EdgeListsView::from_cursor(&mut cursor)?;
+        let occurrences = NodeOccurrencesView::from_cursor(&mut cursor)?;
+
+        Ok(Self {
+            bytes,
+            records_vec,
+            id_index_map,
+            max_id,
+            min_id,
+            sequences,
+            node_occurrence_map,
+            edges,
+            occurrences,
+        })
+    }
+
+    /// Compute the checksum of the serialized graph and compare it
+    /// to the checksum stored at the end of the data.
+    pub fn verify_checksum(&self) -> bool {
+        use std::hash::Hasher;
+
+        if self.bytes.len() < 24 {
+            return false;
+        }
+
+        let body_end = self.bytes.len() - 8;
+        let mut hasher = fnv::FnvHasher::default();
+        hasher.write(&self.bytes[16..body_end]);
+
+        let mut cursor = WordCursor::new(&self.bytes[body_end..]);
+        cursor.read_u64().ok() == Some(hasher.finish())
+    }
+
+    /// Check the integrity of the data, by verifying the checksum
+    /// and that every sequence record lies within the stored bases.
+    /// Takes time linear in the size of the graph.
+    pub fn validate(&self) -> io::Result<()> {
+        if !self.verify_checksum() {
+            return Err(invalid_data("PackedGraph checksum mismatch"));
+        }
+        self.sequences.validate_records()
+    }
+
+    #[inline]
+    fn get_index(&self, id: NodeId) -> Option<NodeRecordId> {
+        let id = u64::from(id);
+        if id < self.min_id || id > self.max_id {
+            return None;
+        }
+        let index = (id - self.min_id) as usize;
+        if index >= self.id_index_map.len() {
+            return None;
+        }
+        let rec_id: NodeRecordId = self.id_index_map.get_unpack(index);
+        if rec_id.is_null() {
+            None
+        } else {
+            Some(rec_id)
+        }
+    }
+
+    #[inline]
+    fn handle_record(&self, h: Handle) -> Option<NodeRecordId> {
+        self.get_index(h.id())
+    }
+
+    #[inline]
+    fn get_edge_list(
+        &self,
+        rec_id: NodeRecordId,
+        dir: Direction,
+    ) -> EdgeListIx {
+        match GraphVecIx::from_one_based_ix(rec_id) {
+            None => EdgeListIx::null(),
+            Some(vec_ix) => {
+                let ix = match dir {
+                    Direction::Right => vec_ix.right_edges_ix(),
+                    Direction::Left => vec_ix.left_edges_ix(),
+                };
+                self.records_vec.get_unpack(ix)
+            }
+        }
+    }
+}
+
+impl<'a, 'b> AllHandles for &'a PackedGraphView<'b> {
+    type Handles = PackedHandlesIter<ViewIter<'a, PackedDequeView<'b>>>;
+
+    #[inline]
+    fn all_handles(self) -> Self::Handles {
+        PackedHandlesIter::new(self.id_index_map.iter(), self.min_id as usize)
+    }
+
+    #[inline]
+    fn has_node<I: Into<NodeId>>(self, n_id: I) -> bool {
+        self.get_index(n_id.into()).is_some()
+    }
+}
+
+impl<'a, 'b> AllEdges for &'a PackedGraphView<'b> {
+    type Edges = EdgesIter<&'a PackedGraphView<'b>>;
+
+    fn all_edges(self) -> Self::Edges {
+        EdgesIter::new(self)
+    }
+}
+
+impl<'a, 'b> HandleNeighbors for &'a PackedGraphView<'b> {
+    type Neighbors = EdgeListViewHandleIter<'a>;
+
+    #[inline]
+    fn neighbors(self, handle: Handle, dir: Direction) -> Self::Neighbors {
+        let g_ix = self.handle_record(handle).unwrap();
+
+        let edge_list_ix = match (dir, handle.is_reverse()) {
+            (Direction::Left, true) => {
+                self.get_edge_list(g_ix, Direction::Right)
+            }
+            (Direction::Left, false) => {
+                self.get_edge_list(g_ix, Direction::Left)
+            }
+            (Direction::Right, true) => {
+                self.get_edge_list(g_ix, Direction::Left)
+            }
+            (Direction::Right, false) => {
+                self.get_edge_list(g_ix, Direction::Right)
+            }
+        };
+
+        EdgeListViewHandleIter {
+            edge_list_iter: list::Iter::new(&self.edges, edge_list_ix),
+        }
+    }
+}
+
+impl<'a, 'b> HandleSequences for &'a PackedGraphView<'b> {
+    type Sequence = PackedSeqViewIter<'a>;
+
+    #[inline]
+    fn sequence_iter(self, handle: Handle) -> Self::Sequence {
+        let rec_id = self.handle_record(handle).unwrap();
+        let (offset, len) = self.sequences.get_record(rec_id).unwrap();
+        PackedSeqViewIter {
+            sequences: &self.sequences.sequences,
+            left_ix: offset,
+            right_ix: offset + len,
+            reverse: handle.is_reverse(),
+            encoding: self.sequences.encoding,
+        }
+    }
+
+    #[inline]
+    fn node_len(self, handle: Handle) -> usize {
+        let rec_id = self.handle_record(handle).unwrap();
+        let (_, len) = self.sequences.get_record(rec_id).unwrap();
+        len
+    }
+}
+
+impl<'a> HandleGraph for PackedGraphView<'a> {
+    #[inline]
+    fn min_node_id(&self) -> NodeId {
+        self.min_id.into()
+    }
+
+    #[inline]
+    fn max_node_id(&self) -> NodeId {
+        self.max_id.into()
+    }
+}
+
+impl<'a, 'b> HandleGraphRef for &'a PackedGraphView<'b> {}
+
+impl<'a> OccurBase for PackedGraphView<'a> {
+    type StepIx = PathStepIx;
+}
+
+impl<'a, 'b> HandleOccurrences for &'a PackedGraphView<'b> {
+    type OccurIter = OccurrencesViewIter<'a>;
+
+    fn handle_occurrences(self, handle: Handle) -> Self::OccurIter {
+        let rec_id = self.handle_record(handle).unwrap();
+        let occ_ix = self
+            .node_occurrence_map
+            .get_unpack(rec_id.to_zero_based().unwrap());
+        OccurrencesViewIter {
+            list_iter: list::Iter::new(&self.occurrences, occ_ix),
+        }
+    }
+}
+
+/// A serialized `PackedGraph` file that has been memory-mapped, so
+/// that its pages can be shared between processes through the OS
+/// page cache.
+///
+/// The graph itself is accessed through `PackedGraphView`, using
+/// `MmapPackedGraph::view`.
+pub struct MmapPackedGraph {
+    mmap: Mmap,
+}
+
+impl MmapPackedGraph {
+    /// Memory-map the serialized graph at `path`, and check its
+    /// integrity using `PackedGraphView::validate`.
+    ///
+    /// The file must not be modified while it is mapped; doing so is
+    /// undefined behavior.
+    pub fn open<P: AsRef<Path>>(path: P) -> io::Result<Self> {
+        let file = File::open(path)?;
+        let mmap = unsafe { Mmap::map(&file)? };
+        PackedGraphView::new(&mmap)?.validate()?;
+        Ok(Self { mmap })
+    }
+
+    /// Get a view of the graph. This walks the headers of the packed
+    /// collections, so the view should be reused rather than
+    /// recreated for each query.
+    pub fn view(&self) -> io::Result<PackedGraphView<'_>> {
+        PackedGraphView::new(&self.mmap)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::handle::Edge;
+    use crate::packedgraph::PackedGraph;
+    use crate::{
+        mutablehandlegraph::AdditiveHandleGraph,
+        pathhandlegraph::MutEmbeddedPaths,
+    };
+
+    fn test_graph() -> PackedGraph {
+        let mut graph = PackedGraph::new();
+        let h1 = graph.append_handle(b"GTCA");
+        let h2 = graph.append_handle(b"AAGTGCTAGT");
+        let h3 = graph.append_handle(b"ATA");
+        let h4 = graph.create_handle(b"GNNCT", 7);
+
+        graph.create_edge(Edge(h1, h2));
+        graph.create_edge(Edge(h1, h3.flip()));
+        graph.create_edge(Edge(h2, h4));
+        graph.create_edge(Edge(h3.flip(), h4));
+
+        let path = graph.create_path(b"path1", false);
+        let steps = [h1, h2, h4];
+        graph.with_path_mut_ctx(path, |path_ref| {
+            steps
+                .iter()
+                .map(|&h| path_ref.append_handle(h))
+                .collect::<Vec<_>>()
+        });
+
+        graph
+    }
+
+    #[test]
+    fn packedgraph_view_matches_graph() {
+        let graph = test_graph();
+
+        let mut bytes: Vec<u8> = Vec::new();
+        graph.serialize(&mut bytes).unwrap();
+
+        let view = PackedGraphView::new(&bytes).unwrap();
+        assert!(view.verify_checksum());
+
+        assert_eq!(view.min_node_id(), graph.min_node_id());
+        assert_eq!(view.max_node_id(), graph.max_node_id());
+
+        let handles = graph.all_handles().collect::<Vec<_>>();
+        assert_eq!(view.all_handles().collect::<Vec<_>>(), handles);
+        assert!(view.has_node(7));
+        assert!(!view.has_node(5));
+
+        let mut edges = graph.all_edges().collect::<Vec<_>>();
+        let mut view_edges = view.all_edges().collect::<Vec<_>>();
+        edges.sort();
+        view_edges.sort();
+        assert_eq!(edges, view_edges);
+
+        for h in handles {
+            for &h in [h, h.flip()].iter() {
+                assert_eq!(
+                    view.sequence_iter(h).collect::<Vec<_>>(),
+                    graph.sequence_iter(h).collect::<Vec<_>>()
+                );
+                assert_eq!(view.node_len(h), graph.node_len(h));
+
+                for &dir in [Direction::Left, Direction::Right].iter() {
+                    assert_eq!(
+                        view.neighbors(h, dir).collect::<Vec<_>>(),
+                        graph.neighbors(h, dir).collect::<Vec<_>>()
+                    );
+                }
+
+                assert_eq!(
+                    view.handle_occurrences(h).collect::<Vec<_>>(),
+                    graph.handle_occurrences(h).collect::<Vec<_>>()
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn packedgraph_view_mmap() {
+        let graph = test_graph();
+
+        let mut path = std::env::temp_dir();
+        path.push(format!("handlegraph_view_test_{}.pg", std::process::id()));
+
+        let file = File::create(&path).unwrap();
+        graph.serialize(file).unwrap();
+
+        let mmap_graph = MmapPackedGraph::open(&path).unwrap();
+        let view = mmap_graph.view().unwrap();
+        assert!(view.verify_checksum());
+
+        assert_eq!(view.all_edges().count(), graph.all_edges().count());
+        assert_eq!(view.sequence(Handle::pack(2, false)), b"AAGTGCTAGT");
+
+        // Corrupt data that passes the layout checks is rejected when
+        // opening the file
+        let mut bytes = std::fs::read(&path).unwrap();
+        let last = bytes.len() - 9;
+        bytes[last] ^= 1;
+        std::fs::write(&path, &bytes).unwrap();
+        assert!(PackedGraphView::new(&bytes).is_ok());
+        let err = MmapPackedGraph::open(&path).err().unwrap();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+
+        std::fs::remove_file(&path).unwrap();
+    }
+}
@@ -1,3 +1,4 @@
+use crate::error::HandleGraphResult;
 use crate::handle::Handle;
 
 pub trait EmbeddedPaths {
@@ -27,7 +28,19 @@ pub trait PathHandleGraph {
     /// Paths have string names as well as handles
     fn name_to_path_handle(&self, name: &[u8]) -> Option<Self::PathHandle>;
 
-    fn path_handle_to_name(&self, handle: &Self::PathHandle) -> &[u8];
+    /// Get the name of the path. Fails if the path doesn't exist.
+    fn try_path_handle_to_name(
+        &self,
+        handle: &Self::PathHandle,
+    ) -> HandleGraphResult<&[u8]>;
+
+    /// Like `try_path_handle_to_name`, but panics on failure.
+    fn path_handle_to_name(&self, handle: &Self::PathHandle) -> &[u8] {
+        match self.try_path_handle_to_name(handle) {
+            Ok(name) => name,
+            Err(err) => panic!("{}", err),
+        }
+    }
 
     fn is_circular(&self, handle: &Self::PathHandle) -> bool;
 
@@ -71,7 +84,19 @@ pub trait PathHandleGraph {
         pos: usize,
     ) -> Option<Self::StepHandle>;
 
-    fn destroy_path(&mut self, path: &Self::PathHandle);
+    /// Remove the path and all of its steps. Fails if the path
+    /// doesn't exist.
+    fn try_destroy_path(
+        &mut self,
+        path: &Self::PathHandle,
+    ) -> HandleGraphResult<()>;
+
+    /// Like `try_destroy_path`, but panics on failure.
+    fn destroy_path(&mut self, path: &Self::PathHandle) {
+        if let Err(err) = self.try_destroy_path(path) {
+            panic!("{}", err);
+        }
+    }
 
     fn next_step(&self, step_handle: &Self::StepHandle) -> Self::StepHandle;
 
@@ -84,24 +109,69 @@ pub trait PathHandleGraph {
         is_circular: bool,
     ) -> Self::PathHandle;
 
+    /// Append a step on the handle to the end of the path. Fails if
+    /// the path or the node of the handle doesn't exist.
+    fn try_append_step(
+        &mut self,
+        path: &Self::PathHandle,
+        to_append: Handle,
+    ) -> HandleGraphResult<Self::StepHandle>;
+
+    /// Like `try_append_step`, but panics on failure.
     fn append_step(
         &mut self,
         path: &Self::PathHandle,
         to_append: Handle,
-    ) -> Self::StepHandle;
+    ) -> Self::StepHandle {
+        match self.try_append_step(path, to_append) {
+            Ok(step) => step,
+            Err(err) => panic!("{}", err),
+        }
+    }
+
+    /// Prepend a step on the handle to the start of the path. Fails
+    /// if the path or the node of the handle doesn't exist.
+    fn try_prepend_step(
+        &mut self,
+        path: &Self::PathHandle,
+        to_prepend: Handle,
+    ) -> HandleGraphResult<Self::StepHandle>;
 
+    /// Like `try_prepend_step`, but panics on failure.
     fn prepend_step(
         &mut self,
         path: &Self::PathHandle,
         to_prepend: Handle,
-    ) -> Self::StepHandle;
+    ) -> Self::StepHandle {
+        match self.try_prepend_step(path, to_prepend) {
+            Ok(step) => step,
+            Err(err) => panic!("{}", err),
+        }
+    }
+
+    /// Replace the steps from `begin` to `end`, inclusive, with steps
+    /// on the handles in `new_segment`. Fails if the path or any of
+    /// the new nodes doesn't exist, or if the steps don't delimit a
+    /// segment of a single path.
+    fn try_rewrite_segment(
+        &mut self,
+        begin: &Self::StepHandle,
+        end: &Self::StepHandle,
+        new_segment: Vec<Handle>,
+    ) -> HandleGraphResult<(Self::StepHandle, Self::StepHandle)>;
 
+    /// Like `try_rewrite_segment`, but panics on failure.
     fn rewrite_segment(
         &mut self,
         begin: &Self::StepHandle,
         end: &Self::StepHandle,
         new_segment: Vec<Handle>,
-    ) -> (Self::StepHandle, Self::StepHandle);
+    ) -> (Self::StepHandle, Self::StepHandle) {
+        match self.try_rewrite_segment(begin, end, new_segment) {
+            Ok(steps) => steps,
+            Err(err) => panic!("{}", err),
+        }
+    }
 
     /// Returns an iterator over all path identifiers in the graph
     fn paths_iter<'a>(
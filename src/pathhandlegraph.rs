@@ -1,9 +1,11 @@
 pub mod embedded_paths;
+pub mod metadata;
 pub mod occurrences;
 pub mod path;
 pub mod step;
 
 pub use self::embedded_paths::*;
+pub use self::metadata::*;
 pub use self::occurrences::*;
 pub use self::path::*;
 pub use self::step::StepHandle;
@@ -1,3 +1,5 @@
+use rayon::prelude::*;
+
 use crate::handle::Handle;
 
 use super::{PathBase, PathId, PathRef, PathRefMut, StepHandle, StepUpdate};
@@ -8,6 +10,14 @@ pub trait AllPathIds: Sized {
     fn all_path_ids(self) -> Self::PathIds;
 }
 
+/// Access all the path IDs in the graph as a parallel iterator, e.g.
+/// to process each path on a separate thread.
+pub trait AllPathIdsPar: Sized {
+    type PathIdsPar: ParallelIterator<Item = PathId>;
+
+    fn all_path_ids_par(self) -> Self::PathIdsPar;
+}
+
 pub trait PathNames: Sized {
     type PathName: Iterator<Item = u8>;
 
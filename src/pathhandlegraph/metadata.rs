@@ -0,0 +1,153 @@
+use fnv::FnvHashMap;
+
+use super::{EmbeddedPaths, PathId};
+
+/// The sample, haplotype, and contig that a path belongs to, along
+/// with the range of the contig that the path covers, if known.
+///
+/// These correspond to the fields of GFA 1.1 W-lines, and are stored
+/// in path names using the PanSN convention, as
+/// `sample#haplotype#contig`, optionally followed by `[start-end]`.
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct PanSN {
+    pub sample: Vec<u8>,
+    pub haplotype: usize,
+    pub contig: Vec<u8>,
+    pub subrange: Option<(usize, usize)>,
+}
+
+impl PanSN {
+    /// Parse a PanSN path name, returning `None` if the name doesn't
+    /// follow the convention.
+    pub fn parse(name: &[u8]) -> Option<Self> {
+        let (name, subrange) = match name.last() {
+            Some(b']') => {
+                let (name, start, end) = split_subrange(name)?;
+                (name, Some((start, end)))
+            }
+            _ => (name, None),
+        };
+
+        let mut fields = name.splitn(3, |&b| b == b'#');
+        let sample = fields.next().filter(|s| !s.is_empty())?;
+        let haplotype = parse_usize(fields.next()?)?;
+        let contig = fields.next().filter(|s| !s.is_empty())?;
+
+        Some(PanSN {
+            sample: sample.to_vec(),
+            haplotype,
+            contig: contig.to_vec(),
+            subrange,
+        })
+    }
+
+    /// Build the PanSN path name for this path.
+    pub fn to_name(&self) -> Vec<u8> {
+        let mut name = self.sample.clone();
+        name.push(b'#');
+        name.extend(self.haplotype.to_string().bytes());
+        name.push(b'#');
+        name.extend_from_slice(&self.contig);
+        if let Some((start, end)) = self.subrange {
+            name.extend(format!("[{}-{}]", start, end).bytes());
+        }
+        name
+    }
+}
+
+/// Split a path name that ends with a subrange, as `name[start-end]`,
+/// into the name and the range. Returns `None` if the name doesn't
+/// end with a valid subrange.
+pub fn split_subrange(name: &[u8]) -> Option<(&[u8], usize, usize)> {
+    let rest = name.strip_suffix(b"]")?;
+    let open = rest.iter().rposition(|&b| b == b'[')?;
+    let range = &rest[open + 1..];
+    let dash = range.iter().position(|&b| b == b'-')?;
+    let start = parse_usize(&range[..dash])?;
+    let end = parse_usize(&range[dash + 1..])?;
+    Some((&rest[..open], start, end))
+}
+
+fn parse_usize(bytes: &[u8]) -> Option<usize> {
+    std::str::from_utf8(bytes).ok()?.parse().ok()
+}
+
+/// Structured access to the sample, haplotype, contig, and subrange
+/// of paths, e.g. paths imported from GFA W-lines.
+pub trait PathMetadata: Sized {
+    type PathIds: Iterator<Item = PathId>;
+
+    /// The IDs of the paths that have metadata.
+    fn paths_with_metadata(self) -> Self::PathIds;
+
+    /// Get the metadata of the path, or `None` if the path has none.
+    fn get_path_metadata(self, id: PathId) -> Option<PanSN>;
+
+    /// Group the paths that have metadata by their sample.
+    fn paths_by_sample(self) -> FnvHashMap<Vec<u8>, Vec<PathId>>
+    where
+        Self: Copy,
+    {
+        let mut samples: FnvHashMap<Vec<u8>, Vec<PathId>> =
+            FnvHashMap::default();
+
+        for id in self.paths_with_metadata() {
+            if let Some(meta) = self.get_path_metadata(id) {
+                samples.entry(meta.sample).or_default().push(id);
+            }
+        }
+
+        samples.values_mut().for_each(|ids| ids.sort());
+
+        samples
+    }
+}
+
+/// The metadata of an embedded path is read from its name, which is
+/// where W-lines store it on import, so it follows the path through
+/// removals and renumbering. Any path with a PanSN name has metadata,
+/// whether or not it was loaded from a W-line.
+impl<G> PathMetadata for G
+where
+    G: EmbeddedPaths + Copy,
+{
+    type PathIds = std::vec::IntoIter<PathId>;
+
+    fn paths_with_metadata(self) -> Self::PathIds {
+        self.all_path_ids()
+            .filter(|&id| self.get_path_metadata(id).is_some())
+            .collect::<Vec<_>>()
+            .into_iter()
+    }
+
+    fn get_path_metadata(self, id: PathId) -> Option<PanSN> {
+        let name = self.get_path_name(id)?.collect::<Vec<_>>();
+        PanSN::parse(&name)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn pansn_names() {
+        let pansn = PanSN::parse(b"HG002#1#chr20").unwrap();
+        assert_eq!(pansn.sample, b"HG002");
+        assert_eq!(pansn.haplotype, 1);
+        assert_eq!(pansn.contig, b"chr20");
+        assert_eq!(pansn.subrange, None);
+        assert_eq!(pansn.to_name(), b"HG002#1#chr20");
+
+        let pansn = PanSN::parse(b"grch38#0#chr1#alt[100-2000]").unwrap();
+        assert_eq!(pansn.contig, b"chr1#alt");
+        assert_eq!(pansn.subrange, Some((100, 2000)));
+        assert_eq!(pansn.to_name(), b"grch38#0#chr1#alt[100-2000]");
+
+        assert_eq!(PanSN::parse(b"path1"), None);
+        assert_eq!(PanSN::parse(b"HG002#chr20"), None);
+        assert_eq!(PanSN::parse(b"HG002#a#chr20"), None);
+        assert_eq!(PanSN::parse(b"#1#chr20"), None);
+        assert_eq!(PanSN::parse(b"HG002#1#chr20[100]"), None);
+    }
+}
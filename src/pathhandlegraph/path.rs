@@ -74,8 +74,18 @@ pub trait PathRef: PathBase {
 
     fn circular(self) -> bool;
 
+    /// Return the first step on the path.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the path is empty.
     fn first_step(self) -> Self::Step;
 
+    /// Return the last step on the path.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the path is empty.
     fn last_step(self) -> Self::Step;
 
     fn next_step(self, step: Self::Step) -> Option<Self::Step>;
@@ -1,5 +1,20 @@
+use std::num::NonZeroUsize;
+
+use crate::packed::OneBasedIndex;
+
 use super::path::PathId;
 
+/// The index of a step on a path, as used in the `StepUpdate`s of
+/// every graph implementation. The index is one-based, so that the
+/// null index can mark the ends of a path; the zero-based index is
+/// the one used by `PathStep::Step`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct PathStepIx(Option<NonZeroUsize>);
+
+crate::impl_space_usage_stack_newtype!(PathStepIx);
+
+crate::impl_one_based_index!(PathStepIx);
+
 /// A step along a path; the path context is implicit.
 #[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
 pub enum PathStep {
@@ -35,3 +50,20 @@ pub struct StepHandle {
     path: PathId,
     step: PathStep,
 }
+
+impl StepHandle {
+    #[inline]
+    pub fn new(path: PathId, step: PathStep) -> Self {
+        StepHandle { path, step }
+    }
+
+    #[inline]
+    pub fn path_id(&self) -> PathId {
+        self.path
+    }
+
+    #[inline]
+    pub fn step(&self) -> PathStep {
+        self.step
+    }
+}
@@ -0,0 +1,675 @@
+use std::cmp::Reverse;
+use std::collections::BinaryHeap;
+use std::hash::Hash;
+
+use fnv::{FnvHashMap, FnvHashSet};
+
+use crate::{
+    handle::{Direction, Edge, Handle, NodeId},
+    handlegraph::*,
+    mutablehandlegraph::AdditiveHandleGraph,
+    pathgraph::PathHandleGraph,
+    pathhandlegraph::split_subrange,
+};
+
+/// How far to expand a subgraph beyond the nodes it was built from,
+/// following the edges of the graph in both directions.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SubgraphContext {
+    /// Add the nodes that are at most this many edges away.
+    Steps(usize),
+    /// Add the nodes that are less than this many bases away, where
+    /// the distance to a node is the total length of the nodes
+    /// between it and the original nodes.
+    Bases(usize),
+}
+
+/// Build the name of the subpath that covers the bases from `start`
+/// up to `end` of the path `name`, as `name[start-end]`. If the path
+/// is itself a subpath, the new range is relative to its original
+/// path.
+pub fn subpath_name(name: &[u8], start: usize, end: usize) -> Vec<u8> {
+    let (name, offset) = match split_subrange(name) {
+        Some((name, offset, _)) => (name, offset),
+        None => (name, 0),
+    };
+
+    let mut result = name.to_vec();
+    result.extend(format!("[{}-{}]", offset + start, offset + end).bytes());
+    result
+}
+
+/// Extract the subgraph around the bases from `start` up to `end` on
+/// the path named `path_name`, expanded by `context`, and add it to
+/// `subgraph`, which would usually be empty.
+///
+/// The subgraph contains the nodes of the steps that overlap the
+/// range, the nodes added by the context, and all edges between
+/// them. Every path in `graph` that visits those nodes is split into
+/// the runs of consecutive steps on them, each of which becomes a
+/// linear path named using `subpath_name`, with the range of bases
+/// it covers on the original path. Paths that are entirely contained
+/// in the subgraph are copied as they are.
+///
+/// The subgraph is passed in, rather than created, so that the
+/// caller can set it up to match `graph`, e.g. with the same
+/// `SequenceEncoding` when extracting from a `PackedGraph`.
+///
+/// Returns `None`, without changing `subgraph`, if there is no path
+/// with the given name.
+pub fn extract_path_range<G, H>(
+    graph: &G,
+    path_name: &[u8],
+    start: usize,
+    end: usize,
+    context: SubgraphContext,
+    subgraph: &mut H,
+) -> Option<()>
+where
+    G: PathHandleGraph,
+    G::PathHandle: Copy + Eq + Hash,
+    for<'a> &'a G: HandleGraphRef,
+    H: AdditiveHandleGraph + PathHandleGraph,
+{
+    let path = graph.name_to_path_handle(path_name)?;
+    let mut nodes = path_range_nodes(graph, &path, start, end);
+    expand_context(graph, &mut nodes, context);
+
+    add_induced_subgraph(graph, &nodes, subgraph);
+    add_path_fragments(graph, &nodes, subgraph);
+    Some(())
+}
+
+/// Extract the subgraph around the `seeds` nodes, expanded by
+/// `context`, containing those nodes and all edges between them, and
+/// add it to `subgraph`, as in `extract_path_range`. Node IDs are
+/// preserved, and seeds that aren't in the graph are ignored. The
+/// paths of the graph are not included, use
+/// `extract_subgraph_with_paths` to include them.
+pub fn extract_subgraph<G, H, I>(
+    graph: &G,
+    seeds: I,
+    context: SubgraphContext,
+    subgraph: &mut H,
+) where
+    for<'a> &'a G: HandleGraphRef,
+    H: AdditiveHandleGraph,
+    I: IntoIterator<Item = NodeId>,
+{
+    let nodes = seed_nodes(graph, seeds, context);
+    add_induced_subgraph(graph, &nodes, subgraph);
+}
+
+/// Extract the subgraph around the `seeds` nodes, like
+/// `extract_subgraph`, and cut the paths that visit the nodes of the
+/// subgraph into subpaths, as in `extract_path_range`.
+pub fn extract_subgraph_with_paths<G, H, I>(
+    graph: &G,
+    seeds: I,
+    context: SubgraphContext,
+    subgraph: &mut H,
+) where
+    G: PathHandleGraph,
+    G::PathHandle: Copy + Eq + Hash,
+    for<'a> &'a G: HandleGraphRef,
+    H: AdditiveHandleGraph + PathHandleGraph,
+    I: IntoIterator<Item = NodeId>,
+{
+    let nodes = seed_nodes(graph, seeds, context);
+    add_induced_subgraph(graph, &nodes, subgraph);
+    add_path_fragments(graph, &nodes, subgraph);
+}
+
+fn seed_nodes<G, I>(
+    graph: &G,
+    seeds: I,
+    context: SubgraphContext,
+) -> FnvHashSet<NodeId>
+where
+    for<'a> &'a G: HandleGraphRef,
+    I: IntoIterator<Item = NodeId>,
+{
+    let mut nodes = seeds
+        .into_iter()
+        .filter(|&id| graph.has_node(id))
+        .collect::<FnvHashSet<_>>();
+    expand_context(graph, &mut nodes, context);
+    nodes
+}
+
+/// Find the nodes of the steps that overlap the range of bases on
+/// the path, using the positional queries of the graph.
+fn path_range_nodes<G>(
+    graph: &G,
+    path: &G::PathHandle,
+    start: usize,
+    end: usize,
+) -> FnvHashSet<NodeId>
+where
+    G: PathHandleGraph,
+    for<'a> &'a G: HandleGraphRef,
+{
+    let mut nodes = FnvHashSet::default();
+
+    let end = end.min(graph.path_bases_len(path).unwrap_or(0));
+    if start >= end {
+        return nodes;
+    }
+
+    let mut step = match graph.step_at_position(path, start) {
+        Some(step) => step,
+        None => return nodes,
+    };
+    let mut pos = match graph.position_of_step(&step) {
+        Some(pos) => pos,
+        None => return nodes,
+    };
+
+    while pos < end {
+        let handle = match graph.handle_of_step(&step) {
+            Some(handle) => handle,
+            None => break,
+        };
+        nodes.insert(handle.id());
+        pos += graph.node_len(handle);
+        step = graph.next_step(&step);
+    }
+
+    nodes
+}
+
+/// Add the nodes within `context` of `nodes` to the set.
+fn expand_context<G>(
+    graph: &G,
+    nodes: &mut FnvHashSet<NodeId>,
+    context: SubgraphContext,
+) where
+    for<'a> &'a G: HandleGraphRef,
+{
+    // Both kinds of context are the shortest distance from the
+    // original nodes, where leaving a node costs one step, or its
+    // length in bases; leaving one of the original nodes is free
+    let (max_dist, by_bases) = match context {
+        SubgraphContext::Steps(0) | SubgraphContext::Bases(0) => return,
+        SubgraphContext::Steps(n) => (n, false),
+        SubgraphContext::Bases(n) => (n - 1, true),
+    };
+
+    let mut dists: FnvHashMap<NodeId, usize> =
+        nodes.iter().map(|&id| (id, 0)).collect();
+    let mut queue: BinaryHeap<Reverse<(usize, NodeId)>> =
+        nodes.iter().map(|&id| Reverse((0, id))).collect();
+
+    while let Some(Reverse((dist, id))) = queue.pop() {
+        if dists.get(&id).is_some_and(|&d| d < dist) {
+            continue;
+        }
+
+        let handle = Handle::pack(id, false);
+        let cost = if !by_bases {
+            1
+        } else if nodes.contains(&id) {
+            0
+        } else {
+            graph.node_len(handle)
+        };
+
+        let next_dist = dist + cost;
+        if next_dist > max_dist {
+            continue;
+        }
+
+        for &dir in [Direction::Left, Direction::Right].iter() {
+            for other in graph.neighbors(handle, dir) {
+                let other = other.id();
+                if !graph.has_node(other)
+                    || dists.get(&other).is_some_and(|&d| d <= next_dist)
+                {
+                    continue;
+                }
+                dists.insert(other, next_dist);
+                queue.push(Reverse((next_dist, other)));
+            }
+        }
+    }
+
+    nodes.extend(dists.keys());
+}
+
+/// Add the subgraph induced by the nodes to `subgraph`, with the
+/// same node IDs.
+fn add_induced_subgraph<G, H>(
+    graph: &G,
+    nodes: &FnvHashSet<NodeId>,
+    subgraph: &mut H,
+) where
+    for<'a> &'a G: HandleGraphRef,
+    H: AdditiveHandleGraph,
+{
+    let mut node_ids = nodes.iter().copied().collect::<Vec<_>>();
+    node_ids.sort();
+
+    for &id in node_ids.iter() {
+        let seq = graph.sequence(Handle::pack(id, false));
+        subgraph.create_handle(&seq, id);
+    }
+
+    let mut edges = node_edges(graph, &node_ids)
+        .into_iter()
+        .filter(|Edge(l, r)| nodes.contains(&l.id()) && nodes.contains(&r.id()))
+        .collect::<Vec<_>>();
+    edges.sort();
+    edges.dedup();
+
+    for edge in edges {
+        subgraph.create_edge(edge);
+    }
+}
+
+/// All edges on either side of the nodes, in their canonical
+/// orientation. Only the forward handles of the nodes are used.
+fn node_edges<G>(graph: &G, node_ids: &[NodeId]) -> Vec<Edge>
+where
+    for<'a> &'a G: HandleGraphRef,
+{
+    let mut edges = Vec::new();
+    for &id in node_ids.iter() {
+        let handle = Handle::pack(id, false);
+        for right in graph.neighbors(handle, Direction::Right) {
+            edges.push(Edge::edge_handle(handle, right));
+        }
+        for left in graph.neighbors(handle, Direction::Left) {
+            edges.push(Edge::edge_handle(left, handle));
+        }
+    }
+    edges
+}
+
+/// The runs of consecutive steps of the path that are on the nodes,
+/// as the range of bases each run covers on the path, and its steps.
+fn path_fragments<G>(
+    graph: &G,
+    path: &G::PathHandle,
+    nodes: &FnvHashSet<NodeId>,
+) -> Vec<(usize, usize, Vec<Handle>)>
+where
+    G: PathHandleGraph,
+    for<'a> &'a G: HandleGraphRef,
+{
+    let mut fragments = Vec::new();
+    let mut current: Option<(usize, usize, Vec<Handle>)> = None;
+    let mut offset = 0;
+
+    for step in graph.steps_iter(path) {
+        let handle = match graph.handle_of_step(&step) {
+            Some(handle) => handle,
+            None => continue,
+        };
+        let len = graph.node_len(handle);
+
+        if nodes.contains(&handle.id()) {
+            let fragment =
+                current.get_or_insert_with(|| (offset, offset, Vec::new()));
+            fragment.1 += len;
+            fragment.2.push(handle);
+        } else if let Some(fragment) = current.take() {
+            fragments.push(fragment);
+        }
+
+        offset += len;
+    }
+
+    fragments.extend(current);
+    fragments
+}
+
+fn add_path_fragments<G, H>(
+    graph: &G,
+    nodes: &FnvHashSet<NodeId>,
+    subgraph: &mut H,
+) where
+    G: PathHandleGraph,
+    G::PathHandle: Copy + Eq + Hash,
+    for<'a> &'a G: HandleGraphRef,
+    H: PathHandleGraph,
+{
+    let mut paths: FnvHashSet<G::PathHandle> = FnvHashSet::default();
+    for &id in nodes.iter() {
+        for step in graph.occurrences_iter(Handle::pack(id, false)) {
+            paths.insert(graph.path_handle_of_step(&step));
+        }
+    }
+
+    let mut paths = paths.into_iter().collect::<Vec<_>>();
+    paths.sort_by_cached_key(|path| graph.path_handle_to_name(path).to_vec());
+
+    for path in paths {
+        let name = graph.path_handle_to_name(&path);
+        let fragments = path_fragments(graph, &path, nodes);
+
+        let whole_path = match fragments.as_slice() {
+            [(start, _, steps)] => {
+                *start == 0 && steps.len() == graph.step_count(&path)
+            }
+            _ => false,
+        };
+
+        for (start, end, steps) in fragments {
+            let new_path = if whole_path {
+                subgraph.create_path_handle(name, graph.is_circular(&path))
+            } else {
+                let name = subpath_name(name, start, end);
+                subgraph.create_path_handle(&name, false)
+            };
+
+            for handle in steps {
+                subgraph.append_step(&new_path, handle);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::{hashgraph::HashGraph, packedgraph::PackedGraph};
+
+    fn hnd(x: u64) -> Handle {
+        Handle::pack(x, false)
+    }
+
+    // A bubble on nodes 2 and 3, with the `ref` path going through
+    // node 2, and `alt` through 3, and skipping node 5
+    fn bubble_graph<G>() -> G
+    where
+        G: Default + AdditiveHandleGraph + PathHandleGraph,
+    {
+        let mut graph: G = Default::default();
+        let seqs: [&[u8]; 6] =
+            [b"ACGT", b"GG", b"T", b"CCCA", b"TT", b"GATTACA"];
+        for (id, seq) in seqs.iter().enumerate() {
+            graph.create_handle(seq, id as u64 + 1);
+        }
+
+        let edges = [(1, 2), (1, 3), (2, 4), (3, 4), (4, 5), (5, 6), (4, 6)];
+        for &(l, r) in edges.iter() {
+            graph.create_edge(Edge(hnd(l), hnd(r)));
+        }
+
+        let paths: [(&[u8], &[u64]); 3] = [
+            (b"ref", &[1, 2, 4, 5, 6]),
+            (b"alt[100-200]", &[1, 3, 4, 6]),
+            (b"loop", &[2, 4, 6, 2, 4]),
+        ];
+        for (name, steps) in paths.iter() {
+            let path = graph.create_path_handle(name, false);
+            for &id in steps.iter() {
+                graph.append_step(&path, hnd(id));
+            }
+        }
+
+        graph
+    }
+
+    // The names and node IDs of each path
+    type PathSteps = Vec<(Vec<u8>, Vec<u64>)>;
+
+    fn subgraph_contents<G>(graph: &G) -> (Vec<u64>, PathSteps)
+    where
+        G: PathHandleGraph,
+        for<'a> &'a G: HandleGraphRef,
+    {
+        let mut nodes = graph
+            .all_handles()
+            .map(|h| u64::from(h.id()))
+            .collect::<Vec<_>>();
+        nodes.sort();
+
+        let mut paths = graph
+            .paths_iter()
+            .map(|path| {
+                let steps = graph
+                    .steps_iter(path)
+                    .map(|s| u64::from(graph.handle_of_step(&s).unwrap().id()))
+                    .collect::<Vec<_>>();
+                (graph.path_handle_to_name(path).to_vec(), steps)
+            })
+            .collect::<Vec<_>>();
+        paths.sort();
+
+        (nodes, paths)
+    }
+
+    fn check_path_ranges<G, H>()
+    where
+        G: Default + AdditiveHandleGraph + PathHandleGraph,
+        G::PathHandle: Copy + Eq + Hash,
+        for<'a> &'a G: HandleGraphRef,
+        H: Default + AdditiveHandleGraph + PathHandleGraph,
+        for<'a> &'a H: HandleGraphRef,
+    {
+        use SubgraphContext::{Bases, Steps};
+
+        let graph: G = bubble_graph();
+        let extract = |start: usize, end: usize, context: SubgraphContext| {
+            let mut subgraph = H::default();
+            extract_path_range(
+                &graph,
+                b"ref",
+                start,
+                end,
+                context,
+                &mut subgraph,
+            )
+            .unwrap();
+            subgraph_contents(&subgraph)
+        };
+
+        let name = |n: &str| n.as_bytes().to_vec();
+
+        // Bases 5 to 11 of `ref` are on nodes 2, 4, and 5
+        let (nodes, paths) = extract(5, 11, Steps(0));
+        assert_eq!(nodes, vec![2, 4, 5]);
+        assert_eq!(
+            paths,
+            vec![
+                (name("alt[105-109]"), vec![4]),
+                (name("loop[0-6]"), vec![2, 4]),
+                (name("loop[13-19]"), vec![2, 4]),
+                (name("ref[4-12]"), vec![2, 4, 5]),
+            ]
+        );
+
+        let mut subgraph = H::default();
+        extract_path_range(&graph, b"ref", 5, 11, Steps(0), &mut subgraph)
+            .unwrap();
+        let node_ids =
+            subgraph.all_handles().map(|h| h.id()).collect::<Vec<_>>();
+        let mut edges = node_edges(&subgraph, &node_ids);
+        edges.sort();
+        edges.dedup();
+        assert_eq!(edges, vec![Edge(hnd(2), hnd(4)), Edge(hnd(4), hnd(5))]);
+
+        // Paths that are entirely in the subgraph keep their names
+        let (nodes, paths) = extract(5, 11, Steps(1));
+        assert_eq!(nodes, vec![1, 2, 3, 4, 5, 6]);
+        assert_eq!(
+            paths,
+            vec![
+                (name("alt[100-200]"), vec![1, 3, 4, 6]),
+                (name("loop"), vec![2, 4, 6, 2, 4]),
+                (name("ref"), vec![1, 2, 4, 5, 6]),
+            ]
+        );
+
+        assert_eq!(extract(4, 6, Steps(1)).0, vec![1, 2, 4]);
+        assert_eq!(extract(4, 6, Steps(2)).0, vec![1, 2, 3, 4, 5, 6]);
+
+        // Nodes 1 and 4 are next to node 2, and nodes 3, 5, and 6 are
+        // 4 bases away, past node 1 or 4
+        assert_eq!(extract(4, 6, Bases(1)).0, vec![1, 2, 4]);
+        assert_eq!(extract(4, 6, Bases(4)).0, vec![1, 2, 4]);
+        assert_eq!(extract(4, 6, Bases(5)).0, vec![1, 2, 3, 4, 5, 6]);
+
+        // Ranges are clamped to the path
+        assert_eq!(extract(18, 100, Steps(0)).0, vec![6]);
+        assert!(extract(19, 100, Steps(0)).0.is_empty());
+        assert_eq!(extract(0, 1, Steps(0)).0, vec![1]);
+
+        let mut subgraph = H::default();
+        let missing =
+            extract_path_range(&graph, b"path", 0, 10, Steps(0), &mut subgraph);
+        assert!(missing.is_none());
+        assert_eq!(subgraph.node_count(), 0);
+    }
+
+    #[test]
+    fn subgraph_path_range() {
+        check_path_ranges::<HashGraph, HashGraph>();
+        check_path_ranges::<PackedGraph, PackedGraph>();
+        check_path_ranges::<HashGraph, PackedGraph>();
+        check_path_ranges::<PackedGraph, HashGraph>();
+    }
+
+    fn check_seed_subgraphs<G, H>()
+    where
+        G: Default + AdditiveHandleGraph + PathHandleGraph,
+        G::PathHandle: Copy + Eq + Hash,
+        for<'a> &'a G: HandleGraphRef,
+        H: Default + AdditiveHandleGraph + PathHandleGraph,
+        for<'a> &'a H: HandleGraphRef,
+    {
+        use SubgraphContext::{Bases, Steps};
+
+        let graph: G = bubble_graph();
+        let seeds = |ids: &[u64]| {
+            ids.iter().map(|&id| NodeId::from(id)).collect::<Vec<_>>()
+        };
+        let name = |n: &str| n.as_bytes().to_vec();
+
+        // Without paths, only the nodes and edges are copied
+        let mut subgraph = H::default();
+        extract_subgraph(&graph, seeds(&[5]), Steps(1), &mut subgraph);
+        let (nodes, paths) = subgraph_contents(&subgraph);
+        assert_eq!(nodes, vec![4, 5, 6]);
+        assert!(paths.is_empty());
+
+        let node_ids =
+            subgraph.all_handles().map(|h| h.id()).collect::<Vec<_>>();
+        let mut edges = node_edges(&subgraph, &node_ids);
+        edges.sort();
+        edges.dedup();
+        assert_eq!(
+            edges,
+            vec![
+                Edge(hnd(4), hnd(5)),
+                Edge(hnd(4), hnd(6)),
+                Edge(hnd(5), hnd(6))
+            ]
+        );
+
+        let mut subgraph = H::default();
+        extract_subgraph_with_paths(
+            &graph,
+            seeds(&[5]),
+            Steps(1),
+            &mut subgraph,
+        );
+        let (nodes, paths) = subgraph_contents(&subgraph);
+        assert_eq!(nodes, vec![4, 5, 6]);
+        assert_eq!(
+            paths,
+            vec![
+                (name("alt[105-116]"), vec![4, 6]),
+                (name("loop[15-19]"), vec![4]),
+                (name("loop[2-13]"), vec![4, 6]),
+                (name("ref[6-19]"), vec![4, 5, 6]),
+            ]
+        );
+
+        // Seeds that aren't in the graph are ignored
+        let mut subgraph = H::default();
+        extract_subgraph_with_paths(
+            &graph,
+            seeds(&[3, 100]),
+            Steps(0),
+            &mut subgraph,
+        );
+        let (nodes, paths) = subgraph_contents(&subgraph);
+        assert_eq!(nodes, vec![3]);
+        assert_eq!(paths, vec![(name("alt[104-105]"), vec![3])]);
+
+        let mut subgraph = H::default();
+        extract_subgraph(&graph, seeds(&[100]), Steps(3), &mut subgraph);
+        assert_eq!(subgraph.node_count(), 0);
+
+        // Nodes 2 and 3 are next to node 1, node 4 is 1 base away,
+        // past node 3, and nodes 5 and 6 are 5 bases away
+        let extract = |context: SubgraphContext| {
+            let mut subgraph = H::default();
+            extract_subgraph(&graph, seeds(&[1]), context, &mut subgraph);
+            subgraph_contents(&subgraph).0
+        };
+        assert_eq!(extract(Bases(1)), vec![1, 2, 3]);
+        assert_eq!(extract(Bases(2)), vec![1, 2, 3, 4]);
+        assert_eq!(extract(Bases(5)), vec![1, 2, 3, 4]);
+        assert_eq!(extract(Bases(6)), vec![1, 2, 3, 4, 5, 6]);
+    }
+
+    #[test]
+    fn subgraph_seed_nodes() {
+        check_seed_subgraphs::<HashGraph, HashGraph>();
+        check_seed_subgraphs::<PackedGraph, PackedGraph>();
+        check_seed_subgraphs::<HashGraph, PackedGraph>();
+        check_seed_subgraphs::<PackedGraph, HashGraph>();
+    }
+
+    #[test]
+    fn subgraph_keeps_encoding() {
+        use crate::packedgraph::SequenceEncoding;
+        use SubgraphContext::Steps;
+
+        let mut graph = PackedGraph::with_encoding(SequenceEncoding::Iupac);
+        let seqs: [&[u8]; 3] = [b"ACRYT", b"NNKM-", b"GATTACA"];
+        for (id, seq) in seqs.iter().enumerate() {
+            graph.create_handle(seq, id as u64 + 1);
+        }
+        graph.create_edge(Edge(hnd(1), hnd(2)));
+        graph.create_edge(Edge(hnd(2), hnd(3)));
+
+        let path = graph.create_path_handle(b"ref", false);
+        for id in 1..=3 {
+            graph.append_step(&path, hnd(id));
+        }
+
+        let mut subgraph = PackedGraph::with_encoding(graph.encoding());
+        extract_path_range(&graph, b"ref", 0, 10, Steps(0), &mut subgraph)
+            .unwrap();
+        assert_eq!(subgraph.encoding(), SequenceEncoding::Iupac);
+        assert_eq!(subgraph.sequence(hnd(1)), b"ACRYT");
+        assert_eq!(subgraph.sequence(hnd(2)), b"NNKM-");
+        let rev = subgraph.sequence_iter(hnd(2).flip()).collect::<Vec<_>>();
+        assert_eq!(rev, b"-KMNN");
+
+        let mut subgraph = PackedGraph::with_encoding(graph.encoding());
+        extract_subgraph(
+            &graph,
+            vec![NodeId::from(1)],
+            Steps(1),
+            &mut subgraph,
+        );
+        assert_eq!(subgraph.sequence(hnd(1)), b"ACRYT");
+        assert_eq!(subgraph.sequence(hnd(2)), b"NNKM-");
+    }
+
+    #[test]
+    fn subgraph_subpath_names() {
+        assert_eq!(subpath_name(b"chr6", 10, 20), b"chr6[10-20]");
+        assert_eq!(
+            subpath_name(b"GRCh38#0#chr6[29600000-33400000]", 5, 7),
+            b"GRCh38#0#chr6[29600005-29600007]"
+        );
+        assert_eq!(subpath_name(b"x[a-b]", 1, 2), b"x[a-b][1-2]");
+    }
+}
@@ -0,0 +1,150 @@
+use crate::{
+    handle::{Direction, Edge, Handle, NodeId},
+    handlegraph::*,
+};
+
+/// The kind of storage record an `OrphanRecord` issue refers to.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum RecordKind {
+    Node,
+    Edge,
+    Occurrence,
+}
+
+/// A single inconsistency found when validating a graph. `P` is the
+/// path identifier type of the graph.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ValidationIssue<P> {
+    /// The edge leads to a node that doesn't exist.
+    DanglingEdge(Edge),
+    /// The edge is only stored on one of the two nodes it connects.
+    AsymmetricEdge(Edge),
+    /// The node ID maps to a node record that doesn't exist, or that
+    /// is shared with another node ID.
+    InvalidNodeRecord(NodeId),
+    /// The path has a step on a node that doesn't exist.
+    MissingStepNode { path: P, handle: Handle },
+    /// Two consecutive steps on the path aren't connected by an edge.
+    MissingPathEdge { path: P, edge: Edge },
+    /// The path has a step on the handle, but the step isn't in the
+    /// occurrences of the handle's node.
+    MissingOccurrence { path: P, handle: Handle },
+    /// The node has an occurrence on the path that doesn't correspond
+    /// to a step on the node.
+    InvalidOccurrence { path: P, node: NodeId },
+    /// A record that isn't reachable from the rest of the graph, but
+    /// also hasn't been marked as removed. The index is the position
+    /// of the record in its storage, starting from 0.
+    OrphanRecord { kind: RecordKind, index: usize },
+}
+
+/// The result of validating a graph, containing every inconsistency
+/// that was found. A graph that has only been built and modified
+/// through the graph traits should produce an empty report.
+///
+/// The checks that only use the graph traits are provided as
+/// methods, so that other graph implementations can build their own
+/// reports.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ValidationReport<P> {
+    pub issues: Vec<ValidationIssue<P>>,
+}
+
+impl<P> Default for ValidationReport<P> {
+    fn default() -> Self {
+        Self { issues: Vec::new() }
+    }
+}
+
+impl<P> ValidationReport<P> {
+    pub fn new() -> Self {
+        Default::default()
+    }
+
+    /// Returns `true` if no inconsistencies were found.
+    #[inline]
+    pub fn is_valid(&self) -> bool {
+        self.issues.is_empty()
+    }
+
+    #[inline]
+    pub fn push(&mut self, issue: ValidationIssue<P>) {
+        self.issues.push(issue);
+    }
+
+    /// Check that every edge in the graph, as reported by
+    /// `neighbors`, leads to a node in the graph, and can be found
+    /// from both of the handles it connects.
+    pub fn check_edges<G>(&mut self, graph: G)
+    where
+        G: AllHandles + HandleNeighbors + Copy,
+    {
+        for handle in graph.all_handles() {
+            for &left in [handle, handle.flip()].iter() {
+                for right in graph.neighbors(left, Direction::Right) {
+                    let edge = Edge(left, right);
+                    if !graph.has_node(right.id()) {
+                        self.push(ValidationIssue::DanglingEdge(edge));
+                    } else if !graph
+                        .neighbors(right, Direction::Left)
+                        .any(|h| h == left)
+                    {
+                        self.push(ValidationIssue::AsymmetricEdge(edge));
+                    }
+                }
+            }
+        }
+    }
+
+    /// Check that every step of a path is on a node in the graph, and
+    /// that each pair of consecutive steps is connected by an edge,
+    /// in either orientation. On circular paths, the last step must
+    /// also be connected to the first.
+    pub fn check_path_steps<G, I>(
+        &mut self,
+        graph: G,
+        path: P,
+        circular: bool,
+        steps: I,
+    ) where
+        G: AllHandles + HandleNeighbors + Copy,
+        P: Copy,
+        I: IntoIterator<Item = Handle>,
+    {
+        let mut first: Option<Handle> = None;
+        let mut prev: Option<Handle> = None;
+
+        for handle in steps {
+            if !graph.has_node(handle.id()) {
+                self.push(ValidationIssue::MissingStepNode { path, handle });
+                prev = None;
+                continue;
+            }
+
+            if let Some(prev) = prev {
+                self.check_path_edge(graph, path, Edge(prev, handle));
+            }
+
+            first = first.or(Some(handle));
+            prev = Some(handle);
+        }
+
+        if circular {
+            if let (Some(last), Some(first)) = (prev, first) {
+                self.check_path_edge(graph, path, Edge(last, first));
+            }
+        }
+    }
+
+    fn check_path_edge<G>(&mut self, graph: G, path: P, edge: Edge)
+    where
+        G: HandleNeighbors + Copy,
+    {
+        let Edge(left, right) = edge;
+        if !graph.has_edge(left, right)
+            && !graph.has_edge(right.flip(), left.flip())
+        {
+            self.push(ValidationIssue::MissingPathEdge { path, edge });
+        }
+    }
+}
@@ -1,9 +1,10 @@
 use handlegraph::{
     handle::{Direction, Edge, Handle, NodeId},
     handlegraph::*,
-    hashgraph::{HashGraph, PathStep},
+    hashgraph::{HashGraph, PathId, PathRemoval, StepHandle},
     mutablehandlegraph::*,
     pathgraph::PathHandleGraph,
+    pathhandlegraph::step::PathStep,
 };
 
 static H1: Handle = Handle::from_integer(2);
@@ -173,6 +174,59 @@ fn graph_neighbors_iter() {
     assert_eq!(None, iter.next());
 }
 
+#[test]
+fn graph_path_step_navigation() {
+    use PathStep::*;
+
+    let mut graph = path_graph();
+
+    let p1 = graph.create_path_handle(b"path-1", false);
+    let empty = graph.create_path_handle(b"empty", false);
+    for &h in [H1, H2, H5].iter() {
+        graph.append_step(&p1, h);
+    }
+
+    let step = |s: PathStep| StepHandle::new(p1, s);
+
+    assert_eq!(graph.path_begin(&p1), step(Step(0)));
+    assert_eq!(graph.path_back(&p1), step(Step(2)));
+    assert_eq!(graph.path_front_end(&p1), step(Before));
+    assert_eq!(graph.path_end(&p1), step(After));
+
+    assert_eq!(graph.next_step(&step(Before)), step(Step(0)));
+    assert_eq!(graph.next_step(&step(Step(1))), step(Step(2)));
+    assert_eq!(graph.next_step(&step(Step(2))), step(After));
+    assert_eq!(graph.next_step(&step(After)), step(After));
+
+    assert_eq!(graph.previous_step(&step(After)), step(Step(2)));
+    assert_eq!(graph.previous_step(&step(Step(1))), step(Step(0)));
+    assert_eq!(graph.previous_step(&step(Step(0))), step(Before));
+    assert_eq!(graph.previous_step(&step(Before)), step(Before));
+
+    assert!(graph.has_next_step(&step(Step(1))));
+    assert!(!graph.has_next_step(&step(Step(2))));
+    assert!(graph.has_previous_step(&step(Step(1))));
+    assert!(!graph.has_previous_step(&step(Step(0))));
+
+    assert_eq!(graph.handle_of_step(&step(Step(1))), Some(H2));
+    assert_eq!(graph.handle_of_step(&step(Before)), None);
+    assert_eq!(graph.handle_of_step(&step(Step(3))), None);
+
+    // Position 0 is the first base of the first step, like every
+    // other position maps to the step that covers it
+    assert_eq!(graph.step_at_position(&p1, 0), Some(step(Step(0))));
+    assert_eq!(graph.step_at_position(&p1, 2), Some(step(Step(2))));
+    assert_eq!(graph.step_at_position(&p1, 3), Some(step(After)));
+    assert_eq!(
+        graph.step_at_position(&empty, 0),
+        Some(StepHandle::new(empty, After))
+    );
+
+    // The first and last steps of an empty path are its ends
+    assert_eq!(graph.path_begin(&empty), StepHandle::new(empty, After));
+    assert_eq!(graph.path_back(&empty), StepHandle::new(empty, Before));
+}
+
 #[test]
 fn graph_all_handles_iter() {
     let graph = path_graph();
@@ -313,8 +367,8 @@ fn append_prepend_path() {
     // Rewrite the segment 1 -> 6 in path 2 with the segment
     // 6 -> 4 -> 5 -> 3 -> 1 -> 2
     graph.rewrite_segment(
-        &PathStep::Step(1, 0),
-        &PathStep::Step(1, 1),
+        &StepHandle::new(p2, PathStep::Step(0)),
+        &StepHandle::new(p2, PathStep::Step(1)),
         vec![H6, H4, H5, H3, H1, H2],
     );
 
@@ -326,10 +380,10 @@ fn append_prepend_path() {
     test_node(&graph, 5, Some(&2), Some(&2));
     test_node(&graph, 6, Some(&3), Some(&0));
 
-    // Rewrite the segment Front(_) .. 5 in path 1 with the segment [2, 3]
+    // Rewrite the segment Before .. 5 in path 1 with the segment [2, 3]
     graph.rewrite_segment(
-        &PathStep::Front(0),
-        &PathStep::Step(0, 2),
+        &StepHandle::new(p1, PathStep::Before),
+        &StepHandle::new(p1, PathStep::Step(2)),
         vec![H2, H3],
     );
 
@@ -340,8 +394,12 @@ fn append_prepend_path() {
     test_node(&graph, 5, None, Some(&2));
     test_node(&graph, 6, Some(&2), Some(&0));
 
-    // Rewrite the segment 3 .. End(_) in path 2 with the segment [1]
-    graph.rewrite_segment(&PathStep::Step(1, 3), &PathStep::End(1), vec![H1]);
+    // Rewrite the segment 3 .. After in path 2 with the segment [1]
+    graph.rewrite_segment(
+        &StepHandle::new(p2, PathStep::Step(3)),
+        &StepHandle::new(p2, PathStep::After),
+        vec![H1],
+    );
 
     // Now path 2 is 6 -> 4 -> 5 -> 1
     test_node(&graph, 1, None, Some(&3));
@@ -359,7 +417,7 @@ fn append_prepend_path() {
 
 #[test]
 fn graph_path_steps_iter() {
-    use handlegraph::hashgraph::PathStep::*;
+    use PathStep::*;
 
     let mut graph = path_graph();
 
@@ -371,10 +429,10 @@ fn graph_path_steps_iter() {
 
     let mut iter = graph.steps_iter(&p1);
 
-    assert_eq!(Some(Step(p1, 0)), iter.next());
-    assert_eq!(Some(Step(p1, 1)), iter.next());
-    assert_eq!(Some(Step(p1, 2)), iter.next());
-    assert_eq!(Some(Step(p1, 3)), iter.next());
+    assert_eq!(Some(StepHandle::new(p1, Step(0))), iter.next());
+    assert_eq!(Some(StepHandle::new(p1, Step(1))), iter.next());
+    assert_eq!(Some(StepHandle::new(p1, Step(2))), iter.next());
+    assert_eq!(Some(StepHandle::new(p1, Step(3))), iter.next());
     assert_eq!(None, iter.next());
 }
 
@@ -453,3 +511,593 @@ fn graph_divide_handle() {
 
     assert_eq!(expected_handles, handles);
 }
+
+#[test]
+fn fallible_construction() {
+    use handlegraph::error::HandleGraphError;
+
+    let mut graph = HashGraph::new();
+    graph.append_handle(b"ABCD");
+    graph.append_handle(b"EFG");
+
+    let id = |x: u64| NodeId::from(x);
+
+    assert_eq!(
+        graph.try_create_handle(b"A", 1u64),
+        Err(HandleGraphError::DuplicateNode(id(1)))
+    );
+    assert_eq!(
+        graph.try_create_handle(b"A", 0u64),
+        Err(HandleGraphError::NullNodeId)
+    );
+    assert_eq!(
+        graph.try_create_handle(b"", 3u64),
+        Err(HandleGraphError::EmptySequence(id(3)))
+    );
+    assert_eq!(graph.sequence(H1).as_slice(), b"ABCD");
+
+    assert_eq!(
+        graph.try_create_edge(Edge(H2, H4)),
+        Err(HandleGraphError::MissingNode(id(4)))
+    );
+    assert_eq!(graph.try_create_edge(Edge(H1, H2)), Ok(()));
+    assert!(graph.has_edge(H1, H2));
+
+    let invalid =
+        |offset| HandleGraphError::InvalidOffset { handle: H1, offset };
+    assert_eq!(graph.try_divide_handle(H1, vec![0]), Err(invalid(0)));
+    assert_eq!(graph.try_divide_handle(H1, vec![4]), Err(invalid(4)));
+    assert_eq!(graph.try_divide_handle(H1, vec![2, 1]), Err(invalid(1)));
+    assert_eq!(
+        graph.try_divide_handle(H4, vec![1]),
+        Err(HandleGraphError::MissingNode(id(4)))
+    );
+    assert_eq!(graph.node_count(), 2);
+
+    let handles = graph.try_divide_handle(H1, vec![1, 3]).unwrap();
+    assert_eq!(handles.len(), 3);
+    assert_eq!(graph.sequence(H1).as_slice(), b"A");
+
+    let path = graph.create_path_handle(b"path", false);
+    let missing = PathId(path.0 + 1);
+    let unknown = HandleGraphError::UnknownPath(missing);
+    assert_eq!(graph.try_append_step(&missing, H1), Err(unknown.clone()));
+    assert_eq!(graph.try_prepend_step(&missing, H1), Err(unknown.clone()));
+    assert_eq!(
+        graph.try_path_handle_to_name(&missing),
+        Err(unknown.clone())
+    );
+    assert_eq!(graph.try_destroy_path(&missing), Err(unknown));
+
+    let h9 = Handle::pack(9, false);
+    assert_eq!(
+        graph.try_append_step(&path, h9),
+        Err(HandleGraphError::MissingNode(id(9)))
+    );
+
+    let first = graph.try_append_step(&path, H1).unwrap();
+    let last = graph.try_append_step(&path, H2).unwrap();
+    let other = StepHandle::new(missing, PathStep::Step(0));
+    assert_eq!(
+        graph.try_rewrite_segment(&first, &other, vec![H1]),
+        Err(HandleGraphError::InvalidSegment {
+            begin: first,
+            end: other
+        })
+    );
+    assert_eq!(
+        graph.try_rewrite_segment(&last, &first, vec![H1]),
+        Err(HandleGraphError::InvalidSegment {
+            begin: last,
+            end: first
+        })
+    );
+    assert_eq!(
+        graph.try_rewrite_segment(&first, &last, vec![h9]),
+        Err(HandleGraphError::MissingNode(id(9)))
+    );
+    assert_eq!(graph.step_count(&path), 2);
+
+    assert_eq!(graph.try_path_handle_to_name(&path), Ok(&b"path"[..]));
+    assert_eq!(graph.try_destroy_path(&path), Ok(()));
+    assert_eq!(
+        graph.try_destroy_path(&path),
+        Err(HandleGraphError::UnknownPath(path))
+    );
+}
+
+#[test]
+fn validate_graph() {
+    use handlegraph::validation::{ValidationIssue as VI, ValidationReport};
+
+    let mut graph = HashGraph::new();
+    for seq in [b"GTCA", b"AAGT", b"CCTA", b"GGAT"].iter() {
+        graph.append_handle(&seq[..]);
+    }
+
+    graph.create_edge(Edge(H1, H2));
+    graph.create_edge(Edge(H2, H3.flip()));
+    graph.create_edge(Edge(H3.flip(), H4));
+
+    let p1 = graph.create_path_handle(b"path1", false);
+    for &h in [H1, H2, H3.flip(), H4].iter() {
+        graph.append_step(&p1, h);
+    }
+
+    let p2 = graph.create_path_handle(b"path2", false);
+    for &h in [H4.flip(), H3].iter() {
+        graph.append_step(&p2, h);
+    }
+
+    assert_eq!(graph.validate(), ValidationReport::new());
+
+    // An edge that's only stored on one of its nodes, and an edge to
+    // a node that doesn't exist
+    graph.get_node_mut(&H1.id()).unwrap().right_edges.push(H3);
+    graph.get_node_mut(&H4.id()).unwrap().right_edges.push(H5);
+
+    // A step across a missing edge
+    graph.append_step(&p2, H1);
+
+    // A node that's lost its occurrence on a path, and one with an
+    // occurrence on a path that doesn't exist
+    graph
+        .get_node_mut(&H2.id())
+        .unwrap()
+        .occurrences
+        .remove(&p1);
+    graph
+        .get_node_mut(&H3.id())
+        .unwrap()
+        .occurrences
+        .insert(PathId(5), 0);
+
+    let report = graph.validate();
+    assert!(!report.is_valid());
+
+    // The edges are checked in the order of `all_handles`
+    let (edge_issues, other_issues) = report.issues.split_at(2);
+    assert!(edge_issues.contains(&VI::AsymmetricEdge(Edge(H1, H3))));
+    assert!(edge_issues.contains(&VI::DanglingEdge(Edge(H4, H5))));
+
+    assert_eq!(
+        other_issues,
+        &[
+            VI::MissingOccurrence {
+                path: p1,
+                handle: H2
+            },
+            VI::MissingPathEdge {
+                path: p2,
+                edge: Edge(H3, H1)
+            },
+            VI::InvalidOccurrence {
+                path: PathId(5),
+                node: H3.id()
+            },
+        ]
+    );
+}
+
+#[test]
+fn sequence_random_access() {
+    let mut graph = HashGraph::new();
+    let h1 = graph.append_handle(b"GTCCACTTNGTGT");
+    let h2 = graph.append_handle(b"A");
+
+    for &handle in [h1, h1.flip(), h2, h2.flip()].iter() {
+        let seq = graph.sequence_iter(handle).collect::<Vec<_>>();
+        let len = seq.len();
+        assert_eq!(graph.node_len(handle), len);
+
+        for (ix, &base) in seq.iter().enumerate() {
+            assert_eq!(graph.base(handle, ix), base);
+        }
+
+        for start in 0..=len {
+            for sub_len in 0..=(len - start + 1) {
+                let expected = seq
+                    .iter()
+                    .skip(start)
+                    .take(sub_len)
+                    .copied()
+                    .collect::<Vec<_>>();
+                let iter = graph.subsequence_iter(handle, start, sub_len);
+                assert_eq!(iter.len(), expected.len());
+                assert_eq!(iter.collect::<Vec<_>>(), expected);
+                assert_eq!(graph.subsequence(handle, start, sub_len), expected);
+            }
+        }
+    }
+
+    assert_eq!(graph.subsequence(h1, 3, 6), b"CACTTN");
+    assert_eq!(graph.subsequence(h1.flip(), 3, 6), b"CNAAGT");
+    assert_eq!(graph.base(h1.flip(), 0), b'A');
+}
+
+fn removal_graph(path_removal: PathRemoval) -> HashGraph {
+    let mut graph = path_graph();
+    graph.path_removal = path_removal;
+    graph.create_edge(Edge(H2, H3));
+    graph.create_edge(Edge(H6, H1));
+
+    let paths: [(&[u8], bool, &[Handle]); 3] = [
+        (b"a", false, &[H1, H2, H5, H6]),
+        (b"b", false, &[H1, H3, H4, H6]),
+        (b"c", true, &[H1, H2, H5, H6]),
+    ];
+    for &(name, circular, steps) in paths.iter() {
+        let path = graph.create_path_handle(name, circular);
+        for &h in steps.iter() {
+            graph.append_step(&path, h);
+        }
+    }
+
+    graph
+}
+
+fn path_contents(graph: &HashGraph) -> Vec<(Vec<u8>, bool, Vec<Handle>)> {
+    let mut paths = graph
+        .paths
+        .values()
+        .map(|p| (p.name.to_vec(), p.is_circular, p.nodes.clone()))
+        .collect::<Vec<_>>();
+    paths.sort();
+    paths
+}
+
+#[test]
+fn remove_handles_and_edges() {
+    use handlegraph::validation::ValidationReport;
+
+    let mut graph = removal_graph(PathRemoval::DestroyPaths);
+
+    assert!(graph.remove_edge(Edge(H2, H3)));
+    assert!(!graph.has_edge(H2, H3));
+    assert!(!graph.remove_edge(Edge(H2, H3)));
+
+    // Paths that use the node are destroyed
+    assert!(graph.remove_handle(H2));
+    assert!(!graph.has_node(H2.id()));
+    assert!(!graph.remove_handle(H2));
+    assert_eq!(graph.path_count(), 1);
+    assert!(!graph.has_path(b"a"));
+    assert!(!graph.has_path(b"c"));
+    assert!(graph.has_path(b"b"));
+
+    let lefts = graph.neighbors(H5, Direction::Left).collect::<Vec<_>>();
+    let rights = graph.neighbors(H1, Direction::Right).collect::<Vec<_>>();
+    assert!(lefts.is_empty());
+    assert_eq!(rights, vec![H3]);
+    assert_eq!(graph.validate(), ValidationReport::new());
+
+    // Edges are removed in either orientation
+    assert!(graph.remove_edge(Edge(H6.flip(), H4.flip())));
+    assert!(!graph.has_path(b"b"));
+    assert_eq!(graph.path_count(), 0);
+
+    // A new path doesn't reuse the ID of an existing path
+    let p1 = graph.create_path_handle(b"x", false);
+    let p2 = graph.create_path_handle(b"y", false);
+    assert_ne!(p1, p2);
+
+    assert!(graph.remove_handle(H6));
+    assert_eq!(graph.max_node_id(), H5.id());
+    assert!(graph.remove_handle(H1));
+    assert_eq!(graph.min_node_id(), H3.id());
+    assert_eq!(graph.validate(), ValidationReport::new());
+
+    graph.clear_graph();
+    assert_eq!(graph.node_count(), 0);
+    assert_eq!(graph.path_count(), 0);
+    assert_eq!(graph.path_removal, PathRemoval::DestroyPaths);
+}
+
+#[test]
+fn remove_handles_and_edges_on_paths() {
+    use handlegraph::validation::ValidationReport;
+
+    // Nodes and edges on paths can't be removed
+    let mut graph = removal_graph(PathRemoval::Refuse);
+    let paths = path_contents(&graph);
+
+    assert!(!graph.remove_handle(H2));
+    assert!(!graph.remove_edge(Edge(H1, H2)));
+    assert!(!graph.remove_edge(Edge(H6, H1)));
+    assert!(graph.has_node(H2.id()));
+    assert!(graph.has_edge(H1, H2));
+    assert_eq!(path_contents(&graph), paths);
+
+    assert!(graph.remove_edge(Edge(H2, H3)));
+
+    // Paths are split around the removed node
+    let mut graph = removal_graph(PathRemoval::SplitPaths);
+    assert!(graph.remove_handle(H5));
+
+    let name = |n: &str| n.as_bytes().to_vec();
+    assert_eq!(
+        path_contents(&graph),
+        vec![
+            (name("a[0-2]"), false, vec![H1, H2]),
+            (name("a[3-4]"), false, vec![H6]),
+            (name("b"), false, vec![H1, H3, H4, H6]),
+            // The steps on either side of the origin of a circular
+            // path stay together
+            (name("c[3-6]"), false, vec![H6, H1, H2]),
+        ]
+    );
+    assert_eq!(graph.validate(), ValidationReport::new());
+
+    // A circular path that's cut once becomes a single linear path
+    let mut graph = removal_graph(PathRemoval::SplitPaths);
+    assert!(graph.remove_edge(Edge(H2, H5)));
+
+    assert_eq!(
+        path_contents(&graph),
+        vec![
+            (name("a[0-2]"), false, vec![H1, H2]),
+            (name("a[2-4]"), false, vec![H5, H6]),
+            (name("b"), false, vec![H1, H3, H4, H6]),
+            (name("c[2-6]"), false, vec![H5, H6, H1, H2]),
+        ]
+    );
+    assert_eq!(graph.validate(), ValidationReport::new());
+
+    // Splits that would reuse the name of another path are refused
+    let mut graph = removal_graph(PathRemoval::SplitPaths);
+    let other = graph.create_path_handle(b"a[0-2]", false);
+    graph.append_step(&other, H3);
+    let paths = path_contents(&graph);
+
+    assert!(!graph.remove_handle(H5));
+    assert!(!graph.remove_edge(Edge(H2, H5)));
+    assert!(graph.has_node(H5.id()));
+    assert_eq!(path_contents(&graph), paths);
+
+    // As are splits of two paths that would result in the same name
+    let mut graph = removal_graph(PathRemoval::SplitPaths);
+    let other = graph.create_path_handle(b"a[0-4]", false);
+    for &h in [H1, H2, H5, H6].iter() {
+        graph.append_step(&other, h);
+    }
+    let paths = path_contents(&graph);
+
+    assert!(!graph.remove_handle(H5));
+    assert_eq!(path_contents(&graph), paths);
+    assert_eq!(graph.validate(), ValidationReport::new());
+
+    // Removing the edge that closes a circular path makes it linear
+    let mut graph = removal_graph(PathRemoval::SplitPaths);
+    assert!(graph.remove_edge(Edge(H6, H1)));
+    assert!(graph.remove_edge(Edge(H3.flip(), H1.flip())));
+
+    assert_eq!(
+        path_contents(&graph),
+        vec![
+            (name("a"), false, vec![H1, H2, H5, H6]),
+            (name("b[0-1]"), false, vec![H1]),
+            (name("b[1-4]"), false, vec![H3, H4, H6]),
+            (name("c"), false, vec![H1, H2, H5, H6]),
+        ]
+    );
+    assert_eq!(graph.validate(), ValidationReport::new());
+}
+
+#[test]
+fn embedded_paths() {
+    use handlegraph::{
+        packed::OneBasedIndex,
+        pathhandlegraph::{
+            step::{PathStep as Step, PathStepIx},
+            EmbeddedMutablePath, EmbeddedPaths, MutEmbeddedPaths, PathId,
+            PathOccurrences, PathRef, PathRefMut, StepHandle, StepUpdate,
+        },
+        validation::ValidationReport,
+    };
+
+    let ix = |i: usize| PathStepIx::from_zero_based(i);
+
+    let mut graph = path_graph();
+    graph.create_edge(Edge(H6, H1));
+
+    let p1 = graph.create_path(b"path1", false);
+    let p2 = graph.create_path(b"path2", true);
+
+    {
+        let mut path = graph.get_path_mut(p1).unwrap();
+        path.append_step(H2);
+        path.append_step(H5);
+        path.prepend_step(H1);
+    }
+    {
+        let mut path = graph.get_path_mut(p2).unwrap();
+        for &h in [H1, H3, H4, H6].iter() {
+            path.append_step(h);
+        }
+    }
+
+    assert_eq!(graph.validate(), ValidationReport::new());
+
+    let path_handles = |graph: &HashGraph, id: PathId| {
+        let path = EmbeddedPaths::get_path(graph, id).unwrap();
+        path.steps().map(|(_, h)| h).collect::<Vec<_>>()
+    };
+
+    assert_eq!(EmbeddedPaths::path_count(&graph), 2);
+    assert_eq!(graph.lookup_path_id(b"path2"), Some(p2));
+    assert_eq!(
+        EmbeddedPaths::get_path_name(&graph, p1)
+            .unwrap()
+            .collect::<Vec<_>>(),
+        b"path1"
+    );
+    assert_eq!(path_handles(&graph, p1), vec![H1, H2, H5]);
+
+    let path = EmbeddedPaths::get_path(&graph, p2).unwrap();
+    assert!(path.circular());
+    assert_eq!(path.next_step(path.last_step()), Some((ix(0), H1)));
+    assert_eq!(path.prev_step(path.first_step()), Some((ix(3), H6)));
+
+    let steps_on = |graph: &HashGraph, h: Handle| {
+        let mut steps = graph.steps_on_handle(h).collect::<Vec<_>>();
+        steps.sort();
+        steps
+    };
+    let step = |path: PathId, ix: usize| StepHandle::new(path, Step::Step(ix));
+
+    assert_eq!(steps_on(&graph, H1), vec![step(p1, 0), step(p2, 0)]);
+    assert_eq!(steps_on(&graph, H5), vec![step(p1, 2)]);
+
+    // Nodes that are visited more than once have a step for each visit
+    graph.get_path_mut(p1).unwrap().append_step(H1);
+    assert_eq!(
+        steps_on(&graph, H1),
+        vec![step(p1, 0), step(p1, 3), step(p2, 0)]
+    );
+
+    {
+        let mut path = graph.get_path_mut(p1).unwrap();
+        assert_eq!(
+            path.remove_step(ix(0)),
+            Some(StepUpdate::Remove {
+                handle: H1,
+                step: ix(0)
+            })
+        );
+        assert_eq!(path.remove_step(ix(3)), None);
+    }
+    assert_eq!(path_handles(&graph, p1), vec![H2, H5, H1]);
+    assert_eq!(steps_on(&graph, H1), vec![step(p1, 2), step(p2, 0)]);
+
+    graph.remove_path(p2);
+    assert_eq!(EmbeddedPaths::path_count(&graph), 1);
+    assert!(EmbeddedPaths::get_path(&graph, p2).is_none());
+    assert!(graph.lookup_path_id(b"path2").is_none());
+    assert_eq!(steps_on(&graph, H1), vec![step(p1, 2)]);
+    assert!(steps_on(&graph, H3).is_empty());
+}
+
+#[test]
+fn parallel_iterators() {
+    use handlegraph::pathhandlegraph::AllPathIdsPar;
+    use rayon::prelude::*;
+
+    let mut graph = path_graph();
+    graph.create_edge(Edge(H2, H4.flip()));
+    graph.create_edge(Edge(H6.flip(), H3));
+    graph.create_edge(Edge(H5, H5));
+
+    for name in [b"path1", b"path2", b"path3"].iter() {
+        let path = graph.create_path_handle(&name[..], false);
+        graph.append_step(&path, H1);
+    }
+
+    let mut handles = graph.all_handles().collect::<Vec<_>>();
+    let mut handles_par = graph.all_handles_par().collect::<Vec<_>>();
+    handles.sort();
+    handles_par.sort();
+    assert_eq!(handles, handles_par);
+
+    let mut edges = graph.all_edges().collect::<Vec<_>>();
+    let mut edges_par = graph.all_edges_par().collect::<Vec<_>>();
+    edges.sort();
+    edges_par.sort();
+    assert_eq!(edges.len(), 9);
+    assert_eq!(edges, edges_par);
+
+    let mut path_ids = graph.paths.keys().copied().collect::<Vec<_>>();
+    let mut path_ids_par = graph.all_path_ids_par().collect::<Vec<_>>();
+    path_ids.sort();
+    path_ids_par.sort();
+    assert_eq!(path_ids.len(), 3);
+    assert_eq!(path_ids, path_ids_par);
+}
+
+#[test]
+fn step_updates_between_graphs() {
+    use handlegraph::{
+        packedgraph::{paths::StepUpdate, PackedGraph},
+        pathhandlegraph::{EmbeddedMutablePath, MutEmbeddedPaths, PathRefMut},
+    };
+
+    // Written against the step updates of `PackedGraph`
+    fn inserted_handles(updates: &[StepUpdate]) -> Vec<Handle> {
+        updates
+            .iter()
+            .filter_map(|update| match update {
+                StepUpdate::Insert { handle, .. } => Some(*handle),
+                StepUpdate::Remove { .. } => None,
+            })
+            .collect()
+    }
+
+    let mut hash_graph = path_graph();
+    let mut packed_graph = PackedGraph::new();
+    for id in 1..=6u64 {
+        packed_graph.create_handle(id.to_string().as_bytes(), id);
+    }
+
+    let hash_path = hash_graph.create_path(b"path", false);
+    let packed_path = packed_graph.create_path(b"path", false);
+
+    let steps = [H1, H3, H4.flip(), H6];
+
+    let hash_updates: Vec<StepUpdate> = {
+        let mut path = hash_graph.get_path_mut(hash_path).unwrap();
+        steps.iter().map(|&h| path.append_step(h)).collect()
+    };
+    let packed_updates: Vec<StepUpdate> = {
+        let mut path = packed_graph.get_path_mut(packed_path).unwrap();
+        steps.iter().map(|&h| path.append_step(h)).collect()
+    };
+
+    assert_eq!(hash_updates, packed_updates);
+    assert_eq!(inserted_handles(&hash_updates), steps.to_vec());
+
+    // The step indices of the updates from one graph can be used to
+    // modify the other
+    let step = match packed_updates[1] {
+        StepUpdate::Insert { step, .. } => step,
+        StepUpdate::Remove { step, .. } => step,
+    };
+    let removed = hash_graph
+        .get_path_mut(hash_path)
+        .unwrap()
+        .remove_step(step);
+    assert_eq!(removed, Some(StepUpdate::Remove { handle: H3, step }));
+    assert_eq!(hash_graph.paths[&hash_path].nodes, vec![H1, H4.flip(), H6]);
+}
+
+#[test]
+fn empty_path_ends_panic_in_both_graphs() {
+    use handlegraph::{
+        packedgraph::PackedGraph,
+        pathhandlegraph::{EmbeddedPaths, MutEmbeddedPaths, PathRef},
+    };
+    use std::panic::{catch_unwind, AssertUnwindSafe};
+
+    let mut hash_graph = path_graph();
+    let mut packed_graph = PackedGraph::new();
+    let hash_path = hash_graph.create_path(b"empty", true);
+    let packed_path = packed_graph.create_path(b"empty", true);
+
+    let hash_ref = EmbeddedPaths::get_path(&hash_graph, hash_path).unwrap();
+    let packed_ref =
+        EmbeddedPaths::get_path(&packed_graph, packed_path).unwrap();
+    assert!(hash_ref.is_empty());
+    assert!(packed_ref.is_empty());
+
+    let panics = |f: &dyn Fn()| catch_unwind(AssertUnwindSafe(f)).is_err();
+    assert!(panics(&|| {
+        hash_ref.first_step();
+    }));
+    assert!(panics(&|| {
+        hash_ref.last_step();
+    }));
+    assert!(panics(&|| {
+        packed_ref.first_step();
+    }));
+    assert!(panics(&|| {
+        packed_ref.last_step();
+    }));
+}